@@ -1,11 +1,17 @@
 // From reth_rpc_layer
 use alloy_rpc_types_engine::{Claims, JwtSecret};
-use http::{header::AUTHORIZATION, HeaderValue};
+use futures::future::{ready, Either, Ready};
+use http::{header::AUTHORIZATION, HeaderValue, StatusCode};
+use jsonrpsee::http_client::{HttpBody, HttpRequest, HttpResponse};
 use std::{
     task::{Context, Poll},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tower::{Layer, Service};
+use tracing::debug;
+
+/// Path of the health check, served without authentication.
+const HEALTH_CHECK_PATH: &str = "/healthz";
 
 /// A layer that adds a new JWT token to every request using `AuthClientService`.
 #[derive(Debug)]
@@ -79,3 +85,169 @@ pub fn secret_to_bearer_header(secret: &JwtSecret) -> HeaderValue {
     .parse()
     .unwrap()
 }
+
+/// A layer that validates the JWT of every inbound request using `AuthService`.
+#[derive(Debug, Clone)]
+pub struct AuthLayer {
+    secret: JwtSecret,
+}
+
+impl AuthLayer {
+    /// Create a new `AuthLayer` with the given `secret`.
+    pub const fn new(secret: JwtSecret) -> Self {
+        Self { secret }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService {
+            secret: self.secret,
+            inner,
+        }
+    }
+}
+
+/// Rejects the requests without a valid JWT signed with the given `secret`, except the health
+/// check, with a `401 Unauthorized` response.
+#[derive(Debug, Clone)]
+pub struct AuthService<S> {
+    secret: JwtSecret,
+    inner: S,
+}
+
+impl<S> AuthService<S> {
+    fn validate(&self, request: &HttpRequest<HttpBody>) -> Result<(), String> {
+        let header = request
+            .headers()
+            .get(AUTHORIZATION)
+            .ok_or("Missing JWT")?
+            .to_str()
+            .map_err(|_| "Invalid JWT header")?;
+        let token = header.strip_prefix("Bearer ").ok_or("Missing Bearer JWT")?;
+        self.secret.validate(token).map_err(|e| e.to_string())
+    }
+}
+
+impl<S> Service<HttpRequest<HttpBody>> for AuthService<S>
+where
+    S: Service<HttpRequest<HttpBody>, Response = HttpResponse>,
+{
+    type Response = HttpResponse;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<HttpResponse, S::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest<HttpBody>) -> Self::Future {
+        if request.uri().path() == HEALTH_CHECK_PATH {
+            return Either::Left(self.inner.call(request));
+        }
+
+        match self.validate(&request) {
+            Ok(()) => Either::Left(self.inner.call(request)),
+            Err(e) => {
+                debug!(message = "rejected unauthenticated request", "error" = %e);
+                let mut response = HttpResponse::new(HttpBody::from(e));
+                *response.status_mut() = StatusCode::UNAUTHORIZED;
+                Either::Right(ready(Ok(response)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ExecutionClient;
+    use crate::proxy::ProxyLayer;
+    use crate::server::PayloadSource;
+    use http::Uri;
+    use http_body_util::BodyExt;
+    use hyper_util::client::legacy::Client;
+    use hyper_util::rt::TokioExecutor;
+    use jsonrpsee::core::client::{ClientT, Error as ClientError};
+    use jsonrpsee::http_client::transport::Error as TransportError;
+    use jsonrpsee::rpc_params;
+    use jsonrpsee::server::Server;
+    use jsonrpsee::RpcModule;
+
+    #[tokio::test]
+    async fn test_auth_layer() -> eyre::Result<()> {
+        let secret = JwtSecret::random();
+        let l2_secret = JwtSecret::random();
+        let l2_uri = "http://127.0.0.1:1".parse::<Uri>()?;
+        let server = Server::builder()
+            .set_http_middleware(
+                tower::ServiceBuilder::new()
+                    .layer(AuthLayer::new(secret))
+                    .layer(ProxyLayer::new(
+                        l2_uri.clone(),
+                        l2_secret,
+                        vec![],
+                        Default::default(),
+                        Default::default(),
+                        None,
+                    )),
+            )
+            .build("127.0.0.1:0")
+            .await?;
+        let addr = server.local_addr()?;
+        let mut module = RpcModule::new(());
+        module.register_method("engine_method", |_, _, _| "engine response")?;
+        let handle = server.start(module);
+        let uri = format!("http://{addr}").parse::<Uri>()?;
+
+        // a request signed with the secret is served
+        let client = ExecutionClient::new(uri.clone(), secret, 1000, None, PayloadSource::L2)?;
+        let response: String = client
+            .auth_client
+            .request("engine_method", rpc_params![])
+            .await?;
+        assert_eq!(response, "engine response");
+
+        // a request signed with another secret is rejected
+        let client = ExecutionClient::new(uri, l2_secret, 1000, None, PayloadSource::L2)?;
+        let response = client
+            .auth_client
+            .request::<String, _>("engine_method", rpc_params![])
+            .await;
+        assert!(matches!(
+            response.unwrap_err(),
+            ClientError::Transport(e)
+                if matches!(e.downcast_ref::<TransportError>(), Some(TransportError::Rejected { status_code: 401 }))
+        ));
+
+        // a WebSocket connection is authenticated when it is opened
+        let ws_uri = format!("ws://{addr}").parse::<Uri>()?;
+        let client = ExecutionClient::new(ws_uri.clone(), secret, 1000, None, PayloadSource::L2)?;
+        let response: String = client
+            .auth_client
+            .request("engine_method", rpc_params![])
+            .await?;
+        assert_eq!(response, "engine response");
+
+        let client = ExecutionClient::new(ws_uri, l2_secret, 1000, None, PayloadSource::L2)?;
+        let response = client
+            .auth_client
+            .request::<String, _>("engine_method", rpc_params![])
+            .await;
+        assert!(matches!(response.unwrap_err(), ClientError::Transport(_)));
+
+        // the health check does not need a JWT
+        let client: Client<_, HttpBody> = Client::builder(TokioExecutor::new()).build_http();
+        let response = client
+            .get(format!("http://{addr}/healthz").parse::<Uri>()?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        assert_eq!(body, "OK");
+
+        handle.stop()?;
+        Ok(())
+    }
+}
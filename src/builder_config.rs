@@ -0,0 +1,124 @@
+use crate::client::BuilderArgs;
+use alloy_rpc_types_engine::JwtSecret;
+use eyre::{bail, eyre};
+use http::Uri;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Connection settings of a block builder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuilderConfig {
+    /// Auth server address, over WebSocket for a `ws://` or `wss://` url. An absolute path is
+    /// the path of an IPC socket
+    pub url: Uri,
+    /// JWT secret of the authenticated engine-API RPC server
+    pub jwt_secret: JwtSecret,
+    /// Timeout for RPC calls in milliseconds
+    pub timeout: u64,
+}
+
+/// Entry of the builders config file, see [`BuilderConfig::from_file`].
+#[derive(Deserialize, Debug)]
+struct BuilderConfigEntry {
+    url: String,
+    #[serde(default)]
+    jwt_token: Option<String>,
+    #[serde(default)]
+    jwt_path: Option<PathBuf>,
+    #[serde(default = "default_timeout")]
+    timeout: u64,
+}
+
+fn default_timeout() -> u64 {
+    1000
+}
+
+impl BuilderConfig {
+    /// Loads the builders from a JSON file containing a list of
+    /// `{ "url", "jwt_token" | "jwt_path", "timeout" }` objects.
+    ///
+    /// The order of the list is the priority of the builders, the first one being the highest.
+    pub fn from_file(path: &Path) -> eyre::Result<Vec<Self>> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_json(&contents)
+    }
+
+    fn from_json(contents: &str) -> eyre::Result<Vec<Self>> {
+        let entries: Vec<BuilderConfigEntry> = serde_json::from_str(contents)?;
+        if entries.is_empty() {
+            bail!("Builders config does not contain any builder");
+        }
+
+        entries
+            .into_iter()
+            .map(|entry| {
+                let jwt_secret = if let Some(token) = entry.jwt_token.as_ref() {
+                    JwtSecret::from_hex(token)?
+                } else if let Some(path) = entry.jwt_path.as_ref() {
+                    JwtSecret::from_file(path)?
+                } else {
+                    bail!("Missing JWT secret for builder {}", entry.url);
+                };
+                Ok(Self {
+                    url: entry
+                        .url
+                        .parse()
+                        .map_err(|e| eyre!("Invalid builder url {}: {}", entry.url, e))?,
+                    jwt_secret,
+                    timeout: entry.timeout,
+                })
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<BuilderArgs> for BuilderConfig {
+    type Error = eyre::Report;
+
+    fn try_from(args: BuilderArgs) -> eyre::Result<Self> {
+        let jwt_secret = if let Some(secret) = args.builder_jwt_token {
+            secret
+        } else if let Some(path) = args.builder_jwt_path.as_ref() {
+            JwtSecret::from_file(path)?
+        } else {
+            bail!("Missing Builder JWT secret");
+        };
+
+        Ok(Self {
+            url: args.builder_url,
+            jwt_secret,
+            timeout: args.builder_timeout,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "688f5d737bad920bdfb2fc2f488d6b6209eebda1dae949a8de91398d932c517a";
+
+    #[test]
+    fn test_parse_builders_config() {
+        let builders = BuilderConfig::from_json(&format!(
+            r#"[
+                {{ "url": "http://builder-a:8551", "jwt_token": "{SECRET}", "timeout": 500 }},
+                {{ "url": "http://builder-b:8551", "jwt_token": "{SECRET}" }}
+            ]"#
+        ))
+        .unwrap();
+
+        assert_eq!(builders.len(), 2);
+        assert_eq!(builders[0].url, Uri::from_static("http://builder-a:8551"));
+        assert_eq!(builders[0].timeout, 500);
+        assert_eq!(builders[1].url, Uri::from_static("http://builder-b:8551"));
+        assert_eq!(builders[1].timeout, 1000);
+        assert_eq!(builders[1].jwt_secret, JwtSecret::from_hex(SECRET).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_builders_config() {
+        assert!(BuilderConfig::from_json("[]").is_err());
+        assert!(BuilderConfig::from_json(r#"[{ "url": "http://builder-a:8551" }]"#).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::metrics::ServerMetrics;
+use crate::state::StateNotifier;
+use clap::{arg, Parser};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use tracing::info;
+
+/// Settings of the canary rollout of the builder blocks.
+#[derive(Parser, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanaryArgs {
+    /// Percentage of the blocks using the builder payload in enabled mode, chosen
+    /// deterministically by block number. The other blocks behave like dry-run
+    #[arg(long, env, default_value_t = 100, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub canary_percentage: u8,
+}
+
+impl Default for CanaryArgs {
+    fn default() -> Self {
+        Self {
+            canary_percentage: 100,
+        }
+    }
+}
+
+/// Canary rollout of a builder, serving the builder payload for a share of the blocks only.
+///
+/// The blocks are spread evenly by block number, every window of 100 consecutive blocks holds
+/// exactly `percentage` builder blocks, so all the instances agree on the canary blocks.
+#[derive(Debug)]
+pub struct Canary {
+    percentage: AtomicU8,
+    metrics: Option<Arc<ServerMetrics>>,
+    state_notifier: StateNotifier,
+}
+
+impl Canary {
+    pub fn new(
+        args: CanaryArgs,
+        metrics: Option<Arc<ServerMetrics>>,
+        state_notifier: StateNotifier,
+    ) -> Self {
+        if let Some(metrics) = &metrics {
+            metrics.set_canary_percentage(args.canary_percentage);
+        }
+        Self {
+            percentage: AtomicU8::new(args.canary_percentage),
+            metrics,
+            state_notifier,
+        }
+    }
+
+    pub fn percentage(&self) -> u8 {
+        self.percentage.load(Ordering::SeqCst)
+    }
+
+    pub fn set_percentage(&self, percentage: u8) {
+        self.percentage.store(percentage, Ordering::SeqCst);
+        if let Some(metrics) = &self.metrics {
+            metrics.set_canary_percentage(percentage);
+        }
+        self.state_notifier.notify();
+        info!(message = "set canary percentage", "percentage" = percentage);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.percentage() < 100
+    }
+
+    /// Whether the block uses the builder payload. A block with an unknown number behaves like
+    /// dry-run, as the rollout cannot tell whether it is a canary block.
+    pub fn is_builder_block(&self, block_number: Option<u64>) -> bool {
+        let builder_block = block_number
+            .is_some_and(|block_number| is_canary_block(block_number, self.percentage()));
+        if let Some(metrics) = &self.metrics {
+            metrics.increment_canary_blocks(builder_block);
+        }
+        builder_block
+    }
+}
+
+fn is_canary_block(block_number: u64, percentage: u8) -> bool {
+    let block_number = block_number as u128;
+    let percentage = percentage as u128;
+    (block_number + 1) * percentage / 100 > block_number * percentage / 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_canary_block() {
+        for percentage in [0, 1, 10, 33, 50, 99, 100] {
+            for start in [0, 57, 1_000_000] {
+                let canary_blocks = (start..start + 100)
+                    .filter(|block_number| is_canary_block(*block_number, percentage))
+                    .count();
+                assert_eq!(canary_blocks, percentage as usize);
+            }
+        }
+        // the canary blocks are spread over the window
+        let canary_blocks: Vec<u64> = (0..10).filter(|n| is_canary_block(*n, 20)).collect();
+        assert_eq!(canary_blocks, vec![4, 9]);
+        assert!(is_canary_block(u64::MAX, 100));
+    }
+
+    #[test]
+    fn test_canary() {
+        let canary = Canary::new(CanaryArgs::default(), None, StateNotifier::default());
+        assert!(!canary.is_enabled());
+        assert!(canary.is_builder_block(Some(1)));
+
+        canary.set_percentage(0);
+        assert!(canary.is_enabled());
+        assert!(!canary.is_builder_block(Some(1)));
+
+        canary.set_percentage(50);
+        assert!(canary.is_builder_block(Some(1)));
+        assert!(!canary.is_builder_block(Some(2)));
+        assert!(!canary.is_builder_block(None));
+    }
+}
@@ -0,0 +1,332 @@
+use crate::client::ExecutionClient;
+use crate::metrics::ServerMetrics;
+use crate::payload::{NewPayload, NewPayloadV3, NewPayloadV4, PayloadVersion};
+use crate::rollup_config::RollupConfig;
+use crate::sync_queue::SyncQueue;
+use crate::sync_status::SyncTracker;
+use alloy_eips::eip2718::Encodable2718;
+use alloy_eips::BlockNumberOrTag;
+use alloy_primitives::{Bytes, B256, U256};
+use alloy_rpc_types_engine::{
+    ExecutionPayloadInputV2, ExecutionPayloadV1, ExecutionPayloadV2, ExecutionPayloadV3,
+    ForkchoiceState,
+};
+use alloy_rpc_types_eth::{Block, BlockTransactions};
+use clap::{arg, Parser};
+use eyre::{bail, eyre};
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::proc_macros::rpc;
+use op_alloy_rpc_types::Transaction;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+/// Settings of the builder catch-up sync.
+#[derive(Parser, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CatchUpArgs {
+    /// Maximum number of missing blocks replayed to a builder answering `SYNCING`, 0 disables the
+    /// catch-up sync
+    #[arg(long, env, default_value_t = 256)]
+    pub builder_catch_up_max_blocks: u64,
+}
+
+impl Default for CatchUpArgs {
+    fn default() -> Self {
+        Self {
+            builder_catch_up_max_blocks: 256,
+        }
+    }
+}
+
+#[rpc(client, namespace = "eth")]
+pub trait EthApi {
+    #[method(name = "getBlockByNumber")]
+    async fn get_block_by_number(
+        &self,
+        block_number: BlockNumberOrTag,
+        include_txs: bool,
+    ) -> RpcResult<Option<Block<Transaction>>>;
+
+    #[method(name = "getBlockByHash")]
+    async fn get_block_by_hash(
+        &self,
+        block_hash: B256,
+        include_txs: bool,
+    ) -> RpcResult<Option<Block<Transaction>>>;
+}
+
+/// Replays the blocks a builder missed, fetched from the L2 execution engine.
+///
+/// The gap is measured between the head of the builder and the canonical head of the last
+/// `engine_forkchoiceUpdated` call. The missing blocks are sent through the builder sync queue
+/// with `engine_newPayload`, followed by an `engine_forkchoiceUpdated` to the canonical head.
+#[derive(Clone)]
+pub struct BuilderCatchUp {
+    pub l2_client: ExecutionClient,
+    pub builder_client: ExecutionClient,
+    pub sync_queue: Arc<SyncQueue>,
+    pub fork_choice_state: Arc<Mutex<Option<ForkchoiceState>>>,
+    pub running: Arc<AtomicBool>,
+    pub sync_tracker: Arc<SyncTracker>,
+    pub rollup_config: Option<Arc<RollupConfig>>,
+    pub max_blocks: u64,
+    pub metrics: Option<Arc<ServerMetrics>>,
+}
+
+impl BuilderCatchUp {
+    /// Starts the catch-up sync in the background, unless the builder is already catching up.
+    pub fn trigger(self) {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        tokio::spawn(async move {
+            let builder = self.builder_client.auth_rpc.to_string();
+            info!(message = "builder is syncing, starting catch-up sync", "url" = %builder);
+            let result = self.run().await;
+            match &result {
+                Ok(blocks) => {
+                    info!(message = "builder catch-up sync completed", "url" = %builder, "blocks" = blocks);
+                }
+                Err(e) => {
+                    error!(message = "builder catch-up sync failed", "url" = %builder, "error" = %e);
+                }
+            }
+            if let Some(metrics) = &self.metrics {
+                metrics.increment_builder_catch_up(builder, result.is_ok());
+            }
+            self.running.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// Replays the missing blocks, returns the number of replayed blocks.
+    async fn run(&self) -> eyre::Result<u64> {
+        let Some(fork_choice_state) = *self.fork_choice_state.lock().await else {
+            bail!("no canonical head received yet");
+        };
+
+        let head = EthApiClient::get_block_by_hash(
+            self.l2_client.auth_client.as_ref(),
+            fork_choice_state.head_block_hash,
+            false,
+        )
+        .await?
+        .ok_or_else(|| eyre!("canonical head not found on the l2 client"))?;
+        let builder_head = EthApiClient::get_block_by_number(
+            self.builder_client.auth_client.as_ref(),
+            BlockNumberOrTag::Latest,
+            false,
+        )
+        .await?
+        .ok_or_else(|| eyre!("latest block not found on the builder"))?;
+
+        let head_number = head.header.number;
+        let builder_head_number = builder_head.header.number;
+        if builder_head_number >= head_number {
+            return Ok(0);
+        }
+        let gap = head_number - builder_head_number;
+        if gap > self.max_blocks {
+            bail!(
+                "builder is {} blocks behind, more than the maximum of {}",
+                gap,
+                self.max_blocks
+            );
+        }
+        info!(message = "replaying missing blocks to builder", "url" = ?self.builder_client.auth_rpc, "from" = builder_head_number + 1, "to" = head_number);
+
+        for number in builder_head_number + 1..=head_number {
+            let new_payload = self.fetch_block(number).await?;
+            let response = self
+                .sync_queue
+                .new_payload(new_payload)
+                .ok_or_else(|| eyre!("builder sync queue is full"))?;
+            let status = response.await??;
+            self.sync_tracker.record(&status.status, false);
+            if status.is_invalid() {
+                bail!("builder rejected block {}: {}", number, status.status);
+            }
+            if let Some(metrics) = &self.metrics {
+                metrics.increment_builder_catch_up_blocks(self.builder_client.auth_rpc.to_string());
+            }
+        }
+
+        let version = self.payload_version(&head);
+        let response = self
+            .sync_queue
+            .fork_choice_updated(fork_choice_state, None, version)
+            .ok_or_else(|| eyre!("builder sync queue is full"))?;
+        let fcu = response.await??;
+        self.sync_tracker.record(&fcu.payload_status.status, true);
+        if fcu.is_invalid() {
+            bail!(
+                "builder rejected fork_choice_updated: {}",
+                fcu.payload_status.status
+            );
+        }
+        Ok(gap)
+    }
+
+    /// Fetches a block with its transactions from the L2 execution engine.
+    async fn fetch_block(&self, number: u64) -> eyre::Result<NewPayload> {
+        let block = EthApiClient::get_block_by_number(
+            self.l2_client.auth_client.as_ref(),
+            BlockNumberOrTag::Number(number),
+            true,
+        )
+        .await?
+        .ok_or_else(|| eyre!("block {} not found on the l2 client", number))?;
+
+        let BlockTransactions::Full(transactions) = &block.transactions else {
+            bail!("block {} returned without its transactions", number);
+        };
+        let transactions = transactions
+            .iter()
+            .map(|transaction| transaction.inner.inner.encoded_2718().into())
+            .collect();
+
+        Ok(new_payload_from_block(
+            &block,
+            transactions,
+            self.payload_version(&block),
+        ))
+    }
+
+    /// Version of the engine API methods for a block, from the rollup config if set or the
+    /// block header otherwise.
+    fn payload_version(&self, block: &Block<Transaction>) -> PayloadVersion {
+        match &self.rollup_config {
+            Some(rollup_config) => rollup_config.payload_version(block.header.timestamp),
+            None if block.header.parent_beacon_block_root.is_some() => PayloadVersion::V3,
+            None => PayloadVersion::V2,
+        }
+    }
+}
+
+/// Builds the `engine_newPayload` call of a block returned by `eth_getBlockByNumber`.
+fn new_payload_from_block(
+    block: &Block<Transaction>,
+    transactions: Vec<Bytes>,
+    version: PayloadVersion,
+) -> NewPayload {
+    let header = &block.header;
+    let payload_inner = ExecutionPayloadV1 {
+        parent_hash: header.parent_hash,
+        fee_recipient: header.beneficiary,
+        state_root: header.state_root,
+        receipts_root: header.receipts_root,
+        logs_bloom: header.logs_bloom,
+        prev_randao: header.mix_hash,
+        block_number: header.number,
+        gas_limit: header.gas_limit,
+        gas_used: header.gas_used,
+        timestamp: header.timestamp,
+        extra_data: header.extra_data.clone(),
+        base_fee_per_gas: U256::from(header.base_fee_per_gas.unwrap_or_default()),
+        block_hash: header.hash,
+        transactions,
+    };
+    let withdrawals = block
+        .withdrawals
+        .as_ref()
+        .map(|withdrawals| withdrawals.to_vec());
+    if version == PayloadVersion::V2 {
+        return NewPayload::V2(ExecutionPayloadInputV2 {
+            execution_payload: payload_inner,
+            withdrawals,
+        });
+    }
+
+    let execution_payload = ExecutionPayloadV3 {
+        payload_inner: ExecutionPayloadV2 {
+            payload_inner,
+            withdrawals: withdrawals.unwrap_or_default(),
+        },
+        blob_gas_used: header.blob_gas_used.unwrap_or_default(),
+        excess_blob_gas: header.excess_blob_gas.unwrap_or_default(),
+    };
+    let parent_beacon_block_root = header.parent_beacon_block_root.unwrap_or_default();
+    if version == PayloadVersion::V4 {
+        NewPayload::V4(NewPayloadV4 {
+            execution_payload,
+            versioned_hashes: vec![],
+            parent_beacon_block_root,
+            execution_requests: vec![],
+        })
+    } else {
+        NewPayload::V3(NewPayloadV3 {
+            execution_payload,
+            versioned_hashes: vec![],
+            parent_beacon_block_root,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+    use serde_json::json;
+
+    fn block() -> Block<Transaction> {
+        serde_json::from_value(json!({
+            "hash": "0x2f2d6aa5bb8c1e4d5b8d6c4d9b8a4b6f3b0bc0f3c9f5d3f5a3f9f0f6b7d3b2a1",
+            "parentHash": "0xe927a1448525fb5d32cb50ee1408461a945ba6c39bd5cf5621407d500ecc8de9",
+            "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+            "miner": "0x4200000000000000000000000000000000000011",
+            "stateRoot": "0x5ce2ad9e3c8ee0a7e7a7fb6e5dc0cf0c2f6b41b6e5f2b3f5fb7f3d1b1b2b3c4d",
+            "transactionsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "receiptsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "difficulty": "0x0",
+            "number": "0x10",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0xb1a0",
+            "timestamp": "0x651f35b8",
+            "extraData": "0x",
+            "mixHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+            "nonce": "0x0000000000000000",
+            "baseFeePerGas": "0x7",
+            "withdrawalsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "blobGasUsed": "0x0",
+            "excessBlobGas": "0x0",
+            "parentBeaconBlockRoot": "0x0000000000000000000000000000000000000000000000000000000000000002",
+            "totalDifficulty": "0x0",
+            "size": "0x200",
+            "uncles": [],
+            "transactions": [
+                "0x4f2c1d6c9e2a0c4b5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4"
+            ],
+            "withdrawals": []
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_new_payload_from_block() {
+        let block = block();
+        let transaction = Bytes::from(hex!("7ef8f8a0"));
+
+        let new_payload =
+            new_payload_from_block(&block, vec![transaction.clone()], PayloadVersion::V3);
+        let NewPayload::V3(new_payload_v3) = &new_payload else {
+            panic!("expected a V3 payload");
+        };
+        assert_eq!(new_payload.block_hash(), block.header.hash);
+        assert_eq!(new_payload.parent_hash(), block.header.parent_hash);
+        assert_eq!(
+            new_payload_v3.parent_beacon_block_root,
+            block.header.parent_beacon_block_root.unwrap()
+        );
+        let payload_inner = &new_payload_v3.execution_payload.payload_inner.payload_inner;
+        assert_eq!(payload_inner.block_number, 16);
+        assert_eq!(payload_inner.base_fee_per_gas, U256::from(7));
+        assert_eq!(payload_inner.prev_randao, block.header.mix_hash);
+        assert_eq!(payload_inner.transactions, vec![transaction]);
+
+        let new_payload = new_payload_from_block(&block, vec![], PayloadVersion::V2);
+        assert_eq!(new_payload.version(), PayloadVersion::V2);
+        assert_eq!(new_payload.timestamp(), 0x651f35b8);
+    }
+}
@@ -0,0 +1,353 @@
+use crate::metrics::ServerMetrics;
+use crate::server::ExecutionMode;
+use crate::state::StateNotifier;
+use clap::{arg, Parser};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Settings of the per-builder circuit breaker.
+#[derive(Parser, Debug, Clone, PartialEq, Eq)]
+pub struct CircuitBreakerArgs {
+    /// Number of consecutive builder failures within the window that trips the circuit breaker,
+    /// 0 disables the circuit breaker
+    #[arg(long, env, default_value_t = 0)]
+    pub circuit_breaker_threshold: u32,
+
+    /// Window in milliseconds in which the consecutive failures are counted
+    #[arg(long, env, default_value_t = 60000)]
+    pub circuit_breaker_window: u64,
+
+    /// Execution mode applied to the builder while the circuit breaker is open
+    #[arg(long, env, default_value = "dry-run")]
+    pub circuit_breaker_mode: ExecutionMode,
+
+    /// Time in milliseconds after which an open circuit breaker lets a probe block through
+    #[arg(long, env, default_value_t = 30000)]
+    pub circuit_breaker_probe_interval: u64,
+}
+
+impl Default for CircuitBreakerArgs {
+    fn default() -> Self {
+        Self {
+            circuit_breaker_threshold: 0,
+            circuit_breaker_window: 60000,
+            circuit_breaker_mode: ExecutionMode::DryRun,
+            circuit_breaker_probe_interval: 30000,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    // The builder is healthy and used as configured
+    Closed,
+    // The builder failed too often, the circuit breaker execution mode applies
+    Open,
+    // The probe interval elapsed, the next block is used to probe the builder
+    HalfOpen,
+}
+
+impl CircuitState {
+    fn as_gauge(&self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::HalfOpen => 1.0,
+            CircuitState::Open => 2.0,
+        }
+    }
+}
+
+impl std::fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CircuitState::Closed => write!(f, "closed"),
+            CircuitState::Open => write!(f, "open"),
+            CircuitState::HalfOpen => write!(f, "half_open"),
+        }
+    }
+}
+
+/// Health of a builder as reported by the debug API.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BuilderHealth {
+    pub builder: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub execution_mode: ExecutionMode,
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    circuit: CircuitState,
+    consecutive_failures: u32,
+    first_failure: Option<Instant>,
+    opened_at: Option<Instant>,
+}
+
+/// Health state machine of a builder.
+///
+/// After `circuit_breaker_threshold` consecutive failures within the window, the circuit opens
+/// and the builder runs in `circuit_breaker_mode`. Once the probe interval elapsed, the circuit
+/// becomes half-open and the builder is used again: a success closes it, a failure opens it again.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    builder: String,
+    args: CircuitBreakerArgs,
+    state: Mutex<BreakerState>,
+    metrics: Option<Arc<ServerMetrics>>,
+    state_notifier: StateNotifier,
+}
+
+impl CircuitBreaker {
+    pub fn new(
+        builder: String,
+        args: CircuitBreakerArgs,
+        metrics: Option<Arc<ServerMetrics>>,
+        state_notifier: StateNotifier,
+    ) -> Self {
+        Self {
+            builder,
+            args,
+            state: Mutex::new(BreakerState {
+                circuit: CircuitState::Closed,
+                consecutive_failures: 0,
+                first_failure: None,
+                opened_at: None,
+            }),
+            metrics,
+            state_notifier,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.args.circuit_breaker_threshold > 0
+    }
+
+    /// Execution mode of the builder according to its current health.
+    pub async fn execution_mode(&self) -> ExecutionMode {
+        let state = self.state.lock().await;
+        self.mode_of(&state)
+    }
+
+    /// Execution mode of the builder for a new block, moving an open circuit to half-open once
+    /// the probe interval elapsed so the block probes the builder.
+    pub async fn probe_execution_mode(&self) -> ExecutionMode {
+        let mut state = self.state.lock().await;
+        let probe_interval = Duration::from_millis(self.args.circuit_breaker_probe_interval);
+        if state.circuit == CircuitState::Open
+            && state
+                .opened_at
+                .is_some_and(|opened_at| opened_at.elapsed() >= probe_interval)
+        {
+            info!(message = "builder circuit breaker half-open, probing builder", "builder" = %self.builder);
+            self.set_circuit(&mut state, CircuitState::HalfOpen);
+        }
+        self.mode_of(&state)
+    }
+
+    pub async fn record_success(&self) {
+        let mut state = self.state.lock().await;
+        if state.circuit != CircuitState::Closed {
+            info!(message = "builder circuit breaker closed", "builder" = %self.builder);
+        }
+        self.close(&mut state);
+    }
+
+    pub async fn record_failure(&self) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut state = self.state.lock().await;
+        match state.circuit {
+            CircuitState::HalfOpen => {
+                warn!(message = "builder probe failed, circuit breaker open", "builder" = %self.builder);
+                self.open(&mut state);
+            }
+            CircuitState::Open => {}
+            CircuitState::Closed => {
+                let window = Duration::from_millis(self.args.circuit_breaker_window);
+                match state.first_failure {
+                    Some(first_failure) if first_failure.elapsed() <= window => {
+                        state.consecutive_failures += 1;
+                    }
+                    _ => {
+                        state.first_failure = Some(Instant::now());
+                        state.consecutive_failures = 1;
+                    }
+                }
+
+                if state.consecutive_failures >= self.args.circuit_breaker_threshold {
+                    warn!(
+                        message = "builder circuit breaker open",
+                        "builder" = %self.builder,
+                        "consecutive_failures" = state.consecutive_failures,
+                        "execution_mode" = ?self.args.circuit_breaker_mode
+                    );
+                    self.open(&mut state);
+                    if let Some(metrics) = &self.metrics {
+                        metrics.increment_circuit_breaker_trips(self.builder.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Closes the circuit, used when the execution mode is set manually.
+    pub async fn reset(&self) {
+        let mut state = self.state.lock().await;
+        self.close(&mut state);
+    }
+
+    pub async fn state(&self) -> CircuitState {
+        self.state.lock().await.circuit
+    }
+
+    /// Restores a persisted state, an open or half-open circuit is opened again so the builder
+    /// is only probed after a full probe interval.
+    pub async fn restore(&self, circuit: CircuitState) {
+        if !self.is_enabled() || circuit == CircuitState::Closed {
+            return;
+        }
+        let mut state = self.state.lock().await;
+        warn!(message = "restored open builder circuit breaker", "builder" = %self.builder);
+        self.open(&mut state);
+    }
+
+    pub async fn health(&self) -> BuilderHealth {
+        let state = self.state.lock().await;
+        BuilderHealth {
+            builder: self.builder.clone(),
+            state: state.circuit,
+            consecutive_failures: state.consecutive_failures,
+            execution_mode: self.mode_of(&state),
+        }
+    }
+
+    fn mode_of(&self, state: &BreakerState) -> ExecutionMode {
+        if state.circuit == CircuitState::Open {
+            self.args.circuit_breaker_mode.clone()
+        } else {
+            ExecutionMode::Enabled
+        }
+    }
+
+    fn open(&self, state: &mut BreakerState) {
+        state.opened_at = Some(Instant::now());
+        self.set_circuit(state, CircuitState::Open);
+    }
+
+    fn close(&self, state: &mut BreakerState) {
+        state.consecutive_failures = 0;
+        state.first_failure = None;
+        state.opened_at = None;
+        self.set_circuit(state, CircuitState::Closed);
+    }
+
+    fn set_circuit(&self, state: &mut BreakerState, circuit: CircuitState) {
+        if state.circuit != circuit {
+            self.state_notifier.notify();
+        }
+        state.circuit = circuit;
+        if let Some(metrics) = &self.metrics {
+            metrics.record_circuit_breaker_state(self.builder.clone(), circuit.as_gauge());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit_breaker(probe_interval: u64) -> CircuitBreaker {
+        CircuitBreaker::new(
+            "builder".to_string(),
+            CircuitBreakerArgs {
+                circuit_breaker_threshold: 2,
+                circuit_breaker_probe_interval: probe_interval,
+                ..Default::default()
+            },
+            None,
+            StateNotifier::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_trip_after_consecutive_failures() {
+        let breaker = circuit_breaker(60000);
+
+        breaker.record_failure().await;
+        breaker.record_success().await;
+        breaker.record_failure().await;
+        assert_eq!(breaker.execution_mode().await, ExecutionMode::Enabled);
+
+        breaker.record_failure().await;
+        assert_eq!(breaker.execution_mode().await, ExecutionMode::DryRun);
+        assert_eq!(breaker.health().await.state, CircuitState::Open);
+
+        breaker.reset().await;
+        assert_eq!(breaker.execution_mode().await, ExecutionMode::Enabled);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe() {
+        let breaker = circuit_breaker(0);
+        breaker.record_failure().await;
+        breaker.record_failure().await;
+
+        // reading the health does not probe the builder
+        assert_eq!(breaker.health().await.state, CircuitState::Open);
+        assert_eq!(breaker.execution_mode().await, ExecutionMode::DryRun);
+
+        // the probe interval elapsed, the next block probes the builder
+        assert_eq!(breaker.probe_execution_mode().await, ExecutionMode::Enabled);
+        assert_eq!(breaker.health().await.state, CircuitState::HalfOpen);
+
+        // a failed probe opens the circuit again
+        breaker.record_failure().await;
+        assert_eq!(breaker.state.lock().await.circuit, CircuitState::Open);
+
+        breaker.probe_execution_mode().await;
+        breaker.record_success().await;
+        assert_eq!(breaker.health().await.state, CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_restore() {
+        let breaker = circuit_breaker(60000);
+        breaker.restore(CircuitState::Closed).await;
+        assert_eq!(breaker.state().await, CircuitState::Closed);
+
+        breaker.restore(CircuitState::HalfOpen).await;
+        assert_eq!(breaker.state().await, CircuitState::Open);
+        assert_eq!(breaker.execution_mode().await, ExecutionMode::DryRun);
+
+        // a disabled circuit breaker stays closed
+        let breaker = CircuitBreaker::new(
+            "builder".to_string(),
+            Default::default(),
+            None,
+            StateNotifier::default(),
+        );
+        breaker.restore(CircuitState::Open).await;
+        assert_eq!(breaker.state().await, CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_circuit_breaker() {
+        let breaker = CircuitBreaker::new(
+            "builder".to_string(),
+            Default::default(),
+            None,
+            StateNotifier::default(),
+        );
+        for _ in 0..10 {
+            breaker.record_failure().await;
+        }
+        assert_eq!(breaker.execution_mode().await, ExecutionMode::Enabled);
+    }
+}
@@ -1,18 +1,21 @@
-use crate::auth_layer::{AuthClientLayer, AuthClientService};
 use crate::metrics::ClientMetrics;
+use crate::payload::{
+    NewPayload, OpExecutionPayloadEnvelope, OpExecutionPayloadEnvelopeV2, PayloadVersion,
+};
 use crate::server::{EngineApiClient, PayloadSource};
-use alloy_primitives::B256;
+use crate::transport::RpcClient;
+use alloy_primitives::{Bytes, B256};
 use alloy_rpc_types_engine::{
-    ExecutionPayload, ExecutionPayloadV3, ForkchoiceState, ForkchoiceUpdated, JwtError, JwtSecret,
-    PayloadId, PayloadStatus,
+    ExecutionPayload, ExecutionPayloadInputV2, ExecutionPayloadV3, ForkchoiceState,
+    ForkchoiceUpdated, JwtError, JwtSecret, PayloadId, PayloadStatus,
 };
 use clap::{arg, Parser};
 use http::{StatusCode, Uri};
 use jsonrpsee::core::{ClientError, RpcResult};
-use jsonrpsee::http_client::transport::HttpBackend;
-use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
 use jsonrpsee::types::ErrorCode;
-use op_alloy_rpc_types_engine::{OpExecutionPayloadEnvelopeV3, OpPayloadAttributes};
+use op_alloy_rpc_types_engine::{
+    OpExecutionPayloadEnvelopeV3, OpExecutionPayloadEnvelopeV4, OpPayloadAttributes,
+};
 use paste::paste;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -33,11 +36,13 @@ pub enum ExecutionClientError {
 /// Client interface for interacting with execution layer node's Engine API.
 ///
 /// - **Engine API** calls are faciliated via the `auth_client` (requires JWT authentication).
+/// - The client connects over WebSocket for `ws://` and `wss://` urls, over IPC for a socket path,
+///   and over HTTP otherwise.
 ///
 #[derive(Clone)]
 pub struct ExecutionClient {
     /// Handles requests to the authenticated Engine API (requires JWT authentication)
-    pub auth_client: Arc<HttpClient<AuthClientService<HttpBackend>>>,
+    pub auth_client: Arc<RpcClient>,
     /// Uri of the RPC server for authenticated Engine API calls
     pub auth_rpc: Uri,
     /// Metrics for the client
@@ -55,11 +60,11 @@ impl ExecutionClient {
         metrics: Option<Arc<ClientMetrics>>,
         payload_source: PayloadSource,
     ) -> Result<Self, ExecutionClientError> {
-        let auth_layer = AuthClientLayer::new(auth_rpc_jwt_secret);
-        let auth_client = HttpClientBuilder::new()
-            .set_http_middleware(tower::ServiceBuilder::new().layer(auth_layer))
-            .request_timeout(Duration::from_millis(timeout))
-            .build(auth_rpc.to_string())?;
+        let auth_client = RpcClient::new(
+            &auth_rpc,
+            auth_rpc_jwt_secret,
+            Duration::from_millis(timeout),
+        )?;
 
         Ok(Self {
             auth_client: Arc::new(auth_client),
@@ -156,6 +161,220 @@ impl ExecutionClient {
         response
     }
 
+    pub async fn fork_choice_updated_v2(
+        &self,
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<OpPayloadAttributes>,
+    ) -> RpcResult<ForkchoiceUpdated> {
+        let start = Instant::now();
+        let response = self
+            .auth_client
+            .fork_choice_updated_v2(fork_choice_state, payload_attributes.clone())
+            .await
+            .map_err(|e| match e {
+                ClientError::Call(err) => err,
+                other_error => {
+                    error!(
+                        message = "error calling fork_choice_updated_v2",
+                        "url" = ?self.auth_rpc,
+                        "error" = %other_error,
+                        "head_block_hash" = %fork_choice_state.head_block_hash,
+                    );
+                    ErrorCode::InternalError.into()
+                }
+            });
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .record_fork_choice_updated_v2(start.elapsed(), self.get_response_code(&response));
+        }
+        response
+    }
+
+    pub async fn get_payload_v2(
+        &self,
+        payload_id: PayloadId,
+    ) -> RpcResult<(OpExecutionPayloadEnvelopeV2, PayloadSource)> {
+        let start = Instant::now();
+        let response = self
+            .auth_client
+            .get_payload_v2(payload_id)
+            .await
+            .map(|payload| (payload, self.payload_source.clone()))
+            .map_err(|e| match e {
+                ClientError::Call(err) => err,
+                other_error => {
+                    error!(
+                        message = "error calling get_payload_v2",
+                        "error" = %other_error,
+                        "payload_id" = %payload_id
+                    );
+                    ErrorCode::InternalError.into()
+                }
+            });
+        if let Some(metrics) = &self.metrics {
+            metrics.record_get_payload_v2(start.elapsed(), self.get_response_code(&response));
+        }
+        response
+    }
+
+    pub async fn new_payload_v2(
+        &self,
+        payload: ExecutionPayloadInputV2,
+    ) -> RpcResult<PayloadStatus> {
+        let block_hash = payload.execution_payload.block_hash;
+        let start = Instant::now();
+        let response = self
+            .auth_client
+            .new_payload_v2(payload)
+            .await
+            .map_err(|e| match e {
+                ClientError::Call(err) => err,
+                other_error => {
+                    error!(
+                        message = "error calling new_payload_v2",
+                        "url" = ?self.auth_rpc,
+                        "error" = %other_error,
+                        "block_hash" = %block_hash
+                    );
+                    ErrorCode::InternalError.into()
+                }
+            });
+        if let Some(metrics) = &self.metrics {
+            metrics.record_new_payload_v2(start.elapsed(), self.get_response_code(&response));
+        }
+        response
+    }
+
+    pub async fn get_payload_v4(
+        &self,
+        payload_id: PayloadId,
+    ) -> RpcResult<(OpExecutionPayloadEnvelopeV4, PayloadSource)> {
+        let start = Instant::now();
+        let response = self
+            .auth_client
+            .get_payload_v4(payload_id)
+            .await
+            .map(|payload| (payload, self.payload_source.clone()))
+            .map_err(|e| match e {
+                ClientError::Call(err) => err,
+                other_error => {
+                    error!(
+                        message = "error calling get_payload_v4",
+                        "error" = %other_error,
+                        "payload_id" = %payload_id
+                    );
+                    ErrorCode::InternalError.into()
+                }
+            });
+        if let Some(metrics) = &self.metrics {
+            metrics.record_get_payload_v4(start.elapsed(), self.get_response_code(&response));
+        }
+        response
+    }
+
+    pub async fn new_payload_v4(
+        &self,
+        payload: ExecutionPayloadV3,
+        versioned_hashes: Vec<B256>,
+        parent_beacon_block_root: B256,
+        execution_requests: Vec<Bytes>,
+    ) -> RpcResult<PayloadStatus> {
+        let execution_payload = ExecutionPayload::from(payload.clone());
+        let block_hash = execution_payload.block_hash();
+        let start = Instant::now();
+        let response = self
+            .auth_client
+            .new_payload_v4(
+                payload,
+                versioned_hashes,
+                parent_beacon_block_root,
+                execution_requests,
+            )
+            .await
+            .map_err(|e| match e {
+                ClientError::Call(err) => err,
+                other_error => {
+                    error!(
+                        message = "error calling new_payload_v4",
+                        "url" = ?self.auth_rpc,
+                        "error" = %other_error,
+                        "block_hash" = %block_hash
+                    );
+                    ErrorCode::InternalError.into()
+                }
+            });
+        if let Some(metrics) = &self.metrics {
+            metrics.record_new_payload_v4(start.elapsed(), self.get_response_code(&response));
+        }
+        response
+    }
+
+    /// Calls the `engine_forkchoiceUpdated` method matching `version`. There is no
+    /// `engine_forkchoiceUpdatedV4`, blocks using the V4 payload methods keep using V3.
+    pub async fn fork_choice_updated(
+        &self,
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<OpPayloadAttributes>,
+        version: PayloadVersion,
+    ) -> RpcResult<ForkchoiceUpdated> {
+        match version {
+            PayloadVersion::V2 => {
+                self.fork_choice_updated_v2(fork_choice_state, payload_attributes)
+                    .await
+            }
+            PayloadVersion::V3 | PayloadVersion::V4 => {
+                self.fork_choice_updated_v3(fork_choice_state, payload_attributes)
+                    .await
+            }
+        }
+    }
+
+    /// Calls the `engine_getPayload` method matching `version`.
+    pub async fn get_payload(
+        &self,
+        payload_id: PayloadId,
+        version: PayloadVersion,
+    ) -> RpcResult<(OpExecutionPayloadEnvelope, PayloadSource)> {
+        match version {
+            PayloadVersion::V2 => self
+                .get_payload_v2(payload_id)
+                .await
+                .map(|(payload, source)| (OpExecutionPayloadEnvelope::V2(payload), source)),
+            PayloadVersion::V3 => self
+                .get_payload_v3(payload_id)
+                .await
+                .map(|(payload, source)| (OpExecutionPayloadEnvelope::V3(payload), source)),
+            PayloadVersion::V4 => self
+                .get_payload_v4(payload_id)
+                .await
+                .map(|(payload, source)| (OpExecutionPayloadEnvelope::V4(payload), source)),
+        }
+    }
+
+    /// Calls the `engine_newPayload` method matching the version of `new_payload`.
+    pub async fn new_payload(&self, new_payload: NewPayload) -> RpcResult<PayloadStatus> {
+        match new_payload {
+            NewPayload::V2(new_payload) => self.new_payload_v2(new_payload).await,
+            NewPayload::V3(new_payload) => {
+                self.new_payload_v3(
+                    new_payload.execution_payload,
+                    new_payload.versioned_hashes,
+                    new_payload.parent_beacon_block_root,
+                )
+                .await
+            }
+            NewPayload::V4(new_payload) => {
+                self.new_payload_v4(
+                    new_payload.execution_payload,
+                    new_payload.versioned_hashes,
+                    new_payload.parent_beacon_block_root,
+                    new_payload.execution_requests,
+                )
+                .await
+            }
+        }
+    }
+
     fn get_response_code<T>(&self, response: &RpcResult<T>) -> String {
         match response {
             Ok(_) => StatusCode::OK.to_string(),
@@ -171,7 +390,8 @@ macro_rules! define_rpc_args {
             paste! {
                 #[derive(Parser, Debug, Clone, PartialEq, Eq)]
                 pub struct $name {
-                    /// Auth server address
+                    /// Auth server address, over WebSocket for a `ws://` or `wss://` url. An absolute
+                    /// path is the path of an IPC socket
                     #[arg(long, env, default_value = "127.0.0.1:8551")]
                     pub [<$prefix _url>]: Uri,
 
@@ -183,7 +403,7 @@ macro_rules! define_rpc_args {
                     #[arg(long, env, value_name = "PATH")]
                     pub [<$prefix _jwt_path>]: Option<PathBuf>,
 
-                    /// Timeout for http calls in milliseconds
+                    /// Timeout for RPC calls in milliseconds
                     #[arg(long, env, default_value_t = 1000)]
                     pub [<$prefix _timeout>]: u64,
                 }
@@ -0,0 +1,279 @@
+use crate::payload::OpExecutionPayloadEnvelope;
+use crate::state::StateNotifier;
+use alloy_primitives::{keccak256, B256, I256};
+use alloy_rpc_types_engine::PayloadId;
+use clap::{arg, Parser};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Settings of the compare execution mode.
+#[derive(Parser, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompareArgs {
+    /// Number of payload comparisons kept in the history of the compare execution mode
+    #[arg(long, env, default_value_t = 100)]
+    pub compare_history_size: usize,
+}
+
+impl Default for CompareArgs {
+    fn default() -> Self {
+        Self {
+            compare_history_size: 100,
+        }
+    }
+}
+
+/// Differences between the builder payload and the local payload of a block, recorded in the
+/// compare execution mode.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PayloadDiff {
+    pub payload_id: PayloadId,
+    pub block_number: Option<u64>,
+    /// Whether a valid builder payload was returned
+    pub builder_valid: bool,
+    /// Reason why no valid builder payload was returned
+    pub builder_error: Option<String>,
+    /// Builder block value minus local block value
+    pub block_value_delta: Option<I256>,
+    pub builder_gas_used: Option<u64>,
+    pub local_gas_used: Option<u64>,
+    pub builder_tx_count: Option<usize>,
+    pub local_tx_count: Option<usize>,
+    /// Hashes of the transactions only included in the builder block
+    pub builder_only_txs: Vec<B256>,
+    /// Hashes of the transactions only included in the local block
+    pub local_only_txs: Vec<B256>,
+    /// Time taken to fetch and validate the builder payload in milliseconds
+    pub builder_latency_ms: Option<u64>,
+    /// Time taken to fetch the local payload in milliseconds
+    pub local_latency_ms: Option<u64>,
+}
+
+impl PayloadDiff {
+    pub fn new<E: std::fmt::Display>(
+        payload_id: PayloadId,
+        builder: Result<&OpExecutionPayloadEnvelope, E>,
+        local: Option<&OpExecutionPayloadEnvelope>,
+        builder_latency: Option<Duration>,
+        local_latency: Option<Duration>,
+    ) -> Self {
+        let (builder, builder_error) = match builder {
+            Ok(builder) => (Some(builder), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        let (builder_only_txs, local_only_txs) = match (builder, local) {
+            (Some(builder), Some(local)) => {
+                let builder_txs = transaction_hashes(builder);
+                let local_txs = transaction_hashes(local);
+                (
+                    difference(&builder_txs, &local_txs),
+                    difference(&local_txs, &builder_txs),
+                )
+            }
+            _ => (vec![], vec![]),
+        };
+
+        Self {
+            payload_id,
+            block_number: local.or(builder).map(|payload| payload.block_number()),
+            builder_valid: builder.is_some(),
+            builder_error,
+            block_value_delta: builder.zip(local).map(|(builder, local)| {
+                I256::from_raw(builder.block_value()) - I256::from_raw(local.block_value())
+            }),
+            builder_gas_used: builder.map(|payload| payload.gas_used()),
+            local_gas_used: local.map(|payload| payload.gas_used()),
+            builder_tx_count: builder.map(|payload| payload.transaction_count()),
+            local_tx_count: local.map(|payload| payload.transaction_count()),
+            builder_only_txs,
+            local_only_txs,
+            builder_latency_ms: builder_latency.map(|latency| latency.as_millis() as u64),
+            local_latency_ms: local_latency.map(|latency| latency.as_millis() as u64),
+        }
+    }
+}
+
+/// Hashes of the transactions of a payload, in block order.
+fn transaction_hashes(payload: &OpExecutionPayloadEnvelope) -> Vec<B256> {
+    payload
+        .execution_payload()
+        .as_v1()
+        .transactions
+        .iter()
+        .map(keccak256)
+        .collect()
+}
+
+/// Transactions of `a` that are not in `b`, in the order of `a`.
+fn difference(a: &[B256], b: &[B256]) -> Vec<B256> {
+    let b: HashSet<_> = b.iter().collect();
+    a.iter().filter(|hash| !b.contains(hash)).copied().collect()
+}
+
+/// Most recent payload comparisons, the oldest ones are dropped once the history is full.
+#[derive(Debug)]
+pub struct CompareHistory {
+    capacity: usize,
+    diffs: Mutex<VecDeque<PayloadDiff>>,
+    state_notifier: StateNotifier,
+}
+
+impl CompareHistory {
+    pub fn new(args: CompareArgs, state_notifier: StateNotifier) -> Self {
+        Self {
+            capacity: args.compare_history_size,
+            diffs: Mutex::new(VecDeque::with_capacity(args.compare_history_size)),
+            state_notifier,
+        }
+    }
+
+    pub async fn push(&self, diff: PayloadDiff) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut diffs = self.diffs.lock().await;
+        if diffs.len() == self.capacity {
+            diffs.pop_front();
+        }
+        diffs.push_back(diff);
+        self.state_notifier.notify();
+    }
+
+    /// Restores persisted comparisons, given the most recent first.
+    pub async fn restore(&self, diffs: Vec<PayloadDiff>) {
+        for diff in diffs.into_iter().rev() {
+            self.push(diff).await;
+        }
+    }
+
+    /// Returns up to `limit` of the most recent comparisons, the most recent first.
+    pub async fn latest(&self, limit: Option<usize>) -> Vec<PayloadDiff> {
+        let diffs = self.diffs.lock().await;
+        diffs
+            .iter()
+            .rev()
+            .take(limit.unwrap_or(diffs.len()))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{bytes, Address, Bytes, U256};
+    use alloy_rpc_types_engine::{
+        BlobsBundleV1, ExecutionPayloadV1, ExecutionPayloadV2, ExecutionPayloadV3,
+    };
+    use op_alloy_rpc_types_engine::OpExecutionPayloadEnvelopeV3;
+
+    fn payload(
+        block_value: u64,
+        gas_used: u64,
+        transactions: Vec<Bytes>,
+    ) -> OpExecutionPayloadEnvelope {
+        OpExecutionPayloadEnvelope::V3(OpExecutionPayloadEnvelopeV3 {
+            execution_payload: ExecutionPayloadV3 {
+                payload_inner: ExecutionPayloadV2 {
+                    payload_inner: ExecutionPayloadV1 {
+                        parent_hash: B256::ZERO,
+                        fee_recipient: Address::ZERO,
+                        state_root: B256::ZERO,
+                        receipts_root: B256::ZERO,
+                        logs_bloom: Default::default(),
+                        prev_randao: B256::ZERO,
+                        block_number: 10,
+                        gas_limit: 30_000_000,
+                        gas_used,
+                        timestamp: 1000,
+                        extra_data: Bytes::new(),
+                        base_fee_per_gas: U256::from(1),
+                        block_hash: B256::ZERO,
+                        transactions,
+                    },
+                    withdrawals: vec![],
+                },
+                blob_gas_used: 0,
+                excess_blob_gas: 0,
+            },
+            block_value: U256::from(block_value),
+            blobs_bundle: BlobsBundleV1 {
+                commitments: vec![],
+                proofs: vec![],
+                blobs: vec![],
+            },
+            should_override_builder: false,
+            parent_beacon_block_root: B256::ZERO,
+        })
+    }
+
+    #[test]
+    fn test_payload_diff() {
+        let deposit = bytes!("7e01");
+        let builder = payload(5, 200, vec![deposit.clone(), bytes!("0201")]);
+        let local = payload(8, 100, vec![deposit, bytes!("0202"), bytes!("0203")]);
+
+        let diff = PayloadDiff::new::<String>(
+            PayloadId::default(),
+            Ok(&builder),
+            Some(&local),
+            Some(Duration::from_millis(120)),
+            Some(Duration::from_millis(30)),
+        );
+        assert!(diff.builder_valid);
+        assert_eq!(diff.block_number, Some(10));
+        assert_eq!(diff.block_value_delta, Some(I256::try_from(-3i64).unwrap()));
+        assert_eq!(diff.builder_gas_used, Some(200));
+        assert_eq!(diff.local_tx_count, Some(3));
+        assert_eq!(diff.builder_only_txs, vec![keccak256(bytes!("0201"))]);
+        assert_eq!(
+            diff.local_only_txs,
+            vec![keccak256(bytes!("0202")), keccak256(bytes!("0203"))]
+        );
+        assert_eq!(diff.builder_latency_ms, Some(120));
+
+        let diff = PayloadDiff::new(
+            PayloadId::default(),
+            Err("Builder payload was not valid"),
+            Some(&local),
+            None,
+            None,
+        );
+        assert!(!diff.builder_valid);
+        assert_eq!(
+            diff.builder_error.as_deref(),
+            Some("Builder payload was not valid")
+        );
+        assert_eq!(diff.block_value_delta, None);
+        assert!(diff.builder_only_txs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_compare_history() {
+        let history = CompareHistory::new(
+            CompareArgs {
+                compare_history_size: 2,
+            },
+            StateNotifier::default(),
+        );
+        for block_number in 0..3 {
+            let mut diff = PayloadDiff::new::<String>(
+                PayloadId::default(),
+                Err("no payload".to_string()),
+                None,
+                None,
+                None,
+            );
+            diff.block_number = Some(block_number);
+            history.push(diff).await;
+        }
+
+        let latest = history.latest(None).await;
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].block_number, Some(2));
+        assert_eq!(latest[1].block_number, Some(1));
+        assert_eq!(history.latest(Some(1)).await.len(), 1);
+    }
+}
@@ -0,0 +1,97 @@
+use clap::{arg, Parser};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+/// Time budget of the builder payload on `engine_getPayload`.
+#[derive(Parser, Debug, Clone, Copy, PartialEq)]
+pub struct DeadlineArgs {
+    /// Budget in milliseconds for the builder payloads, measured from the getPayload call. Once it
+    /// expires and the local payload is ready, the builders that did not return a payload yet are
+    /// abandoned
+    #[arg(long, env, value_name = "MS")]
+    pub get_payload_deadline: Option<u64>,
+
+    /// Budget for the builder payload as a fraction of the block time, measured from the
+    /// timestamp of the block attributes
+    #[arg(
+        long,
+        env,
+        value_name = "FRACTION",
+        conflicts_with = "get_payload_deadline"
+    )]
+    pub get_payload_deadline_fraction: Option<f64>,
+
+    /// Block time of the chain in milliseconds, used with `--get-payload-deadline-fraction`
+    #[arg(long, env, value_name = "MS", default_value_t = 2000)]
+    pub block_time: u64,
+}
+
+impl Default for DeadlineArgs {
+    fn default() -> Self {
+        Self {
+            get_payload_deadline: None,
+            get_payload_deadline_fraction: None,
+            block_time: 2000,
+        }
+    }
+}
+
+impl DeadlineArgs {
+    /// Deadline of the builder payload for a block, `None` if no budget applies.
+    ///
+    /// `timestamp` is the timestamp of the block attributes, in seconds.
+    pub fn deadline(&self, timestamp: Option<u64>) -> Option<Instant> {
+        if let Some(deadline) = self.get_payload_deadline {
+            return Some(Instant::now() + Duration::from_millis(deadline));
+        }
+
+        let fraction = self.get_payload_deadline_fraction?;
+        let deadline_ms = timestamp? as f64 * 1000.0 + fraction * self.block_time as f64;
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as f64;
+        // a deadline in the past expires right away
+        let remaining = (deadline_ms - now_ms).max(0.0);
+        Some(Instant::now() + Duration::from_millis(remaining as u64))
+    }
+}
+
+/// Completes once the deadline expired, never if no deadline applies.
+pub async fn sleep_until_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sleep_until_deadline() {
+        let start = Instant::now();
+        sleep_until_deadline(Some(start + Duration::from_millis(50))).await;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // without a deadline the builders are awaited until they return
+        let no_deadline =
+            tokio::time::timeout(Duration::from_millis(50), sleep_until_deadline(None)).await;
+        assert!(no_deadline.is_err());
+    }
+
+    #[test]
+    fn test_deadline() {
+        assert!(DeadlineArgs::default().deadline(Some(0)).is_none());
+
+        let args = DeadlineArgs {
+            get_payload_deadline_fraction: Some(0.5),
+            ..Default::default()
+        };
+        // no attributes timestamp known for the payload
+        assert!(args.deadline(None).is_none());
+        // a block in the past expires right away
+        assert!(args.deadline(Some(0)).unwrap() <= Instant::now());
+    }
+}
@@ -1,12 +1,24 @@
+use http::header::AUTHORIZATION;
+use http::HeaderMap;
 use jsonrpsee::core::{async_trait, RpcResult};
 use jsonrpsee::http_client::HttpClient;
 use jsonrpsee::proc_macros::rpc;
 use jsonrpsee::server::Server;
+use jsonrpsee::types::{ErrorCode, ErrorObject};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+use crate::canary::Canary;
+use crate::circuit_breaker::{BuilderHealth, CircuitBreaker};
+use crate::compare::{CompareHistory, PayloadDiff};
+use crate::debug_auth::{DebugAuth, DebugAuthLayer, DebugCredential};
+use crate::miner_settings::MinerSettings;
+use crate::routing::{RouteAction, RouteRule, RoutingTable};
+use crate::schedule::{ExecutionModeSchedule, ScheduleWindow, ScheduledExecutionMode};
 use crate::server::ExecutionMode;
+use crate::state::StateNotifier;
+use crate::sync_status::{BuilderSyncStatus, SyncTracker};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SetExecutionModeRequest {
@@ -23,6 +35,59 @@ pub struct GetExecutionModeResponse {
     pub execution_mode: ExecutionMode,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetBuilderHealthResponse {
+    pub builders: Vec<BuilderHealth>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetBuilderSyncStatusResponse {
+    pub builders: Vec<BuilderSyncStatus>,
+}
+
+pub type GetMinerSettingsResponse = MinerSettings;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetPayloadDiffsResponse {
+    pub diffs: Vec<PayloadDiff>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SetCanaryPercentageRequest {
+    pub percentage: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CanaryPercentageResponse {
+    pub percentage: u8,
+}
+
+pub type ScheduleExecutionModeRequest = ScheduleWindow;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScheduleExecutionModeResponse {
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetExecutionModeScheduleResponse {
+    pub schedule: Vec<ScheduledExecutionMode>,
+    /// Id of the window whose execution mode is applied
+    pub active: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CancelExecutionModeScheduleResponse {
+    pub cancelled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetRoutingTableResponse {
+    pub rules: Vec<RouteRule>,
+    /// Action of the methods matching no rule
+    pub default: RouteAction,
+}
+
 #[rpc(server, client, namespace = "debug")]
 trait DebugApi {
     #[method(name = "setExecutionMode")]
@@ -33,19 +98,80 @@ trait DebugApi {
 
     #[method(name = "getExecutionMode")]
     async fn get_execution_mode(&self) -> RpcResult<GetExecutionModeResponse>;
+
+    #[method(name = "getBuilderHealth")]
+    async fn get_builder_health(&self) -> RpcResult<GetBuilderHealthResponse>;
+
+    #[method(name = "getBuilderSyncStatus")]
+    async fn get_builder_sync_status(&self) -> RpcResult<GetBuilderSyncStatusResponse>;
+
+    #[method(name = "getMinerSettings")]
+    async fn get_miner_settings(&self) -> RpcResult<GetMinerSettingsResponse>;
+
+    #[method(name = "getPayloadDiffs")]
+    async fn get_payload_diffs(&self, limit: Option<usize>) -> RpcResult<GetPayloadDiffsResponse>;
+
+    #[method(name = "setCanaryPercentage")]
+    async fn set_canary_percentage(
+        &self,
+        request: SetCanaryPercentageRequest,
+    ) -> RpcResult<CanaryPercentageResponse>;
+
+    #[method(name = "getCanaryPercentage")]
+    async fn get_canary_percentage(&self) -> RpcResult<CanaryPercentageResponse>;
+
+    #[method(name = "scheduleExecutionMode")]
+    async fn schedule_execution_mode(
+        &self,
+        request: ScheduleExecutionModeRequest,
+    ) -> RpcResult<ScheduleExecutionModeResponse>;
+
+    #[method(name = "getExecutionModeSchedule")]
+    async fn get_execution_mode_schedule(&self) -> RpcResult<GetExecutionModeScheduleResponse>;
+
+    #[method(name = "cancelExecutionModeSchedule")]
+    async fn cancel_execution_mode_schedule(
+        &self,
+        id: u64,
+    ) -> RpcResult<CancelExecutionModeScheduleResponse>;
+
+    #[method(name = "getRoutingTable")]
+    async fn get_routing_table(&self) -> RpcResult<GetRoutingTableResponse>;
+}
+
+/// The shared runtime state exposed by the debug server.
+pub struct DebugState {
+    pub circuit_breakers: Vec<Arc<CircuitBreaker>>,
+    pub sync_trackers: Vec<Arc<SyncTracker>>,
+    pub miner_settings: Arc<Mutex<MinerSettings>>,
+    pub compare_history: Arc<CompareHistory>,
+    pub canary: Arc<Canary>,
+    pub execution_mode_schedule: Arc<ExecutionModeSchedule>,
+    pub routing_table: Arc<RoutingTable>,
+    pub state_notifier: StateNotifier,
 }
 
 pub struct DebugServer {
     execution_mode: Arc<Mutex<ExecutionMode>>,
+    state: DebugState,
 }
 
 impl DebugServer {
-    pub fn new(execution_mode: Arc<Mutex<ExecutionMode>>) -> Self {
-        Self { execution_mode }
+    pub fn new(execution_mode: Arc<Mutex<ExecutionMode>>, state: DebugState) -> Self {
+        Self {
+            execution_mode,
+            state,
+        }
     }
 
-    pub async fn run(self, debug_addr: &str) -> eyre::Result<()> {
-        let server = Server::builder().build(debug_addr).await?;
+    /// Starts the debug server, authenticating the requests with `auth` if set.
+    pub async fn run(self, debug_addr: &str, auth: Option<DebugAuth>) -> eyre::Result<()> {
+        let server = Server::builder()
+            .set_http_middleware(
+                tower::ServiceBuilder::new().option_layer(auth.map(DebugAuthLayer::new)),
+            )
+            .build(debug_addr)
+            .await?;
 
         let handle = server.start(self.into_rpc());
 
@@ -68,6 +194,12 @@ impl DebugApiServer for DebugServer {
         let mut execution_mode = self.execution_mode.lock().await;
         *execution_mode = request.execution_mode.clone();
 
+        // A manual override takes precedence over the builder circuit breakers
+        for circuit_breaker in &self.state.circuit_breakers {
+            circuit_breaker.reset().await;
+        }
+        self.state.state_notifier.notify();
+
         tracing::info!("Set execution mode to {:?}", request.execution_mode);
 
         Ok(SetExecutionModeResponse {
@@ -81,6 +213,98 @@ impl DebugApiServer for DebugServer {
             execution_mode: execution_mode.clone(),
         })
     }
+
+    async fn get_builder_health(&self) -> RpcResult<GetBuilderHealthResponse> {
+        let mut builders = vec![];
+        for circuit_breaker in &self.state.circuit_breakers {
+            builders.push(circuit_breaker.health().await);
+        }
+        Ok(GetBuilderHealthResponse { builders })
+    }
+
+    async fn get_builder_sync_status(&self) -> RpcResult<GetBuilderSyncStatusResponse> {
+        Ok(GetBuilderSyncStatusResponse {
+            builders: self
+                .state
+                .sync_trackers
+                .iter()
+                .map(|sync_tracker| sync_tracker.status())
+                .collect(),
+        })
+    }
+
+    async fn get_miner_settings(&self) -> RpcResult<GetMinerSettingsResponse> {
+        Ok(self.state.miner_settings.lock().await.clone())
+    }
+
+    async fn get_payload_diffs(&self, limit: Option<usize>) -> RpcResult<GetPayloadDiffsResponse> {
+        Ok(GetPayloadDiffsResponse {
+            diffs: self.state.compare_history.latest(limit).await,
+        })
+    }
+
+    async fn set_canary_percentage(
+        &self,
+        request: SetCanaryPercentageRequest,
+    ) -> RpcResult<CanaryPercentageResponse> {
+        if request.percentage > 100 {
+            return Err(ErrorObject::owned(
+                ErrorCode::InvalidParams.code(),
+                "Canary percentage must be between 0 and 100",
+                None::<String>,
+            ));
+        }
+        self.state.canary.set_percentage(request.percentage);
+        Ok(CanaryPercentageResponse {
+            percentage: request.percentage,
+        })
+    }
+
+    async fn get_canary_percentage(&self) -> RpcResult<CanaryPercentageResponse> {
+        Ok(CanaryPercentageResponse {
+            percentage: self.state.canary.percentage(),
+        })
+    }
+
+    async fn schedule_execution_mode(
+        &self,
+        request: ScheduleExecutionModeRequest,
+    ) -> RpcResult<ScheduleExecutionModeResponse> {
+        let id = self
+            .state
+            .execution_mode_schedule
+            .schedule(request)
+            .await
+            .map_err(|e| {
+                ErrorObject::owned(
+                    ErrorCode::InvalidParams.code(),
+                    e.to_string(),
+                    None::<String>,
+                )
+            })?;
+        Ok(ScheduleExecutionModeResponse { id })
+    }
+
+    async fn get_execution_mode_schedule(&self) -> RpcResult<GetExecutionModeScheduleResponse> {
+        let (schedule, active) = self.state.execution_mode_schedule.list().await;
+        Ok(GetExecutionModeScheduleResponse { schedule, active })
+    }
+
+    async fn cancel_execution_mode_schedule(
+        &self,
+        id: u64,
+    ) -> RpcResult<CancelExecutionModeScheduleResponse> {
+        Ok(CancelExecutionModeScheduleResponse {
+            cancelled: self.state.execution_mode_schedule.cancel(id).await,
+        })
+    }
+
+    async fn get_routing_table(&self) -> RpcResult<GetRoutingTableResponse> {
+        Ok(GetRoutingTableResponse {
+            rules: self.state.routing_table.rules.clone(),
+            default: self.state.routing_table.default,
+        })
+    }
 }
 
 pub struct DebugClient {
@@ -88,8 +312,12 @@ pub struct DebugClient {
 }
 
 impl DebugClient {
-    pub fn new(url: &str) -> eyre::Result<Self> {
-        let client = HttpClient::builder().build(url)?;
+    pub fn new(url: &str, credential: Option<DebugCredential>) -> eyre::Result<Self> {
+        let mut headers = HeaderMap::new();
+        if let Some(credential) = credential {
+            headers.insert(AUTHORIZATION, credential.header()?);
+        }
+        let client = HttpClient::builder().set_headers(headers).build(url)?;
 
         Ok(Self { client })
     }
@@ -107,11 +335,80 @@ impl DebugClient {
         let result = DebugApiClient::get_execution_mode(&self.client).await?;
         Ok(result)
     }
+
+    pub async fn get_builder_health(&self) -> eyre::Result<GetBuilderHealthResponse> {
+        let result = DebugApiClient::get_builder_health(&self.client).await?;
+        Ok(result)
+    }
+
+    pub async fn get_builder_sync_status(&self) -> eyre::Result<GetBuilderSyncStatusResponse> {
+        let result = DebugApiClient::get_builder_sync_status(&self.client).await?;
+        Ok(result)
+    }
+
+    pub async fn get_miner_settings(&self) -> eyre::Result<GetMinerSettingsResponse> {
+        let result = DebugApiClient::get_miner_settings(&self.client).await?;
+        Ok(result)
+    }
+
+    pub async fn get_payload_diffs(
+        &self,
+        limit: Option<usize>,
+    ) -> eyre::Result<GetPayloadDiffsResponse> {
+        let result = DebugApiClient::get_payload_diffs(&self.client, limit).await?;
+        Ok(result)
+    }
+
+    pub async fn set_canary_percentage(
+        &self,
+        percentage: u8,
+    ) -> eyre::Result<CanaryPercentageResponse> {
+        let request = SetCanaryPercentageRequest { percentage };
+        let result = DebugApiClient::set_canary_percentage(&self.client, request).await?;
+        Ok(result)
+    }
+
+    pub async fn get_canary_percentage(&self) -> eyre::Result<CanaryPercentageResponse> {
+        let result = DebugApiClient::get_canary_percentage(&self.client).await?;
+        Ok(result)
+    }
+
+    pub async fn schedule_execution_mode(
+        &self,
+        window: ScheduleWindow,
+    ) -> eyre::Result<ScheduleExecutionModeResponse> {
+        let result = DebugApiClient::schedule_execution_mode(&self.client, window).await?;
+        Ok(result)
+    }
+
+    pub async fn get_execution_mode_schedule(
+        &self,
+    ) -> eyre::Result<GetExecutionModeScheduleResponse> {
+        let result = DebugApiClient::get_execution_mode_schedule(&self.client).await?;
+        Ok(result)
+    }
+
+    pub async fn cancel_execution_mode_schedule(
+        &self,
+        id: u64,
+    ) -> eyre::Result<CancelExecutionModeScheduleResponse> {
+        let result = DebugApiClient::cancel_execution_mode_schedule(&self.client, id).await?;
+        Ok(result)
+    }
+
+    pub async fn get_routing_table(&self) -> eyre::Result<GetRoutingTableResponse> {
+        let result = DebugApiClient::get_routing_table(&self.client).await?;
+        Ok(result)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::circuit_breaker::{CircuitBreakerArgs, CircuitState};
+    use crate::schedule::ScheduleUnit;
+    use crate::sync_status::SyncState;
+    use alloy_rpc_types_engine::PayloadStatusEnum;
 
     const DEFAULT_ADDR: &str = "127.0.0.1:5555";
 
@@ -119,11 +416,49 @@ mod tests {
     async fn test_debug_client() {
         // spawn the server and try to modify it with the client
         let execution_mode = Arc::new(Mutex::new(ExecutionMode::Enabled));
+        let state_notifier = StateNotifier::default();
 
-        let server = DebugServer::new(execution_mode.clone());
-        let _ = server.run(DEFAULT_ADDR).await.unwrap();
+        let circuit_breaker = Arc::new(CircuitBreaker::new(
+            "builder".to_string(),
+            CircuitBreakerArgs {
+                circuit_breaker_threshold: 1,
+                ..Default::default()
+            },
+            None,
+            state_notifier.clone(),
+        ));
+        let sync_tracker = Arc::new(SyncTracker::new("builder".to_string(), None));
+        let miner_settings = Arc::new(Mutex::new(MinerSettings::default()));
+        let compare_history = Arc::new(CompareHistory::new(
+            Default::default(),
+            state_notifier.clone(),
+        ));
+        let canary = Arc::new(Canary::new(
+            Default::default(),
+            None,
+            state_notifier.clone(),
+        ));
+        let execution_mode_schedule = Arc::new(ExecutionModeSchedule::new(
+            execution_mode.clone(),
+            state_notifier.clone(),
+        ));
+        let routing_table = Arc::new(RoutingTable::default());
+        let server = DebugServer::new(
+            execution_mode.clone(),
+            DebugState {
+                circuit_breakers: vec![circuit_breaker.clone()],
+                sync_trackers: vec![sync_tracker.clone()],
+                miner_settings: miner_settings.clone(),
+                compare_history: compare_history.clone(),
+                canary: canary.clone(),
+                execution_mode_schedule: execution_mode_schedule.clone(),
+                routing_table: routing_table.clone(),
+                state_notifier,
+            },
+        );
+        let _ = server.run(DEFAULT_ADDR, None).await.unwrap();
 
-        let client = DebugClient::new(format!("http://{}", DEFAULT_ADDR).as_str()).unwrap();
+        let client = DebugClient::new(format!("http://{}", DEFAULT_ADDR).as_str(), None).unwrap();
 
         // Test setting execution mode to Disabled
         let result = client
@@ -146,5 +481,105 @@ mod tests {
         // Verify again with get_execution_mode
         let status = client.get_execution_mode().await.unwrap();
         assert_eq!(status.execution_mode, ExecutionMode::Enabled);
+
+        // Test the builder health and that setting the execution mode resets the circuit breakers
+        circuit_breaker.record_failure().await;
+        let health = client.get_builder_health().await.unwrap();
+        assert_eq!(health.builders[0].state, CircuitState::Open);
+        assert_eq!(health.builders[0].execution_mode, ExecutionMode::DryRun);
+
+        client
+            .set_execution_mode(ExecutionMode::Enabled)
+            .await
+            .unwrap();
+        let health = client.get_builder_health().await.unwrap();
+        assert_eq!(health.builders[0].state, CircuitState::Closed);
+
+        // Test the builder sync status
+        sync_tracker.record(&PayloadStatusEnum::Syncing, false);
+        let status = client.get_builder_sync_status().await.unwrap();
+        assert_eq!(status.builders[0].state, SyncState::Syncing);
+
+        // Test the miner settings
+        miner_settings
+            .lock()
+            .await
+            .record("miner_setGasLimit", serde_json::json!(["0x1c9c380"]))
+            .unwrap();
+        let settings = client.get_miner_settings().await.unwrap();
+        assert_eq!(
+            settings.calls["miner_setGasLimit"],
+            serde_json::json!(["0x1c9c380"])
+        );
+        assert_eq!(settings.limits.gas_limit, Some(30_000_000));
+
+        // Test the payload diffs of the compare mode
+        let diff = PayloadDiff::new(
+            Default::default(),
+            Err("No valid builder payload"),
+            None,
+            None,
+            None,
+        );
+        compare_history.push(diff.clone()).await;
+        let diffs = client.get_payload_diffs(None).await.unwrap();
+        assert_eq!(diffs.diffs, vec![diff]);
+
+        // Test the canary percentage
+        let result = client.set_canary_percentage(25).await.unwrap();
+        assert_eq!(result.percentage, 25);
+        assert_eq!(canary.percentage(), 25);
+        let result = client.get_canary_percentage().await.unwrap();
+        assert_eq!(result.percentage, 25);
+        assert!(client.set_canary_percentage(101).await.is_err());
+        assert_eq!(canary.percentage(), 25);
+
+        // Test the execution mode schedule
+        let window = ScheduleWindow {
+            execution_mode: ExecutionMode::Disabled,
+            unit: ScheduleUnit::Block,
+            start: 10,
+            end: Some(20),
+        };
+        let result = client
+            .schedule_execution_mode(window.clone())
+            .await
+            .unwrap();
+        let schedule = client.get_execution_mode_schedule().await.unwrap();
+        assert_eq!(
+            schedule.schedule,
+            vec![ScheduledExecutionMode {
+                id: result.id,
+                window
+            }]
+        );
+        assert_eq!(schedule.active, None);
+        assert!(client
+            .schedule_execution_mode(ScheduleWindow {
+                execution_mode: ExecutionMode::Disabled,
+                unit: ScheduleUnit::Timestamp,
+                start: 2000,
+                end: Some(1000),
+            })
+            .await
+            .is_err());
+        assert!(
+            client
+                .cancel_execution_mode_schedule(result.id)
+                .await
+                .unwrap()
+                .cancelled
+        );
+        assert!(client
+            .get_execution_mode_schedule()
+            .await
+            .unwrap()
+            .schedule
+            .is_empty());
+
+        // Test the routing table
+        let result = client.get_routing_table().await.unwrap();
+        assert_eq!(result.rules, routing_table.rules);
+        assert_eq!(result.default, RouteAction::L2);
     }
 }
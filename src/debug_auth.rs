@@ -0,0 +1,429 @@
+use crate::auth_layer::secret_to_bearer_header;
+use alloy_rpc_types_engine::JwtSecret;
+use clap::{arg, Parser};
+use http::header::AUTHORIZATION;
+use http::{HeaderMap, HeaderValue, StatusCode};
+use jsonrpsee::core::{http_helpers, BoxError};
+use jsonrpsee::http_client::{HttpBody, HttpRequest, HttpResponse};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::{future::Future, pin::Pin};
+use tower::{Layer, Service};
+use tracing::debug;
+
+/// Debug API methods that do not change the state of rollup-boost.
+const READ_ONLY_METHODS: [&str; 8] = [
+    "debug_getExecutionMode",
+    "debug_getBuilderHealth",
+    "debug_getBuilderSyncStatus",
+    "debug_getMinerSettings",
+    "debug_getPayloadDiffs",
+    "debug_getCanaryPercentage",
+    "debug_getExecutionModeSchedule",
+    "debug_getRoutingTable",
+];
+
+/// Maximum size of a request body read to check that it only calls read-only methods.
+const MAX_READ_ONLY_BODY_SIZE: u32 = 16 * 1024;
+
+/// Settings of the debug API authentication. The debug API is not authenticated unless a JWT
+/// secret or a bearer token is set.
+#[derive(Parser, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DebugAuthArgs {
+    /// Hex encoded JWT secret, the requests signed with it can call every debug API method
+    #[arg(long, env, value_name = "HEX")]
+    pub debug_jwt_token: Option<JwtSecret>,
+
+    /// Path to the JWT secret of the debug API (used if `--debug-jwt-token` is not provided)
+    #[arg(long, env, value_name = "PATH")]
+    pub debug_jwt_path: Option<PathBuf>,
+
+    /// Bearer token allowed to call every debug API method
+    #[arg(long, env, value_name = "TOKEN")]
+    pub debug_bearer_token: Option<String>,
+
+    /// Bearer token only allowed to call the read-only debug API methods
+    #[arg(long, env, value_name = "TOKEN")]
+    pub debug_read_only_bearer_token: Option<String>,
+
+    /// Serve the read-only debug API methods without authentication
+    #[arg(long, env, default_value = "false")]
+    pub debug_public_read_methods: bool,
+}
+
+impl DebugAuthArgs {
+    pub fn jwt_secret(&self) -> eyre::Result<Option<JwtSecret>> {
+        if let Some(secret) = self.debug_jwt_token {
+            Ok(Some(secret))
+        } else if let Some(path) = self.debug_jwt_path.as_ref() {
+            Ok(Some(JwtSecret::from_file(path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Credential used by the `debug` commands when no `--token` is given, the one with the
+    /// most access.
+    pub fn credential(&self) -> eyre::Result<Option<DebugCredential>> {
+        Ok(self
+            .jwt_secret()?
+            .map(DebugCredential::Jwt)
+            .or_else(|| self.debug_bearer_token.clone().map(DebugCredential::Bearer))
+            .or_else(|| {
+                self.debug_read_only_bearer_token
+                    .clone()
+                    .map(DebugCredential::Bearer)
+            }))
+    }
+}
+
+/// Credential sent by the debug client.
+#[derive(Debug, Clone)]
+pub enum DebugCredential {
+    /// JWT signed with the secret when the client is built, valid for a short time only
+    Jwt(JwtSecret),
+    Bearer(String),
+}
+
+impl DebugCredential {
+    pub fn header(&self) -> eyre::Result<HeaderValue> {
+        match self {
+            DebugCredential::Jwt(secret) => Ok(secret_to_bearer_header(secret)),
+            DebugCredential::Bearer(token) => Ok(format!("Bearer {token}").parse()?),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Access {
+    None,
+    ReadOnly,
+    Full,
+}
+
+/// Credentials accepted by the debug API.
+#[derive(Debug, Clone)]
+pub struct DebugAuth {
+    jwt_secret: Option<JwtSecret>,
+    bearer_token: Option<String>,
+    read_only_bearer_token: Option<String>,
+    public_read_methods: bool,
+}
+
+impl DebugAuth {
+    /// Returns `None` if no credential is set, the debug API is then not authenticated.
+    pub fn new(args: &DebugAuthArgs) -> eyre::Result<Option<Self>> {
+        let jwt_secret = args.jwt_secret()?;
+        if jwt_secret.is_none()
+            && args.debug_bearer_token.is_none()
+            && args.debug_read_only_bearer_token.is_none()
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            jwt_secret,
+            bearer_token: args.debug_bearer_token.clone(),
+            read_only_bearer_token: args.debug_read_only_bearer_token.clone(),
+            public_read_methods: args.debug_public_read_methods,
+        }))
+    }
+
+    fn access(&self, headers: &HeaderMap) -> Access {
+        let public = if self.public_read_methods {
+            Access::ReadOnly
+        } else {
+            Access::None
+        };
+        let Some(token) = headers
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+        else {
+            return public;
+        };
+
+        if self
+            .jwt_secret
+            .as_ref()
+            .is_some_and(|secret| secret.validate(token).is_ok())
+            || self
+                .bearer_token
+                .as_deref()
+                .is_some_and(|bearer_token| constant_time_eq(bearer_token, token))
+        {
+            Access::Full
+        } else if self
+            .read_only_bearer_token
+            .as_deref()
+            .is_some_and(|bearer_token| constant_time_eq(bearer_token, token))
+        {
+            Access::ReadOnly
+        } else {
+            public
+        }
+    }
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Access needed by a request or a batch of requests. Requests that cannot be parsed need a
+/// full access, as their methods are unknown.
+fn required_access(body: &[u8]) -> Access {
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return Access::Full;
+    };
+    let requests = match &value {
+        serde_json::Value::Array(requests) => requests.iter().collect(),
+        request => vec![request],
+    };
+    let read_only = requests.iter().all(|request| {
+        request
+            .get("method")
+            .and_then(serde_json::Value::as_str)
+            .is_some_and(|method| READ_ONLY_METHODS.contains(&method))
+    });
+    if read_only {
+        Access::ReadOnly
+    } else {
+        Access::Full
+    }
+}
+
+/// A layer checking the credentials of the debug API requests using `DebugAuthService`.
+#[derive(Debug, Clone)]
+pub struct DebugAuthLayer {
+    auth: Arc<DebugAuth>,
+}
+
+impl DebugAuthLayer {
+    pub fn new(auth: DebugAuth) -> Self {
+        Self {
+            auth: Arc::new(auth),
+        }
+    }
+}
+
+impl<S> Layer<S> for DebugAuthLayer {
+    type Service = DebugAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DebugAuthService {
+            auth: self.auth.clone(),
+            inner,
+        }
+    }
+}
+
+/// Rejects the requests without credentials with a `401 Unauthorized` response, and the requests
+/// calling a mutating method with a read-only credential with a `403 Forbidden` response.
+#[derive(Debug, Clone)]
+pub struct DebugAuthService<S> {
+    auth: Arc<DebugAuth>,
+    inner: S,
+}
+
+impl<S> Service<HttpRequest<HttpBody>> for DebugAuthService<S>
+where
+    S: Service<HttpRequest<HttpBody>, Response = HttpResponse> + Send + Clone + 'static,
+    S::Error: Into<BoxError> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: HttpRequest<HttpBody>) -> Self::Future {
+        let access = self.auth.access(req.headers());
+        let mut inner = self.inner.clone();
+
+        Box::pin(async move {
+            match access {
+                Access::Full => return inner.call(req).await.map_err(Into::into),
+                Access::None => {
+                    return Ok(rejected(
+                        StatusCode::UNAUTHORIZED,
+                        "Missing or invalid credentials",
+                    ))
+                }
+                Access::ReadOnly => {}
+            }
+
+            // A body too large to be read within the limit cannot be checked to only call
+            // read-only methods
+            let (parts, body) = req.into_parts();
+            let body_bytes = match http_helpers::read_body(
+                &parts.headers,
+                body,
+                MAX_READ_ONLY_BODY_SIZE,
+            )
+            .await
+            {
+                Ok((body_bytes, _)) if required_access(&body_bytes) <= access => body_bytes,
+                _ => {
+                    return Ok(rejected(
+                        StatusCode::FORBIDDEN,
+                        "Read-only credentials cannot call mutating methods",
+                    ))
+                }
+            };
+
+            let req = HttpRequest::from_parts(parts, HttpBody::from(body_bytes));
+            inner.call(req).await.map_err(Into::into)
+        })
+    }
+}
+
+fn rejected(status: StatusCode, message: &'static str) -> HttpResponse {
+    debug!(message = "rejected debug API request", "status" = %status);
+    let mut response = HttpResponse::new(HttpBody::from(message));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::core::client::{ClientT, Error as ClientError};
+    use jsonrpsee::http_client::transport::Error as TransportError;
+    use jsonrpsee::http_client::HttpClient;
+    use jsonrpsee::rpc_params;
+    use jsonrpsee::server::Server;
+    use jsonrpsee::RpcModule;
+
+    fn rejected_with(response: Result<String, ClientError>, status: u16) -> bool {
+        matches!(
+            response,
+            Err(ClientError::Transport(e))
+                if matches!(e.downcast_ref::<TransportError>(), Some(TransportError::Rejected { status_code }) if *status_code == status)
+        )
+    }
+
+    #[test]
+    fn test_required_access() {
+        assert_eq!(
+            required_access(br#"{"jsonrpc":"2.0","id":1,"method":"debug_getExecutionMode"}"#),
+            Access::ReadOnly
+        );
+        assert_eq!(
+            required_access(br#"{"jsonrpc":"2.0","id":1,"method":"debug_setExecutionMode"}"#),
+            Access::Full
+        );
+        // a batch needs the access of its most privileged request
+        assert_eq!(
+            required_access(
+                br#"[{"method":"debug_getExecutionMode"},{"method":"debug_getBuilderHealth"}]"#
+            ),
+            Access::ReadOnly
+        );
+        assert_eq!(
+            required_access(
+                br#"[{"method":"debug_getExecutionMode"},{"method":"debug_setCanaryPercentage"}]"#
+            ),
+            Access::Full
+        );
+        assert_eq!(required_access(b"not json"), Access::Full);
+    }
+
+    #[tokio::test]
+    async fn test_debug_auth() -> eyre::Result<()> {
+        let secret = JwtSecret::random();
+        let auth = DebugAuth::new(&DebugAuthArgs {
+            debug_jwt_token: Some(secret),
+            debug_read_only_bearer_token: Some("read-only".to_string()),
+            ..Default::default()
+        })?
+        .unwrap();
+        let server = Server::builder()
+            .set_http_middleware(tower::ServiceBuilder::new().layer(DebugAuthLayer::new(auth)))
+            .build("127.0.0.1:0")
+            .await?;
+        let addr = server.local_addr()?;
+        let mut module = RpcModule::new(());
+        module.register_method("debug_getExecutionMode", |_, _, _| "enabled")?;
+        module.register_method("debug_setExecutionMode", |_, _, _| "disabled")?;
+        let handle = server.start(module);
+
+        let client = |credential: Option<DebugCredential>| -> eyre::Result<HttpClient> {
+            let mut headers = HeaderMap::new();
+            if let Some(credential) = credential {
+                headers.insert(AUTHORIZATION, credential.header()?);
+            }
+            Ok(HttpClient::builder()
+                .set_headers(headers)
+                .build(format!("http://{addr}"))?)
+        };
+
+        // requests without credentials are rejected
+        let anonymous = client(None)?;
+        let response = anonymous
+            .request("debug_getExecutionMode", rpc_params![])
+            .await;
+        assert!(rejected_with(response, 401));
+
+        // the read-only token can only call the read-only methods
+        let read_only = client(Some(DebugCredential::Bearer("read-only".to_string())))?;
+        let response: String = read_only
+            .request("debug_getExecutionMode", rpc_params![])
+            .await?;
+        assert_eq!(response, "enabled");
+        let response = read_only
+            .request("debug_setExecutionMode", rpc_params![])
+            .await;
+        assert!(rejected_with(response, 403));
+        let response = read_only
+            .request(
+                "debug_getExecutionMode",
+                rpc_params!["0".repeat(MAX_READ_ONLY_BODY_SIZE as usize)],
+            )
+            .await;
+        assert!(rejected_with(response, 403));
+
+        // a JWT signed with the secret can call every method
+        let full = client(Some(DebugCredential::Jwt(secret)))?;
+        let response: String = full
+            .request("debug_setExecutionMode", rpc_params![])
+            .await?;
+        assert_eq!(response, "disabled");
+
+        let other = client(Some(DebugCredential::Jwt(JwtSecret::random())))?;
+        let response = other.request("debug_setExecutionMode", rpc_params![]).await;
+        assert!(rejected_with(response, 401));
+
+        handle.stop()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_read_methods() -> eyre::Result<()> {
+        let auth = DebugAuth::new(&DebugAuthArgs {
+            debug_bearer_token: Some("admin".to_string()),
+            debug_public_read_methods: true,
+            ..Default::default()
+        })?
+        .unwrap();
+        assert_eq!(auth.access(&HeaderMap::new()), Access::ReadOnly);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            DebugCredential::Bearer("admin".to_string()).header()?,
+        );
+        assert_eq!(auth.access(&headers), Access::Full);
+
+        assert!(DebugAuth::new(&DebugAuthArgs::default())?.is_none());
+        Ok(())
+    }
+}
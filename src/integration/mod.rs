@@ -676,7 +676,7 @@ impl RollupBoostTestHarness {
         let rb_service = self._framework.services.get("rollup-boost").unwrap();
         let endpoint = rb_service.get_endpoint("debug");
 
-        DebugClient::new(&endpoint).unwrap()
+        DebugClient::new(&endpoint, None).unwrap()
     }
 }
 
@@ -1,9 +1,26 @@
+use auth_layer::AuthLayer;
+use builder_config::BuilderConfig;
+use canary::CanaryArgs;
+use catch_up::CatchUpArgs;
+use circuit_breaker::CircuitBreakerArgs;
 use clap::{arg, Parser, Subcommand};
 use client::{BuilderArgs, ExecutionClient, L2ClientArgs};
+use compare::CompareArgs;
+use deadline::DeadlineArgs;
 use debug_api::DebugClient;
+use debug_auth::{DebugAuth, DebugAuthArgs, DebugCredential};
 use metrics::{ClientMetrics, ServerMetrics};
+use miner_settings::BuilderRecoveryArgs;
+use rollup_config::RollupConfig;
+use routing::RoutingTable;
+use schedule::{ScheduleUnit, ScheduleWindow};
+use selection::SelectionArgs;
 use server::ExecutionMode;
-use std::{net::SocketAddr, sync::Arc};
+use state::StateArgs;
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use sync_queue::SyncQueueArgs;
+use transport::is_ipc_path;
+use validator::{ValidatorArgs, ValidatorPool};
 
 use alloy_rpc_types_engine::JwtSecret;
 use dotenv::dotenv;
@@ -13,6 +30,7 @@ use hyper::service::service_fn;
 use hyper::{server::conn::http1, Request, Response};
 use hyper_util::rt::TokioIo;
 use jsonrpsee::http_client::HttpBody;
+use jsonrpsee::server::middleware::rpc::RpcServiceBuilder;
 use jsonrpsee::server::Server;
 use jsonrpsee::RpcModule;
 use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
@@ -25,17 +43,37 @@ use server::{PayloadSource, RollupBoostServer};
 
 use tokio::net::TcpListener;
 use tokio::signal::unix::{signal as unix_signal, SignalKind};
-use tracing::{error, info, Level};
+use tracing::{error, info, warn, Level};
 use tracing_subscriber::EnvFilter;
 
 mod auth_layer;
+mod builder_config;
+mod canary;
+mod catch_up;
+mod circuit_breaker;
 mod client;
+mod compare;
+mod deadline;
 mod debug_api;
+mod debug_auth;
 #[cfg(all(feature = "integration", test))]
 mod integration;
 mod metrics;
+mod miner_limits;
+mod miner_settings;
+mod payload;
 mod proxy;
+mod rollup_config;
+mod routing;
+mod schedule;
+mod selection;
 mod server;
+mod state;
+mod sync_queue;
+mod sync_status;
+mod transport;
+mod validation;
+mod validator;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
@@ -61,6 +99,21 @@ struct Args {
     #[arg(long, env, default_value = "8081")]
     rpc_port: u16,
 
+    /// Hex encoded JWT secret authenticating the requests of the op-node to the server. The
+    /// requests are not authenticated without a secret
+    #[arg(long, env, value_name = "HEX")]
+    rpc_jwt_token: Option<JwtSecret>,
+
+    /// Path to the JWT secret authenticating the requests of the op-node to the server
+    #[arg(long, env, value_name = "PATH")]
+    rpc_jwt_path: Option<PathBuf>,
+
+    /// Path of an IPC socket served alongside the server. The connections to the socket are not
+    /// authenticated
+    #[cfg(feature = "ipc")]
+    #[arg(long, env, value_name = "PATH")]
+    rpc_ipc_path: Option<PathBuf>,
+
     // Enable tracing
     #[arg(long, env, default_value = "false")]
     tracing: bool,
@@ -97,15 +150,68 @@ struct Args {
     #[arg(long, env, default_value = "5555")]
     debug_server_port: u16,
 
+    #[clap(flatten)]
+    debug_auth: DebugAuthArgs,
+
     /// Execution mode to start rollup boost with
     #[arg(long, env, default_value = "enabled")]
     execution_mode: ExecutionMode,
+
+    /// Path to the op-node rollup config (rollup.json) or a hardfork schedule. When set, engine
+    /// API calls are checked against the version of the hardfork active at the block timestamp
+    #[arg(long, env, value_name = "PATH")]
+    rollup_config: Option<PathBuf>,
+
+    /// Path to a JSON file listing several builders, each with its own url, JWT secret and
+    /// timeout. When set, the `--builder-*` arguments are ignored
+    #[arg(long, env, value_name = "PATH")]
+    builders_config: Option<PathBuf>,
+
+    /// Path to a JSON file of routing rules mapping methods or prefixes to their destination,
+    /// added to the default routing table
+    #[arg(long, env, value_name = "PATH")]
+    routing_config: Option<PathBuf>,
+
+    #[clap(flatten)]
+    selection: SelectionArgs,
+
+    #[clap(flatten)]
+    circuit_breaker: CircuitBreakerArgs,
+
+    #[clap(flatten)]
+    deadline: DeadlineArgs,
+
+    #[clap(flatten)]
+    sync_queue: SyncQueueArgs,
+
+    #[clap(flatten)]
+    catch_up: CatchUpArgs,
+
+    #[clap(flatten)]
+    builder_recovery: BuilderRecoveryArgs,
+
+    #[clap(flatten)]
+    validator: ValidatorArgs,
+
+    #[clap(flatten)]
+    compare: CompareArgs,
+
+    #[clap(flatten)]
+    canary: CanaryArgs,
+
+    #[clap(flatten)]
+    state: StateArgs,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Debug commands
     Debug {
+        /// Bearer token sent to the debug server. Without it, the debug server JWT secret or
+        /// bearer tokens are used if set
+        #[arg(long, env = "DEBUG_TOKEN")]
+        token: Option<String>,
+
         #[command(subcommand)]
         command: DebugCommands,
     },
@@ -118,6 +224,57 @@ enum DebugCommands {
 
     /// Get the execution mode
     ExecutionMode {},
+
+    /// Get the circuit breaker state of every builder
+    BuilderHealth {},
+
+    /// Get the sync status of every builder
+    BuilderSyncStatus {},
+
+    /// Get the last miner settings replayed to the recovering builders
+    MinerSettings {},
+
+    /// Get the most recent payload comparisons of the compare execution mode
+    PayloadDiffs {
+        /// Maximum number of comparisons to return
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+
+    /// Set the percentage of the blocks using the builder payload in enabled mode
+    SetCanaryPercentage {
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+        percentage: u8,
+    },
+
+    /// Get the percentage of the blocks using the builder payload in enabled mode
+    CanaryPercentage {},
+
+    /// Schedule an execution mode change by block number or timestamp
+    ScheduleExecutionMode {
+        execution_mode: ExecutionMode,
+
+        /// Unit of the start and end bounds
+        #[arg(long, default_value = "block")]
+        unit: ScheduleUnit,
+
+        /// First block number or timestamp of the window
+        #[arg(long)]
+        start: u64,
+
+        /// Last block number or timestamp of the window, the execution mode is kept without it
+        #[arg(long)]
+        end: Option<u64>,
+    },
+
+    /// Get the scheduled execution mode changes
+    ExecutionModeSchedule {},
+
+    /// Cancel a scheduled execution mode change
+    CancelExecutionModeSchedule { id: u64 },
+
+    /// Get the routing table of the proxied methods
+    RoutingTable {},
 }
 
 #[tokio::main]
@@ -135,22 +292,136 @@ async fn main() -> eyre::Result<()> {
     if let Some(cmd) = args.command {
         let debug_addr = format!("http://{}", debug_addr);
         return match cmd {
-            Commands::Debug { command } => match command {
-                DebugCommands::SetExecutionMode { execution_mode } => {
-                    let client = DebugClient::new(debug_addr.as_str())?;
-                    let result = client.set_execution_mode(execution_mode).await.unwrap();
-                    println!("Response: {:?}", result.execution_mode);
+            Commands::Debug { token, command } => {
+                let credential = match token {
+                    Some(token) => Some(DebugCredential::Bearer(token)),
+                    None => args.debug_auth.credential()?,
+                };
+                let client = DebugClient::new(debug_addr.as_str(), credential)?;
+                match command {
+                    DebugCommands::SetExecutionMode { execution_mode } => {
+                        let result = client.set_execution_mode(execution_mode).await.unwrap();
+                        println!("Response: {:?}", result.execution_mode);
+
+                        Ok(())
+                    }
+                    DebugCommands::ExecutionMode {} => {
+                        let result = client.get_execution_mode().await?;
+                        println!("Execution mode: {:?}", result.execution_mode);
 
-                    Ok(())
-                }
-                DebugCommands::ExecutionMode {} => {
-                    let client = DebugClient::new(debug_addr.as_str())?;
-                    let result = client.get_execution_mode().await?;
-                    println!("Execution mode: {:?}", result.execution_mode);
+                        Ok(())
+                    }
+                    DebugCommands::BuilderHealth {} => {
+                        let result = client.get_builder_health().await?;
+                        for builder in result.builders {
+                            println!(
+                                "{}: {} ({} consecutive failures, execution mode {:?})",
+                                builder.builder,
+                                builder.state,
+                                builder.consecutive_failures,
+                                builder.execution_mode
+                            );
+                        }
+
+                        Ok(())
+                    }
+                    DebugCommands::BuilderSyncStatus {} => {
+                        let result = client.get_builder_sync_status().await?;
+                        for builder in result.builders {
+                            println!("{}: {}", builder.builder, builder.state);
+                        }
+
+                        Ok(())
+                    }
+                    DebugCommands::MinerSettings {} => {
+                        let result = client.get_miner_settings().await?;
+                        for (method, params) in result.calls {
+                            println!("{}: {}", method, params);
+                        }
+
+                        Ok(())
+                    }
+                    DebugCommands::PayloadDiffs { limit } => {
+                        let result = client.get_payload_diffs(limit).await?;
+                        println!("{}", serde_json::to_string_pretty(&result.diffs)?);
+
+                        Ok(())
+                    }
+                    DebugCommands::SetCanaryPercentage { percentage } => {
+                        let result = client.set_canary_percentage(percentage).await?;
+                        println!("Canary percentage: {}%", result.percentage);
 
-                    Ok(())
+                        Ok(())
+                    }
+                    DebugCommands::CanaryPercentage {} => {
+                        let result = client.get_canary_percentage().await?;
+                        println!("Canary percentage: {}%", result.percentage);
+
+                        Ok(())
+                    }
+                    DebugCommands::ScheduleExecutionMode {
+                        execution_mode,
+                        unit,
+                        start,
+                        end,
+                    } => {
+                        let window = ScheduleWindow {
+                            execution_mode,
+                            unit,
+                            start,
+                            end,
+                        };
+                        let result = client.schedule_execution_mode(window).await?;
+                        println!("Scheduled execution mode change {}", result.id);
+
+                        Ok(())
+                    }
+                    DebugCommands::ExecutionModeSchedule {} => {
+                        let result = client.get_execution_mode_schedule().await?;
+                        for scheduled in result.schedule {
+                            let window = scheduled.window;
+                            let end = window
+                                .end
+                                .map_or("onwards".to_string(), |end| format!("to {}", end));
+                            let active = if result.active == Some(scheduled.id) {
+                                " (active)"
+                            } else {
+                                ""
+                            };
+                            println!(
+                                "{}: {:?} from {} {} {}{}",
+                                scheduled.id,
+                                window.execution_mode,
+                                window.unit,
+                                window.start,
+                                end,
+                                active
+                            );
+                        }
+
+                        Ok(())
+                    }
+                    DebugCommands::CancelExecutionModeSchedule { id } => {
+                        let result = client.cancel_execution_mode_schedule(id).await?;
+                        if result.cancelled {
+                            println!("Cancelled scheduled execution mode change {}", id);
+                        } else {
+                            println!("No scheduled execution mode change {}", id);
+                        }
+
+                        Ok(())
+                    }
+                    DebugCommands::RoutingTable {} => {
+                        let result = client.get_routing_table().await?;
+                        for rule in result.rules {
+                            println!("{}: {}", rule.method, rule.action);
+                        }
+                        println!("default: {}", result.default);
+
+                        Ok(())
+                    }
                 }
-            },
+            }
         };
     }
 
@@ -196,11 +467,19 @@ async fn main() -> eyre::Result<()> {
     }
 
     let l2_client_args = args.l2_client;
+    if is_ipc_path(&l2_client_args.l2_url) && !cfg!(feature = "ipc") {
+        bail!(
+            "The L2 url is an IPC socket path, rollup-boost must be built with the `ipc` feature"
+        );
+    }
 
     let l2_auth_jwt = if let Some(secret) = l2_client_args.l2_jwt_token {
         secret
     } else if let Some(path) = l2_client_args.l2_jwt_path.as_ref() {
         JwtSecret::from_file(path)?
+    } else if is_ipc_path(&l2_client_args.l2_url) {
+        // The connections to the IPC socket are not authenticated
+        JwtSecret::random()
     } else {
         bail!("Missing L2 Client JWT secret");
     };
@@ -218,13 +497,16 @@ async fn main() -> eyre::Result<()> {
         PayloadSource::L2,
     )?;
 
-    let builder_args = args.builder;
-    let builder_auth_jwt = if let Some(secret) = builder_args.builder_jwt_token {
-        secret
-    } else if let Some(path) = builder_args.builder_jwt_path.as_ref() {
-        JwtSecret::from_file(path)?
+    let builders = if let Some(path) = args.builders_config.as_ref() {
+        let builders = BuilderConfig::from_file(path)?;
+        info!(
+            message = "loaded builders config",
+            "builders" = builders.len(),
+            "policy" = ?args.selection.builder_selection_policy
+        );
+        builders
     } else {
-        bail!("Missing Builder JWT secret");
+        vec![BuilderConfig::try_from(args.builder)?]
     };
 
     let builder_metrics = if args.metrics {
@@ -233,45 +515,124 @@ async fn main() -> eyre::Result<()> {
         None
     };
 
-    let builder_client = ExecutionClient::new(
-        builder_args.builder_url.clone(),
-        builder_auth_jwt,
-        builder_args.builder_timeout,
-        builder_metrics,
-        PayloadSource::Builder,
-    )?;
+    let builder_clients = builders
+        .iter()
+        .map(|builder| {
+            ExecutionClient::new(
+                builder.url.clone(),
+                builder.jwt_secret,
+                builder.timeout,
+                builder_metrics.clone(),
+                PayloadSource::Builder,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
     let boost_sync_enabled = !args.no_boost_sync;
     if boost_sync_enabled {
         info!("Boost sync enabled");
     }
 
-    let rollup_boost = RollupBoostServer::new(
+    let mut rollup_boost = RollupBoostServer::new(
         l2_client,
-        builder_client,
+        builder_clients,
         boost_sync_enabled,
         metrics.clone(),
         args.execution_mode,
-    );
+    )
+    .with_selection(args.selection)
+    .with_circuit_breaker(args.circuit_breaker)
+    .with_deadline(args.deadline)
+    .with_sync_queue(args.sync_queue)
+    .with_catch_up(args.catch_up)
+    .with_builder_recovery(args.builder_recovery)
+    .with_compare(args.compare)
+    .with_canary(args.canary);
+
+    if let Some(path) = args.rollup_config.as_ref() {
+        let rollup_config = RollupConfig::from_file(path)?;
+        info!(
+            message = "loaded rollup config",
+            "ecotone_time" = ?rollup_config.ecotone_time,
+            "isthmus_time" = ?rollup_config.isthmus_time
+        );
+        rollup_boost = rollup_boost.with_rollup_config(rollup_config);
+    }
+
+    if let Some(path) = args.routing_config.as_ref() {
+        let routing_table = RoutingTable::from_file(path)?;
+        info!(
+            message = "loaded routing config",
+            "rules" = routing_table.rules.len(),
+            "default" = %routing_table.default
+        );
+        rollup_boost = rollup_boost.with_routing_table(routing_table);
+    }
+
+    if let Some(validator) = ValidatorPool::new(args.validator, metrics.clone())? {
+        rollup_boost = rollup_boost.with_validator(validator);
+    }
+
+    rollup_boost.start_state_file(args.state).await?;
 
     // Spawn the debug server
-    rollup_boost.start_debug_server(debug_addr.as_str()).await?;
+    let debug_auth = DebugAuth::new(&args.debug_auth)?;
+    if debug_auth.is_none() {
+        warn!("No debug API credentials, the debug server does not authenticate the requests");
+    }
+    rollup_boost
+        .start_debug_server(debug_addr.as_str(), debug_auth)
+        .await?;
 
+    let miner_settings = rollup_boost.miner_settings.clone();
+    let state_notifier = rollup_boost.state_notifier.clone();
+    let routing_table = rollup_boost.routing_table.clone();
     let module: RpcModule<()> = rollup_boost.try_into()?;
 
     // Build and start the server
     info!("Starting server on :{}", args.rpc_port);
 
-    let service_builder = tower::ServiceBuilder::new().layer(ProxyLayer::new(
+    let rpc_jwt = if let Some(secret) = args.rpc_jwt_token {
+        Some(secret)
+    } else if let Some(path) = args.rpc_jwt_path.as_ref() {
+        Some(JwtSecret::from_file(path)?)
+    } else {
+        warn!("No RPC JWT secret, the server does not authenticate the requests");
+        None
+    };
+
+    let proxy_layer = ProxyLayer::new(
         l2_client_args.l2_url,
         l2_auth_jwt,
-        builder_args.builder_url,
-        builder_auth_jwt,
+        builders
+            .into_iter()
+            .map(|builder| (builder.url, builder.jwt_secret))
+            .collect(),
+        miner_settings,
+        state_notifier,
+        routing_table,
         metrics,
-    ));
+    );
+    // The calls of the WebSocket and IPC connections bypass the HTTP middleware
+    let rpc_middleware = RpcServiceBuilder::new().layer(proxy_layer.rpc_layer());
+    let service_builder = tower::ServiceBuilder::new()
+        .option_layer(rpc_jwt.map(AuthLayer::new))
+        .layer(proxy_layer);
+
+    #[cfg(feature = "ipc")]
+    let ipc_handle = if let Some(path) = args.rpc_ipc_path.as_ref() {
+        info!("Starting IPC server on {}", path.display());
+        let ipc_server = reth_ipc::server::Builder::default()
+            .set_rpc_middleware(rpc_middleware.clone())
+            .build(path.to_string_lossy().to_string());
+        Some(ipc_server.start(module.clone()).await?)
+    } else {
+        None
+    };
 
     let server = Server::builder()
         .set_http_middleware(service_builder)
+        .set_rpc_middleware(rpc_middleware)
         .build(format!("{}:{}", args.rpc_host, args.rpc_port).parse::<SocketAddr>()?)
         .await?;
     let handle = server.start(module);
@@ -296,6 +657,10 @@ async fn main() -> eyre::Result<()> {
             let _ = stop_handle.stop();
         }
     }
+    #[cfg(feature = "ipc")]
+    if let Some(ipc_handle) = ipc_handle {
+        let _ = ipc_handle.stop();
+    }
 
     Ok(())
 }
@@ -366,12 +731,10 @@ mod tests {
     use http::Uri;
     use jsonrpsee::core::client::ClientT;
 
-    use crate::auth_layer::AuthClientService;
     use crate::server::PayloadSource;
+    use crate::transport::RpcClient;
     use alloy_rpc_types_engine::JwtSecret;
     use jsonrpsee::http_client::transport::Error as TransportError;
-    use jsonrpsee::http_client::transport::HttpBackend;
-    use jsonrpsee::http_client::HttpClient;
     use jsonrpsee::RpcModule;
     use jsonrpsee::{
         core::ClientError,
@@ -428,9 +791,7 @@ mod tests {
         ));
     }
 
-    async fn send_request(
-        client: Arc<HttpClient<AuthClientService<HttpBackend>>>,
-    ) -> Result<String, ClientError> {
+    async fn send_request(client: Arc<RpcClient>) -> Result<String, ClientError> {
         let server = spawn_server().await;
 
         let response = client
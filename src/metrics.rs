@@ -1,8 +1,10 @@
 use std::time::Duration;
 
-use metrics::{counter, histogram, Counter, Histogram};
+use metrics::{counter, gauge, histogram, Counter, Histogram};
 use metrics_derive::Metrics;
 
+use crate::compare::PayloadDiff;
+use crate::selection::SelectionReason;
 use crate::server::PayloadSource;
 
 #[derive(Metrics)]
@@ -17,6 +19,21 @@ pub struct ServerMetrics {
     #[metric(describe = "Total latency for server `engine_forkChoiceUpdatedV3` call")]
     pub fork_choice_updated_v3_total: Histogram,
 
+    #[metric(describe = "Total latency for server `engine_newPayloadV4` call")]
+    pub new_payload_v4_total: Histogram,
+
+    #[metric(describe = "Total latency for server `engine_getPayloadV4` call")]
+    pub get_payload_v4_total: Histogram,
+
+    #[metric(describe = "Total latency for server `engine_newPayloadV2` call")]
+    pub new_payload_v2_total: Histogram,
+
+    #[metric(describe = "Total latency for server `engine_getPayloadV2` call")]
+    pub get_payload_v2_total: Histogram,
+
+    #[metric(describe = "Total latency for server `engine_forkChoiceUpdatedV2` call")]
+    pub fork_choice_updated_v2_total: Histogram,
+
     // Builder proxy metrics
     #[metric(describe = "Latency for builder client forwarded rpc calls (excluding the engine api)", labels = ["method"])]
     #[allow(dead_code)]
@@ -62,6 +79,46 @@ pub struct ClientMetrics {
     #[metric(describe = "Number of client `engine_forkChoiceUpdatedV3` responses", labels = ["code"])]
     #[allow(dead_code)]
     pub fork_choice_updated_v3_response_count: Counter,
+
+    #[metric(describe = "Latency for client `engine_newPayloadV4` call")]
+    #[allow(dead_code)]
+    pub new_payload_v4: Histogram,
+
+    #[metric(describe = "Number of client `engine_newPayloadV4` responses", labels = ["code"])]
+    #[allow(dead_code)]
+    pub new_payload_v4_response_count: Counter,
+
+    #[metric(describe = "Latency for client `engine_getPayloadV4` call")]
+    #[allow(dead_code)]
+    pub get_payload_v4: Histogram,
+
+    #[metric(describe = "Number of client `engine_getPayloadV4` responses", labels = ["code"])]
+    #[allow(dead_code)]
+    pub get_payload_v4_response_count: Counter,
+
+    #[metric(describe = "Latency for client `engine_newPayloadV2` call")]
+    #[allow(dead_code)]
+    pub new_payload_v2: Histogram,
+
+    #[metric(describe = "Number of client `engine_newPayloadV2` responses", labels = ["code"])]
+    #[allow(dead_code)]
+    pub new_payload_v2_response_count: Counter,
+
+    #[metric(describe = "Latency for client `engine_getPayloadV2` call")]
+    #[allow(dead_code)]
+    pub get_payload_v2: Histogram,
+
+    #[metric(describe = "Number of client `engine_getPayloadV2` responses", labels = ["code"])]
+    #[allow(dead_code)]
+    pub get_payload_v2_response_count: Counter,
+
+    #[metric(describe = "Latency for client `engine_forkChoiceUpdatedV2` call")]
+    #[allow(dead_code)]
+    pub fork_choice_updated_v2: Histogram,
+
+    #[metric(describe = "Number of client `engine_forkChoiceUpdatedV2` responses", labels = ["code"])]
+    #[allow(dead_code)]
+    pub fork_choice_updated_v2_response_count: Counter,
 }
 
 impl ServerMetrics {
@@ -69,6 +126,114 @@ impl ServerMetrics {
         counter!("rpc.blocks_created", "source" => source.to_string()).increment(1);
     }
 
+    pub fn set_canary_percentage(&self, percentage: u8) {
+        gauge!("rpc.canary_percentage").set(percentage as f64);
+    }
+
+    pub fn increment_canary_blocks(&self, builder_block: bool) {
+        counter!("rpc.canary_blocks", "builder_block" => builder_block.to_string()).increment(1);
+    }
+
+    pub fn increment_block_selection(&self, source: &PayloadSource, reason: SelectionReason) {
+        counter!("rpc.block_selection", "source" => source.to_string(), "reason" => reason.to_string())
+            .increment(1);
+    }
+
+    pub fn increment_builder_payload_selected(&self, builder: String) {
+        counter!("rpc.builder_payload_selected", "builder" => builder).increment(1);
+    }
+
+    pub fn increment_builder_deadline_exceeded(&self, builder: String) {
+        counter!("rpc.builder_deadline_exceeded", "builder" => builder).increment(1);
+    }
+
+    pub fn increment_builder_fcu_race(&self, builder: String) {
+        counter!("rpc.builder_fcu_race", "builder" => builder).increment(1);
+    }
+
+    pub fn increment_builder_miner_limit_exceeded(&self, builder: String, limit: &'static str) {
+        counter!("rpc.builder_miner_limit_exceeded", "builder" => builder, "limit" => limit)
+            .increment(1);
+    }
+
+    pub fn increment_builder_miner_settings_replay(&self, builder: String, success: bool) {
+        counter!("rpc.builder_miner_settings_replay", "builder" => builder, "success" => success.to_string())
+            .increment(1);
+    }
+
+    pub fn increment_validator_payload_status(&self, validator: String, status: String) {
+        counter!("rpc.validator_payload_status", "validator" => validator, "status" => status)
+            .increment(1);
+    }
+
+    pub fn increment_validator_fallback(&self) {
+        counter!("rpc.validator_fallback").increment(1);
+    }
+
+    pub fn record_payload_diff(&self, diff: &PayloadDiff) {
+        counter!("rpc.compare_builder_payload", "valid" => diff.builder_valid.to_string())
+            .increment(1);
+        if let Some(delta) = diff.block_value_delta {
+            let value = delta.unsigned_abs().saturating_to::<u128>() as f64;
+            histogram!("rpc.compare_block_value_delta").record(if delta.is_negative() {
+                -value
+            } else {
+                value
+            });
+        }
+        if let (Some(builder), Some(local)) = (diff.builder_gas_used, diff.local_gas_used) {
+            histogram!("rpc.compare_gas_used_delta").record(builder as f64 - local as f64);
+        }
+        if let (Some(builder), Some(local)) = (diff.builder_tx_count, diff.local_tx_count) {
+            histogram!("rpc.compare_tx_count_delta").record(builder as f64 - local as f64);
+        }
+        histogram!("rpc.compare_builder_only_txs").record(diff.builder_only_txs.len() as f64);
+        histogram!("rpc.compare_local_only_txs").record(diff.local_only_txs.len() as f64);
+        if let Some(latency) = diff.builder_latency_ms {
+            histogram!("rpc.compare_builder_latency").record(latency as f64 / 1000.0);
+        }
+        if let Some(latency) = diff.local_latency_ms {
+            histogram!("rpc.compare_local_latency").record(latency as f64 / 1000.0);
+        }
+    }
+
+    pub fn record_builder_sync_queue_depth(&self, builder: String, depth: f64) {
+        gauge!("rpc.builder_sync_queue_depth", "builder" => builder).set(depth);
+    }
+
+    pub fn record_builder_sync_queue_lag(&self, builder: String, lag: Duration) {
+        histogram!("rpc.builder_sync_queue_lag", "builder" => builder).record(lag.as_secs_f64());
+    }
+
+    pub fn increment_builder_sync_queue_retries(&self, builder: String) {
+        counter!("rpc.builder_sync_queue_retries", "builder" => builder).increment(1);
+    }
+
+    pub fn increment_builder_sync_queue_dropped(&self, builder: String) {
+        counter!("rpc.builder_sync_queue_dropped", "builder" => builder).increment(1);
+    }
+
+    pub fn increment_builder_catch_up(&self, builder: String, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        counter!("rpc.builder_catch_up", "builder" => builder, "result" => result).increment(1);
+    }
+
+    pub fn increment_builder_catch_up_blocks(&self, builder: String) {
+        counter!("rpc.builder_catch_up_blocks", "builder" => builder).increment(1);
+    }
+
+    pub fn record_builder_syncing(&self, builder: String, syncing: bool) {
+        gauge!("rpc.builder_syncing", "builder" => builder).set(if syncing { 1.0 } else { 0.0 });
+    }
+
+    pub fn record_circuit_breaker_state(&self, builder: String, state: f64) {
+        gauge!("rpc.builder_circuit_breaker_state", "builder" => builder).set(state);
+    }
+
+    pub fn increment_circuit_breaker_trips(&self, builder: String) {
+        counter!("rpc.builder_circuit_breaker_trips", "builder" => builder).increment(1);
+    }
+
     pub fn record_builder_forwarded_call(&self, latency: Duration, method: String) {
         histogram!("rpc.builder_forwarded_call", "method" => method).record(latency.as_secs_f64());
     }
@@ -108,15 +273,33 @@ impl ServerMetrics {
 
 impl ClientMetrics {
     pub fn new(source: &PayloadSource) -> Self {
+        Self::with_target(source.to_string())
+    }
+
+    /// Metrics of the calls to an execution engine that is not a payload source, such as the
+    /// validators.
+    pub fn with_target(target: String) -> Self {
         Self {
-            new_payload_v3: histogram!("rpc.new_payload_v3", "target" => source.to_string()),
-            get_payload_v3: histogram!("rpc.get_payload_v3", "target" => source.to_string()),
-            fork_choice_updated_v3: histogram!("rpc.fork_choice_updated_v3", "target" => source.to_string()),
+            new_payload_v3: histogram!("rpc.new_payload_v3", "target" => target.clone()),
+            get_payload_v3: histogram!("rpc.get_payload_v3", "target" => target.clone()),
+            fork_choice_updated_v3: histogram!("rpc.fork_choice_updated_v3", "target" => target.clone()),
             new_payload_v3_response_count: counter!("rpc.new_payload_v3_response_count"),
             get_payload_v3_response_count: counter!("rpc.get_payload_v3_response_count"),
             fork_choice_updated_v3_response_count: counter!(
                 "rpc.fork_choice_updated_v3_response_count"
             ),
+            new_payload_v4: histogram!("rpc.new_payload_v4", "target" => target.clone()),
+            get_payload_v4: histogram!("rpc.get_payload_v4", "target" => target.clone()),
+            new_payload_v4_response_count: counter!("rpc.new_payload_v4_response_count"),
+            get_payload_v4_response_count: counter!("rpc.get_payload_v4_response_count"),
+            new_payload_v2: histogram!("rpc.new_payload_v2", "target" => target.clone()),
+            get_payload_v2: histogram!("rpc.get_payload_v2", "target" => target.clone()),
+            fork_choice_updated_v2: histogram!("rpc.fork_choice_updated_v2", "target" => target.clone()),
+            new_payload_v2_response_count: counter!("rpc.new_payload_v2_response_count"),
+            get_payload_v2_response_count: counter!("rpc.get_payload_v2_response_count"),
+            fork_choice_updated_v2_response_count: counter!(
+                "rpc.fork_choice_updated_v2_response_count"
+            ),
         }
     }
 
@@ -134,4 +317,29 @@ impl ClientMetrics {
         self.fork_choice_updated_v3.record(latency.as_secs_f64());
         counter!("rpc.fork_choice_updated_v3_response_count", "code" => code).increment(1);
     }
+
+    pub fn record_new_payload_v4(&self, latency: Duration, code: String) {
+        self.new_payload_v4.record(latency.as_secs_f64());
+        counter!("rpc.new_payload_v4_response_count", "code" => code).increment(1);
+    }
+
+    pub fn record_get_payload_v4(&self, latency: Duration, code: String) {
+        self.get_payload_v4.record(latency.as_secs_f64());
+        counter!("rpc.get_payload_v4_response_count", "code" => code).increment(1);
+    }
+
+    pub fn record_new_payload_v2(&self, latency: Duration, code: String) {
+        self.new_payload_v2.record(latency.as_secs_f64());
+        counter!("rpc.new_payload_v2_response_count", "code" => code).increment(1);
+    }
+
+    pub fn record_get_payload_v2(&self, latency: Duration, code: String) {
+        self.get_payload_v2.record(latency.as_secs_f64());
+        counter!("rpc.get_payload_v2_response_count", "code" => code).increment(1);
+    }
+
+    pub fn record_fork_choice_updated_v2(&self, latency: Duration, code: String) {
+        self.fork_choice_updated_v2.record(latency.as_secs_f64());
+        counter!("rpc.fork_choice_updated_v2_response_count", "code" => code).increment(1);
+    }
 }
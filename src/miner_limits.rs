@@ -0,0 +1,287 @@
+use alloy_primitives::{Bytes, U256, U64};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Type byte of the OP Stack deposit transactions, which are forced and not subject to the limits
+const DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+/// Parameters of the Fjord estimation of the compressed size of a transaction, scaled by 1e6
+const L1_COST_INTERCEPT: i64 = -42_585_600;
+const L1_COST_FASTLZ_COEF: i64 = 836_500;
+const MIN_TRANSACTION_SIZE_SCALED: i64 = 100_000_000;
+
+/// Block building limits set through the `miner_` namespace, for example by the batcher to
+/// throttle the data availability usage of the chain.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MinerLimits {
+    /// Maximum estimated compressed size of a transaction, set by `miner_setMaxDASize`
+    pub max_da_tx_size: Option<u64>,
+    /// Maximum estimated compressed size of the transactions of a block, set by `miner_setMaxDASize`
+    pub max_da_block_size: Option<u64>,
+    /// Maximum gas limit of a block header, set by `miner_setGasLimit`
+    pub gas_limit: Option<u64>,
+}
+
+/// Limit of [MinerLimits] exceeded by a builder block.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MinerLimitViolation {
+    #[error("transaction {index} has an estimated compressed size of {size} bytes, above the maximum of {max}")]
+    DaTxSize { index: usize, size: u64, max: u64 },
+    #[error("block has an estimated compressed size of {size} bytes, above the maximum of {max}")]
+    DaBlockSize { size: u64, max: u64 },
+    #[error("block has a gas limit of {gas_limit}, above the maximum of {max}")]
+    GasLimit { gas_limit: u64, max: u64 },
+}
+
+impl MinerLimitViolation {
+    /// Name of the exceeded limit, used as metric label.
+    pub fn limit(&self) -> &'static str {
+        match self {
+            MinerLimitViolation::DaTxSize { .. } => "max_da_tx_size",
+            MinerLimitViolation::DaBlockSize { .. } => "max_da_block_size",
+            MinerLimitViolation::GasLimit { .. } => "gas_limit",
+        }
+    }
+}
+
+impl MinerLimits {
+    /// Records the settings of a `miner_` call, returns `false` if the method does not set a limit.
+    /// A limit of 0 removes it.
+    pub fn record(&mut self, method: &str, params: serde_json::Value) -> eyre::Result<bool> {
+        match method {
+            "miner_setMaxDASize" => {
+                let (max_tx_size, max_block_size): (U256, U256) = serde_json::from_value(params)?;
+                self.max_da_tx_size = Some(max_tx_size.saturating_to()).filter(|max| *max > 0);
+                self.max_da_block_size =
+                    Some(max_block_size.saturating_to()).filter(|max| *max > 0);
+                Ok(true)
+            }
+            "miner_setGasLimit" => {
+                let (gas_limit,): (U64,) = serde_json::from_value(params)?;
+                self.gas_limit = Some(gas_limit.to()).filter(|max| *max > 0);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Checks the transactions and the header gas limit of a block against the limits.
+    pub fn check(&self, transactions: &[Bytes], gas_limit: u64) -> Result<(), MinerLimitViolation> {
+        if let Some(max) = self.gas_limit {
+            if gas_limit > max {
+                return Err(MinerLimitViolation::GasLimit { gas_limit, max });
+            }
+        }
+
+        if self.max_da_tx_size.is_none() && self.max_da_block_size.is_none() {
+            return Ok(());
+        }
+        let mut block_size = 0;
+        for (index, transaction) in transactions.iter().enumerate() {
+            if transaction.first() == Some(&DEPOSIT_TX_TYPE) {
+                continue;
+            }
+            let size = estimated_da_size(transaction);
+            if let Some(max) = self.max_da_tx_size {
+                if size > max {
+                    return Err(MinerLimitViolation::DaTxSize { index, size, max });
+                }
+            }
+            block_size += size;
+        }
+        if let Some(max) = self.max_da_block_size {
+            if block_size > max {
+                return Err(MinerLimitViolation::DaBlockSize {
+                    size: block_size,
+                    max,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Estimated compressed size of an encoded transaction, as used by op-geth to enforce
+/// `miner_setMaxDASize` since Fjord.
+pub fn estimated_da_size(transaction: &[u8]) -> u64 {
+    // 68 bytes account for the signature, which does not compress
+    let fastlz_size = flz_compress_len(transaction) as i64 + 68;
+    let estimated_size = L1_COST_INTERCEPT + L1_COST_FASTLZ_COEF * fastlz_size;
+    (estimated_size.max(MIN_TRANSACTION_SIZE_SCALED) / 1_000_000) as u64
+}
+
+/// Length of the data after FastLZ compression, ported from op-geth `FlzCompressLen`.
+fn flz_compress_len(ib: &[u8]) -> u32 {
+    let mut n: u32 = 0;
+    let mut ht = vec![0u32; 8192];
+    let u24 = |i: u32| -> u32 {
+        let i = i as usize;
+        ib[i] as u32 | (ib[i + 1] as u32) << 8 | (ib[i + 2] as u32) << 16
+    };
+    let cmp = |p: u32, q: u32, e: u32| -> u32 {
+        let mut l = 0;
+        let mut e = e - q;
+        while l < e {
+            if ib[(p + l) as usize] != ib[(q + l) as usize] {
+                e = 0;
+            }
+            l += 1;
+        }
+        l
+    };
+    let literals = |n: &mut u32, r: u32| {
+        *n += 0x21 * (r / 0x20);
+        let r = r % 0x20;
+        if r != 0 {
+            *n += r + 1;
+        }
+    };
+    let match_len = |n: &mut u32, l: u32| {
+        let l = l.wrapping_sub(1);
+        *n += 3 * (l / 262);
+        *n += if l % 262 >= 6 { 3 } else { 2 };
+    };
+    let hash = |v: u32| -> u32 { (2654435769u32.wrapping_mul(v) >> 19) & 0x1fff };
+
+    let len = ib.len() as u32;
+    let ip_limit = if len < 13 { 0 } else { len - 13 };
+    let mut a: u32 = 0;
+    let mut ip = a + 2;
+    while ip < ip_limit {
+        let mut r;
+        loop {
+            let s = u24(ip);
+            let h = hash(s) as usize;
+            r = ht[h];
+            ht[h] = ip;
+            let d = ip.wrapping_sub(r);
+            if ip >= ip_limit {
+                break;
+            }
+            ip += 1;
+            if d <= 0x1fff && s == u24(r) {
+                break;
+            }
+        }
+        if ip >= ip_limit {
+            break;
+        }
+        ip -= 1;
+        if ip > a {
+            literals(&mut n, ip - a);
+        }
+        let l = cmp(r + 3, ip + 3, ip_limit + 9);
+        match_len(&mut n, l);
+        ip += l;
+        for _ in 0..2 {
+            ht[hash(u24(ip)) as usize] = ip;
+            ip += 1;
+        }
+        a = ip;
+    }
+    literals(&mut n, len - a);
+    n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_flz_compress_len() {
+        assert_eq!(flz_compress_len(&[]), 0);
+        // short inputs are stored as literals
+        assert_eq!(flz_compress_len(&[1, 2, 3, 4, 5]), 6);
+        // repeated data compresses
+        assert!(flz_compress_len(&[0; 1000]) < 100);
+        let random: Vec<u8> = (0..1000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        assert!(flz_compress_len(&random) > flz_compress_len(&[0; 1000]));
+    }
+
+    #[test]
+    fn test_estimated_da_size() {
+        // small transactions are counted with the minimum size
+        assert_eq!(estimated_da_size(&[2, 1]), 100);
+        assert!(estimated_da_size(&[0xab; 2000]) < 2000);
+    }
+
+    #[test]
+    fn test_record() {
+        let mut limits = MinerLimits::default();
+        assert!(limits
+            .record("miner_setMaxDASize", json!(["0x3e8", "0x2710"]))
+            .unwrap());
+        assert!(limits
+            .record("miner_setGasLimit", json!(["0x1c9c380"]))
+            .unwrap());
+        assert!(!limits.record("miner_setExtra", json!(["0x"])).unwrap());
+        assert_eq!(
+            limits,
+            MinerLimits {
+                max_da_tx_size: Some(1000),
+                max_da_block_size: Some(10000),
+                gas_limit: Some(30_000_000),
+            }
+        );
+
+        limits
+            .record("miner_setMaxDASize", json!(["0x0", "0x0"]))
+            .unwrap();
+        assert_eq!(limits.max_da_tx_size, None);
+        assert_eq!(limits.max_da_block_size, None);
+        limits.record("miner_setGasLimit", json!(["0x0"])).unwrap();
+        assert_eq!(limits.gas_limit, None);
+        assert!(limits.record("miner_setGasLimit", json!([])).is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let transaction = Bytes::from(vec![0x02; 300]);
+        let size = estimated_da_size(&transaction);
+        let deposit = Bytes::from(vec![DEPOSIT_TX_TYPE; 5000]);
+        let transactions = vec![deposit, transaction.clone(), transaction];
+
+        assert_eq!(
+            MinerLimits::default().check(&transactions, u64::MAX),
+            Ok(())
+        );
+
+        let limits = MinerLimits {
+            max_da_tx_size: Some(size),
+            max_da_block_size: Some(2 * size),
+            gas_limit: Some(1000),
+        };
+        assert_eq!(limits.check(&transactions, 1000), Ok(()));
+        assert_eq!(
+            limits.check(&transactions, 1001),
+            Err(MinerLimitViolation::GasLimit {
+                gas_limit: 1001,
+                max: 1000
+            })
+        );
+
+        let limits = MinerLimits {
+            max_da_tx_size: Some(size - 1),
+            ..Default::default()
+        };
+        assert!(matches!(
+            limits.check(&transactions, 0),
+            Err(MinerLimitViolation::DaTxSize { index: 1, .. })
+        ));
+
+        let limits = MinerLimits {
+            max_da_block_size: Some(2 * size - 1),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.check(&transactions, 0),
+            Err(MinerLimitViolation::DaBlockSize {
+                size: 2 * size,
+                max: 2 * size - 1
+            })
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use crate::client::ExecutionClient;
+use crate::metrics::ServerMetrics;
+use crate::miner_limits::MinerLimits;
+use clap::{arg, Parser};
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::core::params::ArrayParams;
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::proc_macros::rpc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{debug, error, info, warn};
+
+/// Settings of the detection of the builder restarts.
+#[derive(Parser, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuilderRecoveryArgs {
+    /// Interval in milliseconds between the `engine_getClientVersionV1` calls used to detect a
+    /// builder restart with a new version, 0 disables the polling
+    #[arg(long, env, default_value_t = 12000)]
+    pub builder_client_version_interval: u64,
+}
+
+impl Default for BuilderRecoveryArgs {
+    fn default() -> Self {
+        Self {
+            builder_client_version_interval: 12000,
+        }
+    }
+}
+
+#[rpc(client, namespace = "engine")]
+pub trait ClientVersionApi {
+    #[method(name = "getClientVersionV1")]
+    async fn get_client_version_v1(&self, client_version: Value) -> RpcResult<Value>;
+}
+
+/// Last settings forwarded to the builders through the `miner_` namespace.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MinerSettings {
+    /// Parameters of the last call of each `miner_` method
+    pub calls: BTreeMap<String, Value>,
+    /// Limits enforced on the builder blocks
+    pub limits: MinerLimits,
+}
+
+impl MinerSettings {
+    /// Records a forwarded `miner_` call.
+    pub fn record(&mut self, method: &str, params: Value) -> eyre::Result<()> {
+        self.calls.insert(method.to_string(), params.clone());
+        if self.limits.record(method, params)? {
+            info!(message = "updated miner limits", "method" = method, "limits" = ?self.limits);
+        }
+        Ok(())
+    }
+}
+
+/// Detects when a builder recovers and replays the miner settings to it.
+///
+/// A builder recovers when a call succeeds after a failure, or when its client version changed,
+/// as a restarted builder lost the settings forwarded before.
+pub struct BuilderRecovery {
+    builder: ExecutionClient,
+    miner_settings: Arc<Mutex<MinerSettings>>,
+    failed: AtomicBool,
+    client_version: Mutex<Option<Value>>,
+    metrics: Option<Arc<ServerMetrics>>,
+}
+
+impl BuilderRecovery {
+    pub fn new(
+        builder: ExecutionClient,
+        miner_settings: Arc<Mutex<MinerSettings>>,
+        metrics: Option<Arc<ServerMetrics>>,
+    ) -> Self {
+        Self {
+            builder,
+            miner_settings,
+            failed: AtomicBool::new(false),
+            client_version: Mutex::new(None),
+            metrics,
+        }
+    }
+
+    /// Records the outcome of a call to the builder, replaying the settings when a call succeeds
+    /// after a failure.
+    pub fn record_result(self: &Arc<Self>, success: bool) {
+        if !success {
+            self.failed.store(true, Ordering::SeqCst);
+        } else if self.failed.swap(false, Ordering::SeqCst) {
+            info!(message = "builder recovered, replaying miner settings", "url" = ?self.builder.auth_rpc);
+            let recovery = self.clone();
+            tokio::spawn(async move { recovery.replay().await });
+        }
+    }
+
+    /// Spawns the task polling the builder client version.
+    pub fn spawn_client_version_poll(self: &Arc<Self>, interval: Duration) {
+        let recovery = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                recovery.check_client_version().await;
+            }
+        });
+    }
+
+    async fn check_client_version(&self) {
+        let rollup_boost_version = json!({
+            "code": "RB",
+            "name": "rollup-boost",
+            "version": env!("CARGO_PKG_VERSION"),
+            "commit": "0x00000000",
+        });
+        let client_version = match self
+            .builder
+            .auth_client
+            .get_client_version_v1(rollup_boost_version)
+            .await
+        {
+            Ok(client_version) => client_version,
+            Err(e) => {
+                // Unreachable builders are detected by the engine API calls
+                debug!(message = "error calling get_client_version from builder", "url" = ?self.builder.auth_rpc, "error" = %e);
+                return;
+            }
+        };
+
+        let previous = self
+            .client_version
+            .lock()
+            .await
+            .replace(client_version.clone());
+        if previous.is_some_and(|previous| previous != client_version) {
+            info!(message = "builder client version changed, replaying miner settings", "url" = ?self.builder.auth_rpc, "client_version" = %client_version);
+            self.replay().await;
+        }
+    }
+
+    /// Sends the last call of each `miner_` method to the builder.
+    async fn replay(&self) {
+        let calls = self.miner_settings.lock().await.calls.clone();
+        for (method, params) in calls {
+            let success = match self.call(&method, params).await {
+                Ok(_) => {
+                    info!(message = "replayed miner setting to builder", "url" = ?self.builder.auth_rpc, "method" = %method);
+                    true
+                }
+                Err(e) => {
+                    error!(message = "error replaying miner setting to builder", "url" = ?self.builder.auth_rpc, "method" = %method, "error" = %e);
+                    false
+                }
+            };
+            if let Some(metrics) = &self.metrics {
+                metrics.increment_builder_miner_settings_replay(
+                    self.builder.auth_rpc.to_string(),
+                    success,
+                );
+            }
+        }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> eyre::Result<Value> {
+        let mut array_params = ArrayParams::new();
+        match params {
+            Value::Array(values) => {
+                for value in values {
+                    array_params.insert(value)?;
+                }
+            }
+            Value::Null => {}
+            params => {
+                warn!(message = "replaying miner setting with non-array params", "url" = ?self.builder.auth_rpc, "method" = %method);
+                array_params.insert(params)?;
+            }
+        }
+        Ok(self
+            .builder
+            .auth_client
+            .request(method, array_params)
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::PayloadSource;
+    use alloy_rpc_types_engine::JwtSecret;
+    use http::Uri;
+    use jsonrpsee::server::Server;
+    use jsonrpsee::RpcModule;
+
+    #[test]
+    fn test_record_miner_settings() {
+        let mut settings = MinerSettings::default();
+        settings.record("miner_setExtra", json!(["0x01"])).unwrap();
+        settings
+            .record("miner_setMaxDASize", json!(["0x3e8", "0x2710"]))
+            .unwrap();
+        settings.record("miner_setExtra", json!(["0x02"])).unwrap();
+
+        // only the last call of each method is kept
+        assert_eq!(settings.calls.len(), 2);
+        assert_eq!(settings.calls["miner_setExtra"], json!(["0x02"]));
+        assert_eq!(settings.limits.max_da_tx_size, Some(1000));
+        assert_eq!(settings.limits.max_da_block_size, Some(10000));
+    }
+
+    #[tokio::test]
+    async fn test_replay_on_recovery() -> eyre::Result<()> {
+        let calls = Arc::new(std::sync::Mutex::new(vec![]));
+        let mut module = RpcModule::new(calls.clone());
+        module.register_method("miner_setExtra", |params, calls, _| {
+            calls.lock().unwrap().push(params.parse::<Value>().unwrap());
+            true
+        })?;
+        let server = Server::builder().build("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        let handle = server.start(module);
+
+        let builder = ExecutionClient::new(
+            format!("http://{addr}").parse::<Uri>()?,
+            JwtSecret::random(),
+            1000,
+            None,
+            PayloadSource::Builder,
+        )?;
+        let miner_settings = Arc::new(Mutex::new(MinerSettings::default()));
+        miner_settings
+            .lock()
+            .await
+            .record("miner_setExtra", json!(["0x01"]))?;
+        let recovery = Arc::new(BuilderRecovery::new(builder, miner_settings, None));
+
+        // a success without a prior failure is not a recovery
+        recovery.record_result(true);
+        recovery.record_result(false);
+        recovery.record_result(true);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(*calls.lock().unwrap(), vec![json!(["0x01"])]);
+
+        handle.stop()?;
+        Ok(())
+    }
+}
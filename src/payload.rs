@@ -0,0 +1,185 @@
+use alloy_primitives::{Bytes, B256, U256};
+use alloy_rpc_types_engine::{
+    ExecutionPayload, ExecutionPayloadFieldV2, ExecutionPayloadInputV2, ExecutionPayloadV2,
+    ExecutionPayloadV3,
+};
+use op_alloy_rpc_types_engine::{OpExecutionPayloadEnvelopeV3, OpExecutionPayloadEnvelopeV4};
+use serde::{Deserialize, Serialize};
+
+/// Version of the `engine_getPayload`/`engine_newPayload` methods used for a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadVersion {
+    V2,
+    V3,
+    V4,
+}
+
+impl std::fmt::Display for PayloadVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PayloadVersion::V2 => write!(f, "v2"),
+            PayloadVersion::V3 => write!(f, "v3"),
+            PayloadVersion::V4 => write!(f, "v4"),
+        }
+    }
+}
+
+/// Response of an `engine_getPayloadV2` call on chains that have not activated Ecotone.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OpExecutionPayloadEnvelopeV2 {
+    pub execution_payload: ExecutionPayloadFieldV2,
+    pub block_value: U256,
+}
+
+/// Response of an `engine_getPayload` call for any of the supported versions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpExecutionPayloadEnvelope {
+    V2(OpExecutionPayloadEnvelopeV2),
+    V3(OpExecutionPayloadEnvelopeV3),
+    V4(OpExecutionPayloadEnvelopeV4),
+}
+
+impl OpExecutionPayloadEnvelope {
+    pub fn execution_payload(&self) -> ExecutionPayload {
+        match self {
+            OpExecutionPayloadEnvelope::V2(envelope) => match envelope.execution_payload.clone() {
+                ExecutionPayloadFieldV2::V1(payload) => ExecutionPayload::V1(payload),
+                ExecutionPayloadFieldV2::V2(payload) => ExecutionPayload::V2(payload),
+            },
+            OpExecutionPayloadEnvelope::V3(envelope) => {
+                ExecutionPayload::from(envelope.execution_payload.clone())
+            }
+            OpExecutionPayloadEnvelope::V4(envelope) => {
+                ExecutionPayload::from(envelope.execution_payload.clone())
+            }
+        }
+    }
+
+    pub fn block_hash(&self) -> B256 {
+        self.execution_payload().block_hash()
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.execution_payload().block_number()
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.execution_payload().as_v1().timestamp
+    }
+
+    pub fn gas_used(&self) -> u64 {
+        self.execution_payload().as_v1().gas_used
+    }
+
+    pub fn transaction_count(&self) -> usize {
+        self.execution_payload().as_v1().transactions.len()
+    }
+
+    pub fn block_value(&self) -> U256 {
+        match self {
+            OpExecutionPayloadEnvelope::V2(envelope) => envelope.block_value,
+            OpExecutionPayloadEnvelope::V3(envelope) => envelope.block_value,
+            OpExecutionPayloadEnvelope::V4(envelope) => envelope.block_value,
+        }
+    }
+}
+
+/// Parameters of an `engine_newPayloadV3` call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewPayloadV3 {
+    pub execution_payload: ExecutionPayloadV3,
+    pub versioned_hashes: Vec<B256>,
+    pub parent_beacon_block_root: B256,
+}
+
+/// Parameters of an `engine_newPayloadV4` call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewPayloadV4 {
+    pub execution_payload: ExecutionPayloadV3,
+    pub versioned_hashes: Vec<B256>,
+    pub parent_beacon_block_root: B256,
+    pub execution_requests: Vec<Bytes>,
+}
+
+/// Parameters of an `engine_newPayload` call for any of the supported versions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NewPayload {
+    V2(ExecutionPayloadInputV2),
+    V3(NewPayloadV3),
+    V4(NewPayloadV4),
+}
+
+impl NewPayload {
+    pub fn version(&self) -> PayloadVersion {
+        match self {
+            NewPayload::V2(_) => PayloadVersion::V2,
+            NewPayload::V3(_) => PayloadVersion::V3,
+            NewPayload::V4(_) => PayloadVersion::V4,
+        }
+    }
+
+    pub fn execution_payload(&self) -> ExecutionPayload {
+        match self {
+            NewPayload::V2(new_payload) => match new_payload.withdrawals.clone() {
+                Some(withdrawals) => ExecutionPayload::V2(ExecutionPayloadV2 {
+                    payload_inner: new_payload.execution_payload.clone(),
+                    withdrawals,
+                }),
+                None => ExecutionPayload::V1(new_payload.execution_payload.clone()),
+            },
+            NewPayload::V3(new_payload) => {
+                ExecutionPayload::from(new_payload.execution_payload.clone())
+            }
+            NewPayload::V4(new_payload) => {
+                ExecutionPayload::from(new_payload.execution_payload.clone())
+            }
+        }
+    }
+
+    pub fn block_hash(&self) -> B256 {
+        self.execution_payload().block_hash()
+    }
+
+    pub fn parent_hash(&self) -> B256 {
+        self.execution_payload().parent_hash()
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.execution_payload().block_number()
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.execution_payload().as_v1().timestamp
+    }
+}
+
+/// Builds the `engine_newPayload` call used to validate a payload returned by `engine_getPayload`.
+/// OP Stack blocks do not carry blobs, so the versioned hashes are always empty.
+impl From<OpExecutionPayloadEnvelope> for NewPayload {
+    fn from(envelope: OpExecutionPayloadEnvelope) -> Self {
+        match envelope {
+            OpExecutionPayloadEnvelope::V2(envelope) => match envelope.execution_payload {
+                ExecutionPayloadFieldV2::V1(payload) => NewPayload::V2(ExecutionPayloadInputV2 {
+                    execution_payload: payload,
+                    withdrawals: None,
+                }),
+                ExecutionPayloadFieldV2::V2(payload) => NewPayload::V2(ExecutionPayloadInputV2 {
+                    execution_payload: payload.payload_inner,
+                    withdrawals: Some(payload.withdrawals),
+                }),
+            },
+            OpExecutionPayloadEnvelope::V3(envelope) => NewPayload::V3(NewPayloadV3 {
+                execution_payload: envelope.execution_payload,
+                versioned_hashes: vec![],
+                parent_beacon_block_root: envelope.parent_beacon_block_root,
+            }),
+            OpExecutionPayloadEnvelope::V4(envelope) => NewPayload::V4(NewPayloadV4 {
+                execution_payload: envelope.execution_payload,
+                versioned_hashes: vec![],
+                parent_beacon_block_root: envelope.parent_beacon_block_root,
+                execution_requests: envelope.execution_requests,
+            }),
+        }
+    }
+}
@@ -1,60 +1,63 @@
-use crate::auth_layer::secret_to_bearer_header;
 use crate::metrics::ServerMetrics;
+use crate::miner_settings::MinerSettings;
+use crate::routing::{RouteAction, RoutingTable};
 use crate::server::PayloadSource;
+use crate::state::StateNotifier;
+use crate::transport::UpstreamClient;
 use alloy_rpc_types_engine::JwtSecret;
-use http::header::AUTHORIZATION;
-use http::{StatusCode, Uri};
-use hyper_rustls::HttpsConnector;
-use hyper_util::client::legacy::connect::HttpConnector;
-use hyper_util::client::legacy::Client;
-use hyper_util::rt::TokioExecutor;
+use futures::future::Either;
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE, UPGRADE};
+use http::{HeaderValue, StatusCode, Uri};
 use jsonrpsee::core::{http_helpers, BoxError};
 use jsonrpsee::http_client::{HttpBody, HttpRequest, HttpResponse};
+use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use jsonrpsee::types::{ErrorCode, ErrorObject, ErrorObjectOwned, Id, Request};
+use jsonrpsee::{MethodResponse, ResponsePayload};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Instant;
 use std::{future::Future, pin::Pin};
+use tokio::sync::Mutex;
 use tower::{Layer, Service};
 use tracing::{debug, error, info, warn};
 
-const MULTIPLEX_METHODS: [&str; 4] = [
-    "engine_",
-    "eth_sendRawTransactionConditional",
-    "eth_sendRawTransaction",
-    "miner_",
-];
-const FORWARD_REQUESTS: [&str; 6] = [
-    "eth_sendRawTransaction",
-    "eth_sendRawTransactionConditional",
-    "miner_setExtra",
-    "miner_setGasPrice",
-    "miner_setGasLimit",
-    "miner_setMaxDASize",
-];
-
 #[derive(Debug, Clone)]
 pub struct ProxyLayer {
-    l2_auth_uri: Uri,
-    l2_auth_secret: JwtSecret,
-    builder_auth_uri: Uri,
-    builder_auth_secret: JwtSecret,
-    metrics: Option<Arc<ServerMetrics>>,
+    forwarder: Forwarder,
+    routing_table: Arc<RoutingTable>,
 }
 
 impl ProxyLayer {
     pub fn new(
         l2_auth_uri: Uri,
         l2_auth_secret: JwtSecret,
-        builder_auth_uri: Uri,
-        builder_auth_secret: JwtSecret,
+        builder_auths: Vec<(Uri, JwtSecret)>,
+        miner_settings: Arc<Mutex<MinerSettings>>,
+        state_notifier: StateNotifier,
+        routing_table: Arc<RoutingTable>,
         metrics: Option<Arc<ServerMetrics>>,
     ) -> Self {
         ProxyLayer {
-            l2_auth_uri,
-            l2_auth_secret,
-            builder_auth_uri,
-            builder_auth_secret,
-            metrics,
+            forwarder: Forwarder {
+                client: UpstreamClient::default(),
+                l2_auth_uri,
+                l2_auth_secret,
+                builder_auths,
+                miner_settings,
+                state_notifier,
+                metrics,
+            },
+            routing_table,
+        }
+    }
+
+    /// RPC middleware routing the calls of the WebSocket and IPC connections like the proxy
+    /// layer, it shares the upstream connections of the proxy layer.
+    pub fn rpc_layer(&self) -> ProxyRpcLayer {
+        ProxyRpcLayer {
+            forwarder: self.forwarder.clone(),
+            routing_table: self.routing_table.clone(),
         }
     }
 }
@@ -63,22 +66,10 @@ impl<S> Layer<S> for ProxyLayer {
     type Service = ProxyService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        let connector = hyper_rustls::HttpsConnectorBuilder::new()
-            .with_native_roots()
-            .expect("no native root CA certificates found")
-            .https_or_http()
-            .enable_http1()
-            .enable_http2()
-            .build();
-
         ProxyService {
             inner,
-            client: Client::builder(TokioExecutor::new()).build(connector),
-            l2_auth_uri: self.l2_auth_uri.clone(),
-            l2_auth_secret: self.l2_auth_secret,
-            builder_auth_uri: self.builder_auth_uri.clone(),
-            builder_auth_secret: self.builder_auth_secret,
-            metrics: self.metrics.clone(),
+            forwarder: self.forwarder.clone(),
+            routing_table: self.routing_table.clone(),
         }
     }
 }
@@ -86,12 +77,8 @@ impl<S> Layer<S> for ProxyLayer {
 #[derive(Clone)]
 pub struct ProxyService<S> {
     inner: S,
-    client: Client<HttpsConnector<HttpConnector>, HttpBody>,
-    l2_auth_uri: Uri,
-    l2_auth_secret: JwtSecret,
-    builder_auth_uri: Uri,
-    builder_auth_secret: JwtSecret,
-    metrics: Option<Arc<ServerMetrics>>,
+    forwarder: Forwarder,
+    routing_table: Arc<RoutingTable>,
 }
 
 impl<S> Service<HttpRequest<HttpBody>> for ProxyService<S>
@@ -115,88 +102,589 @@ where
             return Box::pin(async { Ok(Self::Response::new(HttpBody::from("OK"))) });
         }
 
-        let client = self.client.clone();
+        let service = self.clone();
         let mut inner = self.inner.clone();
-        let builder_uri = self.builder_auth_uri.clone();
-        let builder_secret = self.builder_auth_secret;
-        let l2_uri = self.l2_auth_uri.clone();
-        let l2_secret = self.l2_auth_secret;
-        let metrics = self.metrics.clone();
+
+        // The calls of the WebSocket connections are routed by the `ProxyRpcLayer`
+        if is_websocket_upgrade(&req) {
+            return Box::pin(async move { inner.call(req).await.map_err(Into::into) });
+        }
+
+        let forwarder = self.forwarder.clone();
+        let routing_table = self.routing_table.clone();
 
         #[derive(serde::Deserialize, Debug)]
         struct RpcRequest<'a> {
             #[serde(borrow)]
             method: &'a str,
+            #[serde(default)]
+            params: serde_json::Value,
+            #[serde(default)]
+            id: serde_json::Value,
         }
 
         let fut = async move {
             let (parts, body) = req.into_parts();
             let (body_bytes, _) = http_helpers::read_body(&parts.headers, body, u32::MAX).await?;
 
+            if let Ok(batch) = serde_json::from_slice::<Vec<serde_json::Value>>(&body_bytes) {
+                // An empty batch is left to the server, which rejects it
+                if !batch.is_empty() {
+                    return service.call_batch(parts, batch).await;
+                }
+            }
+
             // Deserialize the bytes to find the method
-            let method = serde_json::from_slice::<RpcRequest>(&body_bytes)?
-                .method
-                .to_string();
-
-            if MULTIPLEX_METHODS.iter().any(|&m| method.starts_with(m)) {
-                if FORWARD_REQUESTS.contains(&method.as_str()) {
-                    let builder_client = client.clone();
-                    let builder_req =
-                        HttpRequest::from_parts(parts.clone(), HttpBody::from(body_bytes.clone()));
-                    let builder_method = method.clone();
-                    let builder_metrics = metrics.clone();
-                    tokio::spawn(async move {
-                        let _ = forward_request(
-                            builder_client,
-                            builder_req,
-                            &builder_method,
-                            builder_uri,
-                            builder_secret,
-                            builder_metrics,
-                            PayloadSource::Builder,
-                        )
-                        .await;
-                    });
+            let request = serde_json::from_slice::<RpcRequest>(&body_bytes)?;
+            let method = request.method.to_string();
 
-                    let l2_req = HttpRequest::from_parts(parts, HttpBody::from(body_bytes));
-                    info!(target: "proxy::call", message = "proxying request to rollup-boost server", ?method);
-                    forward_request(
-                        client,
-                        l2_req,
-                        &method,
-                        l2_uri,
-                        l2_secret,
-                        metrics,
-                        PayloadSource::L2,
-                    )
-                    .await
-                } else {
+            match routing_table.action(&method) {
+                RouteAction::RollupBoost => {
                     let req = HttpRequest::from_parts(parts, HttpBody::from(body_bytes));
                     info!(target: "proxy::call", message = "proxying request to rollup-boost server", ?method);
                     inner.call(req).await.map_err(|e| e.into())
                 }
-            } else {
+                action => {
+                    forwarder
+                        .forward(
+                            action,
+                            parts,
+                            body_bytes,
+                            &method,
+                            request.params,
+                            request.id,
+                        )
+                        .await
+                }
+            }
+        };
+        Box::pin(fut)
+    }
+}
+
+impl<S> ProxyService<S>
+where
+    S: Service<HttpRequest<HttpBody>, Response = HttpResponse> + Send + Clone + 'static,
+    S::Error: Into<BoxError> + 'static,
+{
+    /// Splits a batch by route action, sends each part to its destination as a batch of its own and
+    /// reassembles the responses in the order of the requests of the batch.
+    async fn call_batch(
+        self,
+        parts: http::request::Parts,
+        batch: Vec<serde_json::Value>,
+    ) -> Result<HttpResponse, BoxError> {
+        let mut parts = parts;
+        // The parts of the batch have their own length
+        parts.headers.remove(CONTENT_LENGTH);
+
+        let ProxyService {
+            mut inner,
+            forwarder,
+            routing_table,
+        } = self;
+        let Forwarder {
+            client,
+            l2_auth_uri,
+            l2_auth_secret,
+            builder_auths,
+            miner_settings,
+            state_notifier,
+            metrics,
+        } = forwarder;
+
+        let mut server_batch = vec![];
+        let mut l2_batch = vec![];
+        let mut builder_batch = vec![];
+        let mut multiplex_batch = vec![];
+        let mut rejected = vec![];
+        for request in &batch {
+            let method = request
+                .get("method")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default();
+            match routing_table.action(method) {
+                RouteAction::RollupBoost => server_batch.push(request.clone()),
+                RouteAction::Both => {
+                    let params = request.get("params").cloned().unwrap_or_default();
+                    record_miner_settings(&miner_settings, &state_notifier, method, params).await;
+                    multiplex_batch.push(request.clone());
+                    l2_batch.push(request.clone());
+                }
+                RouteAction::Builder => {
+                    let params = request.get("params").cloned().unwrap_or_default();
+                    record_miner_settings(&miner_settings, &state_notifier, method, params).await;
+                    builder_batch.push(request.clone());
+                }
+                RouteAction::L2 => l2_batch.push(request.clone()),
+                RouteAction::Reject => {
+                    if response_id(request).is_some() {
+                        rejected.push(rejected_response(
+                            request.get("id").cloned().unwrap_or_default(),
+                        ));
+                    }
+                }
+            }
+        }
+        info!(target: "proxy::call", message = "proxying batch request", "server_requests" = server_batch.len(), "l2_requests" = l2_batch.len(), "builder_requests" = builder_batch.len(), "multiplexed_requests" = multiplex_batch.len(), "rejected_requests" = rejected.len());
+
+        if !multiplex_batch.is_empty() {
+            spawn_builder_requests(
+                &client,
+                &parts,
+                &serde_json::to_vec(&multiplex_batch)?,
+                BATCH_METHOD,
+                builder_auths.clone(),
+                &metrics,
+            );
+        }
+
+        let server_fut = async {
+            if server_batch.is_empty() {
+                return Ok::<_, BoxError>(serde_json::Value::Array(vec![]));
+            }
+            let req = HttpRequest::from_parts(
+                parts.clone(),
+                HttpBody::from(serde_json::to_vec(&server_batch)?),
+            );
+            let response = inner.call(req).await.map_err(Into::<BoxError>::into)?;
+            read_json_body(response).await
+        };
+        let l2_fut = async {
+            if l2_batch.is_empty() {
+                return Ok::<_, BoxError>(serde_json::Value::Array(vec![]));
+            }
+            let req = HttpRequest::from_parts(
+                parts.clone(),
+                HttpBody::from(serde_json::to_vec(&l2_batch)?),
+            );
+            let response = forward_request(
+                client.clone(),
+                req,
+                BATCH_METHOD,
+                l2_auth_uri.clone(),
+                l2_auth_secret,
+                metrics.clone(),
+                PayloadSource::L2,
+            )
+            .await?;
+            read_json_body(response).await
+        };
+        let builder_fut = async {
+            if builder_batch.is_empty() {
+                return Ok::<_, BoxError>(serde_json::Value::Array(vec![]));
+            }
+            let response = forward_builder_request(
+                client.clone(),
+                parts.clone(),
+                serde_json::to_vec(&builder_batch)?,
+                BATCH_METHOD,
+                builder_auths.clone(),
+                metrics.clone(),
+            )
+            .await?;
+            read_json_body(response).await
+        };
+        let (server_response, l2_response, builder_response) =
+            tokio::join!(server_fut, l2_fut, builder_fut);
+
+        let responses = reassemble_batch(
+            &batch,
+            batch_responses(&server_batch, server_response)
+                .into_iter()
+                .chain(batch_responses(&l2_batch, l2_response))
+                .chain(batch_responses(&builder_batch, builder_response))
+                .chain(rejected),
+        );
+
+        // A batch of notifications only has no response
+        if responses.is_empty() {
+            return Ok(HttpResponse::new(HttpBody::empty()));
+        }
+        json_response(&serde_json::Value::Array(responses))
+    }
+}
+
+/// Destinations of the requests routed away from the rollup-boost server.
+#[derive(Debug, Clone)]
+struct Forwarder {
+    client: UpstreamClient,
+    l2_auth_uri: Uri,
+    l2_auth_secret: JwtSecret,
+    builder_auths: Vec<(Uri, JwtSecret)>,
+    miner_settings: Arc<Mutex<MinerSettings>>,
+    state_notifier: StateNotifier,
+    metrics: Option<Arc<ServerMetrics>>,
+}
+
+impl Forwarder {
+    /// Forwards a request to the destination of its route action, the rejected requests are
+    /// answered with an error.
+    async fn forward(
+        &self,
+        action: RouteAction,
+        parts: http::request::Parts,
+        body_bytes: Vec<u8>,
+        method: &str,
+        params: serde_json::Value,
+        id: serde_json::Value,
+    ) -> Result<HttpResponse, BoxError> {
+        match action {
+            RouteAction::Both => {
+                record_miner_settings(&self.miner_settings, &self.state_notifier, method, params)
+                    .await;
+                spawn_builder_requests(
+                    &self.client,
+                    &parts,
+                    &body_bytes,
+                    method,
+                    self.builder_auths.clone(),
+                    &self.metrics,
+                );
+
+                let l2_req = HttpRequest::from_parts(parts, HttpBody::from(body_bytes));
+                info!(target: "proxy::call", message = "proxying request to rollup-boost server", ?method);
+                forward_request(
+                    self.client.clone(),
+                    l2_req,
+                    method,
+                    self.l2_auth_uri.clone(),
+                    self.l2_auth_secret,
+                    self.metrics.clone(),
+                    PayloadSource::L2,
+                )
+                .await
+            }
+            RouteAction::Builder => {
+                record_miner_settings(&self.miner_settings, &self.state_notifier, method, params)
+                    .await;
+                info!(target: "proxy::call", message = "proxying request to the builders", ?method);
+                forward_builder_request(
+                    self.client.clone(),
+                    parts,
+                    body_bytes,
+                    method,
+                    self.builder_auths.clone(),
+                    self.metrics.clone(),
+                )
+                .await
+            }
+            RouteAction::L2 => {
                 let req = HttpRequest::from_parts(parts, HttpBody::from(body_bytes));
                 forward_request(
-                    client,
+                    self.client.clone(),
                     req,
-                    &method,
-                    l2_uri,
-                    l2_secret,
-                    metrics,
+                    method,
+                    self.l2_auth_uri.clone(),
+                    self.l2_auth_secret,
+                    self.metrics.clone(),
                     PayloadSource::L2,
                 )
                 .await
             }
-        };
-        Box::pin(fut)
+            RouteAction::Reject => {
+                info!(target: "proxy::call", message = "rejecting request", ?method);
+                json_response(&rejected_response(id))
+            }
+            RouteAction::RollupBoost => Err("Request served by the rollup-boost server".into()),
+        }
+    }
+}
+
+/// Routes the calls of the WebSocket and IPC connections, which bypass the proxy layer. The HTTP
+/// calls reaching the server were already routed by the proxy layer and are all served by the
+/// server.
+#[derive(Debug, Clone)]
+pub struct ProxyRpcLayer {
+    forwarder: Forwarder,
+    routing_table: Arc<RoutingTable>,
+}
+
+impl<S> Layer<S> for ProxyRpcLayer {
+    type Service = ProxyRpcService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ProxyRpcService {
+            inner,
+            forwarder: self.forwarder.clone(),
+            routing_table: self.routing_table.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ProxyRpcService<S> {
+    inner: S,
+    forwarder: Forwarder,
+    routing_table: Arc<RoutingTable>,
+}
+
+impl<'a, S> RpcServiceT<'a> for ProxyRpcService<S>
+where
+    S: RpcServiceT<'a> + Send + Sync + Clone + 'static,
+{
+    type Future = Either<S::Future, Pin<Box<dyn Future<Output = MethodResponse> + Send + 'a>>>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let method = request.method_name().to_string();
+        let action = self.routing_table.action(&method);
+        if action == RouteAction::RollupBoost {
+            return Either::Left(self.inner.call(request));
+        }
+
+        let forwarder = self.forwarder.clone();
+        Either::Right(Box::pin(async move {
+            let params: Option<serde_json::Value> = request
+                .params
+                .as_ref()
+                .and_then(|params| serde_json::from_str(params.get()).ok());
+            let response = async {
+                let id = serde_json::to_value(&request.id)?;
+                let mut body = serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": method });
+                if let Some(params) = &params {
+                    body["params"] = params.clone();
+                }
+                let (parts, ()) = http::Request::post("/")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(())?
+                    .into_parts();
+                let response = forwarder
+                    .forward(
+                        action,
+                        parts,
+                        serde_json::to_vec(&body)?,
+                        &method,
+                        params.unwrap_or_default(),
+                        id,
+                    )
+                    .await?;
+                read_json_body(response).await
+            }
+            .await;
+            method_response(request.id, response)
+        }))
+    }
+}
+
+/// Whether the request opens a WebSocket connection.
+fn is_websocket_upgrade(req: &HttpRequest<HttpBody>) -> bool {
+    req.headers()
+        .get(UPGRADE)
+        .and_then(|upgrade| upgrade.to_str().ok())
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+}
+
+/// Response to a call of a WebSocket or IPC connection from the JSON-RPC response of its destination.
+fn method_response(id: Id<'_>, response: Result<serde_json::Value, BoxError>) -> MethodResponse {
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            return MethodResponse::error(
+                id,
+                ErrorObject::owned(ErrorCode::InternalError.code(), e.to_string(), None::<()>),
+            )
+        }
+    };
+    if let Some(result) = response.get("result") {
+        return MethodResponse::response(
+            id,
+            ResponsePayload::success(result.clone()),
+            u32::MAX as usize,
+        );
+    }
+    match response
+        .get("error")
+        .map(|error| serde_json::from_value::<ErrorObjectOwned>(error.clone()))
+    {
+        Some(Ok(error)) => MethodResponse::error(id, error),
+        _ => MethodResponse::error(
+            id,
+            ErrorObject::owned(
+                ErrorCode::InternalError.code(),
+                format!("Unexpected response: {response}"),
+                None::<()>,
+            ),
+        ),
+    }
+}
+
+/// Method reported in the metrics of the forwarded batches.
+const BATCH_METHOD: &str = "batch";
+
+/// Keeps the miner settings to replay them to recovering builders and check the builder blocks
+/// against the limits set by the batcher.
+async fn record_miner_settings(
+    miner_settings: &Mutex<MinerSettings>,
+    state_notifier: &StateNotifier,
+    method: &str,
+    params: serde_json::Value,
+) {
+    if method.starts_with("miner_") {
+        let result = miner_settings.lock().await.record(method, params);
+        state_notifier.notify();
+        if let Err(e) = result {
+            warn!(target: "proxy::call", message = "failed to parse miner settings", ?method, error = %e);
+        }
+    }
+}
+
+/// Forwards a request to every builder, returns the response of the first one.
+async fn forward_builder_request(
+    client: UpstreamClient,
+    parts: http::request::Parts,
+    body_bytes: Vec<u8>,
+    method: &str,
+    builder_auths: Vec<(Uri, JwtSecret)>,
+    metrics: Option<Arc<ServerMetrics>>,
+) -> Result<HttpResponse, BoxError> {
+    let mut builder_auths = builder_auths.into_iter();
+    let Some((builder_uri, builder_secret)) = builder_auths.next() else {
+        return Err("No builder to forward the request to".into());
+    };
+    spawn_builder_requests(
+        &client,
+        &parts,
+        &body_bytes,
+        method,
+        builder_auths.collect(),
+        &metrics,
+    );
+    let req = HttpRequest::from_parts(parts, HttpBody::from(body_bytes));
+    forward_request(
+        client,
+        req,
+        method,
+        builder_uri,
+        builder_secret,
+        metrics,
+        PayloadSource::Builder,
+    )
+    .await
+}
+
+/// Forwards a request to every builder in the background, their responses are ignored.
+fn spawn_builder_requests(
+    client: &UpstreamClient,
+    parts: &http::request::Parts,
+    body_bytes: &[u8],
+    method: &str,
+    builder_auths: Vec<(Uri, JwtSecret)>,
+    metrics: &Option<Arc<ServerMetrics>>,
+) {
+    for (builder_uri, builder_secret) in builder_auths {
+        let builder_client = client.clone();
+        let builder_req =
+            HttpRequest::from_parts(parts.clone(), HttpBody::from(body_bytes.to_vec()));
+        let builder_method = method.to_string();
+        let builder_metrics = metrics.clone();
+        tokio::spawn(async move {
+            let _ = forward_request(
+                builder_client,
+                builder_req,
+                &builder_method,
+                builder_uri,
+                builder_secret,
+                builder_metrics,
+                PayloadSource::Builder,
+            )
+            .await;
+        });
+    }
+}
+
+async fn read_json_body(response: HttpResponse) -> Result<serde_json::Value, BoxError> {
+    let (parts, body) = response.into_parts();
+    let (body_bytes, _) = http_helpers::read_body(&parts.headers, body, u32::MAX).await?;
+    if body_bytes.is_empty() {
+        // The response to a batch of notifications
+        return Ok(serde_json::Value::Array(vec![]));
+    }
+    Ok(serde_json::from_slice(&body_bytes)?)
+}
+
+/// Error returned for the methods rejected by the routing table.
+fn rejected_response(id: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": {
+            "code": ErrorCode::MethodNotFound.code(),
+            "message": "Method rejected by the routing table",
+        },
+        "id": id,
+    })
+}
+
+fn json_response(value: &serde_json::Value) -> Result<HttpResponse, BoxError> {
+    let mut response = HttpResponse::new(HttpBody::from(serde_json::to_vec(value)?));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    Ok(response)
+}
+
+/// Id of the response expected for a request of a batch, `None` for a notification. The
+/// invalid requests are answered with a `null` id.
+fn response_id(request: &serde_json::Value) -> Option<String> {
+    match request.get("id") {
+        Some(id) => Some(id.to_string()),
+        // A notification
+        None if request["method"].is_string() => None,
+        None => Some(serde_json::Value::Null.to_string()),
     }
 }
 
-/// Forwards an HTTP request to the `authrpc``, attaching the provided JWT authorization.
+/// Responses to a part of a batch. When the part failed as a whole, every request is answered
+/// with the error.
+fn batch_responses(
+    requests: &[serde_json::Value],
+    response: Result<serde_json::Value, BoxError>,
+) -> Vec<serde_json::Value> {
+    let error = match response {
+        Ok(serde_json::Value::Array(responses)) => return responses,
+        Ok(response) if response.get("error").is_some() => response["error"].clone(),
+        Ok(response) => serde_json::json!({
+            "code": ErrorCode::InternalError.code(),
+            "message": format!("Unexpected batch response: {response}"),
+        }),
+        Err(e) => serde_json::json!({
+            "code": ErrorCode::InternalError.code(),
+            "message": e.to_string(),
+        }),
+    };
+    requests
+        .iter()
+        .filter(|request| response_id(request).is_some())
+        .map(|request| {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "error": error,
+                "id": request.get("id").cloned().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Orders the responses like the requests of the batch, matching them by id.
+fn reassemble_batch(
+    batch: &[serde_json::Value],
+    responses: impl IntoIterator<Item = serde_json::Value>,
+) -> Vec<serde_json::Value> {
+    let mut responses_by_id: HashMap<String, VecDeque<serde_json::Value>> = HashMap::new();
+    for response in responses {
+        let id = response.get("id").cloned().unwrap_or_default().to_string();
+        responses_by_id.entry(id).or_default().push_back(response);
+    }
+    batch
+        .iter()
+        .filter_map(|request| responses_by_id.get_mut(&response_id(request)?)?.pop_front())
+        .collect()
+}
+
+/// Forwards a request to the `authrpc`, over HTTP, WebSocket or IPC, attaching the provided JWT
+/// authorization.
 async fn forward_request(
-    client: Client<HttpsConnector<HttpConnector>, HttpBody>,
-    mut req: http::Request<HttpBody>,
+    client: UpstreamClient,
+    req: http::Request<HttpBody>,
     method: &str,
     uri: Uri,
     auth: JwtSecret,
@@ -204,9 +692,6 @@ async fn forward_request(
     source: PayloadSource,
 ) -> Result<http::Response<HttpBody>, BoxError> {
     let start = Instant::now();
-    *req.uri_mut() = uri.clone();
-    req.headers_mut()
-        .insert(AUTHORIZATION, secret_to_bearer_header(&auth));
 
     debug!(
         target: "proxy::forward_request",
@@ -215,19 +700,8 @@ async fn forward_request(
         ?req,
     );
 
-    match client.request(req).await {
-        Ok(resp) => {
-            let (parts, body) = resp.into_parts();
-            let (body_bytes, _) = http_helpers::read_body(&parts.headers, body, u32::MAX)
-                .await
-                .map_err(|e| {
-                    warn!(
-                        target: "proxy::forward_request",
-                        message = "error reading body",
-                        error = %e,
-                    );
-                    e
-                })?;
+    match client.send(req, &uri, auth).await {
+        Ok((parts, body_bytes)) => {
             let rpc_status_code = parse_response_code(&body_bytes);
             record_metrics(
                 metrics,
@@ -260,7 +734,7 @@ async fn forward_request(
                 source,
             )
             .await;
-            Err(e.into())
+            Err(e)
         }
     }
 }
@@ -306,6 +780,11 @@ fn parse_response_code(body_bytes: &[u8]) -> Option<String> {
         code: i32,
     }
 
+    // The responses of a batch each have their own code
+    if body_bytes.first() == Some(&b'[') {
+        return None;
+    }
+
     // Safely try to deserialize, return empty string on failure
     serde_json::from_slice::<RpcResponse>(body_bytes)
                 .map_err(|e| {
@@ -318,13 +797,18 @@ fn parse_response_code(body_bytes: &[u8]) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::routing::RouteRule;
     use alloy_primitives::{hex, Bytes, B256, U128, U64};
     use alloy_rpc_types_engine::JwtSecret;
     use alloy_rpc_types_eth::erc4337::ConditionalOptions;
     use http_body_util::BodyExt;
     use hyper::service::service_fn;
-    use hyper_util::rt::TokioIo;
+    use hyper_util::client::legacy::connect::HttpConnector;
+    use hyper_util::client::legacy::Client;
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use jsonrpsee::server::middleware::rpc::RpcServiceBuilder;
     use jsonrpsee::server::Server;
+    use jsonrpsee::ws_client::WsClientBuilder;
     use jsonrpsee::{
         core::{client::ClientT, ClientError},
         http_client::HttpClient,
@@ -350,7 +834,9 @@ mod tests {
         builder: MockHttpServer,
         l2: MockHttpServer,
         server_handle: ServerHandle,
+        server_addr: SocketAddr,
         proxy_client: HttpClient,
+        miner_settings: Arc<tokio::sync::Mutex<MinerSettings>>,
     }
 
     impl Drop for TestHarness {
@@ -361,21 +847,35 @@ mod tests {
 
     impl TestHarness {
         async fn new() -> eyre::Result<Self> {
+            Self::with_routing_table(RoutingTable::default()).await
+        }
+
+        async fn with_routing_table(routing_table: RoutingTable) -> eyre::Result<Self> {
             let builder = MockHttpServer::serve().await?;
             let l2 = MockHttpServer::serve().await?;
-            let middleware = tower::ServiceBuilder::new().layer(ProxyLayer::new(
+            let miner_settings = Arc::new(tokio::sync::Mutex::new(MinerSettings::default()));
+            let proxy_layer = ProxyLayer::new(
                 format!("http://{}:{}", l2.addr.ip(), l2.addr.port()).parse::<Uri>()?,
                 JwtSecret::random(),
-                format!("http://{}:{}", builder.addr.ip(), builder.addr.port()).parse::<Uri>()?,
-                JwtSecret::random(),
+                vec![(
+                    format!("http://{}:{}", builder.addr.ip(), builder.addr.port())
+                        .parse::<Uri>()?,
+                    JwtSecret::random(),
+                )],
+                miner_settings.clone(),
+                StateNotifier::default(),
+                Arc::new(routing_table),
                 None,
-            ));
+            );
+            let rpc_middleware = RpcServiceBuilder::new().layer(proxy_layer.rpc_layer());
+            let middleware = tower::ServiceBuilder::new().layer(proxy_layer);
 
             let temp_listener = TcpListener::bind("0.0.0.0:0").await?;
             let server_addr = temp_listener.local_addr()?;
             drop(temp_listener);
             let server = Server::builder()
                 .set_http_middleware(middleware.clone())
+                .set_rpc_middleware(rpc_middleware)
                 .build(server_addr)
                 .await?;
 
@@ -386,13 +886,17 @@ mod tests {
                 server_addr.port()
             ))?;
 
-            let server_handle = server.start(RpcModule::new(()));
+            let mut module = RpcModule::new(());
+            module.register_method("engine_method", |_, _, _| "engine response")?;
+            let server_handle = server.start(module);
 
             Ok(Self {
                 builder,
                 l2,
                 server_handle,
+                server_addr,
                 proxy_client,
+                miner_settings,
             })
         }
     }
@@ -479,40 +983,46 @@ mod tests {
 
             requests.lock().unwrap().push(request_body.clone());
 
-            let method = request_body["method"].as_str().unwrap_or_default();
+            let response = match &request_body {
+                serde_json::Value::Array(batch) => {
+                    serde_json::Value::Array(batch.iter().map(Self::response).collect())
+                }
+                request => Self::response(request),
+            };
 
-            let response = match method {
+            Ok(hyper::Response::new(response.to_string()))
+        }
+
+        fn response(request: &serde_json::Value) -> serde_json::Value {
+            let method = request["method"].as_str().unwrap_or_default();
+
+            match method {
                 "eth_sendRawTransaction" | "eth_sendRawTransactionConditional" => json!({
                     "jsonrpc": "2.0",
                     "result": format!("{}", B256::from([1; 32])),
-                    "id": request_body["id"]
+                    "id": request["id"]
                 }),
                 "miner_setMaxDASize" | "miner_setGasLimit" | "miner_setGasPrice"
                 | "miner_setExtra" => {
                     json!({
                         "jsonrpc": "2.0",
                         "result": true,
-                        "id": request_body["id"]
+                        "id": request["id"]
                     })
                 }
                 "mock_forwardedMethod" => {
                     json!({
                         "jsonrpc": "2.0",
                         "result": "forwarded response",
-                        "id": request_body["id"]
+                        "id": request["id"]
                     })
                 }
-                _ => {
-                    let error_response = json!({
-                        "jsonrpc": "2.0",
-                        "error": { "code": -32601, "message": "Method not found" },
-                        "id": request_body["id"]
-                    });
-                    return Ok(hyper::Response::new(error_response.to_string()));
-                }
-            };
-
-            Ok(hyper::Response::new(response.to_string()))
+                _ => json!({
+                    "jsonrpc": "2.0",
+                    "error": { "code": -32601, "message": "Method not found" },
+                    "id": request["id"]
+                }),
+            }
         }
     }
 
@@ -629,7 +1139,15 @@ mod tests {
         .parse::<Uri>()
         .unwrap();
 
-        let proxy_layer = ProxyLayer::new(l2_auth_uri.clone(), jwt, l2_auth_uri, jwt, None);
+        let proxy_layer = ProxyLayer::new(
+            l2_auth_uri.clone(),
+            jwt,
+            vec![(l2_auth_uri, jwt)],
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            None,
+        );
 
         // Create a layered server
         let server = ServerBuilder::default()
@@ -690,6 +1208,15 @@ mod tests {
         assert_eq!(l2_req["params"][0], expected_tx_size);
         assert_eq!(builder_req["params"][1], expected_block_size);
 
+        // Assert the settings and limits were recorded
+        let miner_settings = test_harness.miner_settings.lock().await;
+        assert_eq!(
+            miner_settings.calls[expected_method],
+            json!([expected_tx_size, expected_block_size])
+        );
+        assert_eq!(miner_settings.limits.max_da_tx_size, Some(u64::MAX));
+        assert_eq!(miner_settings.limits.max_da_block_size, Some(u64::MAX));
+
         Ok(())
     }
 
@@ -898,4 +1425,162 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_batch_request() -> eyre::Result<()> {
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        let test_harness = TestHarness::new().await?;
+
+        let batch = json!([
+            { "jsonrpc": "2.0", "id": "a", "method": "mock_forwardedMethod", "params": [] },
+            { "jsonrpc": "2.0", "id": 1, "method": "engine_method", "params": [] },
+            { "jsonrpc": "2.0", "method": "miner_setGasLimit", "params": ["0x1c9c380"] },
+            { "jsonrpc": "2.0", "id": 2, "method": "eth_sendRawTransaction", "params": ["0x1234"] },
+            { "jsonrpc": "2.0", "id": 3, "method": "engine_method", "params": [] },
+        ]);
+        let client: Client<HttpConnector, HttpBody> =
+            Client::builder(TokioExecutor::new()).build_http();
+        let request = http::Request::post(format!("http://{}", test_harness.server_addr))
+            .header(CONTENT_TYPE, "application/json")
+            .body(HttpBody::from(batch.to_string()))?;
+        let response = client.request(request).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await?.to_bytes();
+        let responses: serde_json::Value = serde_json::from_slice(&body)?;
+
+        // The responses follow the order of the batch, without the notification
+        assert_eq!(
+            responses,
+            json!([
+                { "jsonrpc": "2.0", "result": "forwarded response", "id": "a" },
+                { "jsonrpc": "2.0", "result": "engine response", "id": 1 },
+                { "jsonrpc": "2.0", "result": format!("{}", B256::from([1; 32])), "id": 2 },
+                { "jsonrpc": "2.0", "result": "engine response", "id": 3 },
+            ])
+        );
+
+        // Assert the l2 received the requests not served by rollup-boost in a single batch
+        let l2_requests = test_harness.l2.requests.lock().unwrap().clone();
+        assert_eq!(l2_requests.len(), 1);
+        let l2_methods: Vec<_> = l2_requests[0]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|request| request["method"].clone())
+            .collect();
+        assert_eq!(
+            l2_methods,
+            vec![
+                "mock_forwardedMethod",
+                "miner_setGasLimit",
+                "eth_sendRawTransaction"
+            ]
+        );
+
+        // Assert the builder received the transaction and the miner settings
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let builder_requests = test_harness.builder.requests.lock().unwrap().clone();
+        assert_eq!(builder_requests.len(), 1);
+        assert_eq!(builder_requests[0].as_array().unwrap().len(), 2);
+        assert_eq!(builder_requests[0][0]["method"], "miner_setGasLimit");
+        assert_eq!(builder_requests[0][1]["method"], "eth_sendRawTransaction");
+
+        let miner_settings = test_harness.miner_settings.lock().await;
+        assert_eq!(miner_settings.limits.gas_limit, Some(30_000_000));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_routing_table() -> eyre::Result<()> {
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        let mut routing_table = RoutingTable::default();
+        routing_table.rules.push(RouteRule {
+            method: "mock_forwardedMethod".to_string(),
+            action: RouteAction::Builder,
+        });
+        routing_table.rules.push(RouteRule {
+            method: "admin_*".to_string(),
+            action: RouteAction::Reject,
+        });
+        let test_harness = TestHarness::with_routing_table(routing_table).await?;
+
+        // A method routed to the builders gets the builder response
+        let response = test_harness
+            .proxy_client
+            .request::<String, _>("mock_forwardedMethod", rpc_params![])
+            .await?;
+        assert_eq!(response, "forwarded response");
+        assert_eq!(test_harness.builder.requests.lock().unwrap().len(), 1);
+        assert!(test_harness.l2.requests.lock().unwrap().is_empty());
+
+        // A rejected method is not forwarded
+        let response = test_harness
+            .proxy_client
+            .request::<String, _>("admin_addPeer", rpc_params![])
+            .await;
+        assert!(matches!(
+            response.unwrap_err(),
+            ClientError::Call(e) if e.code() == ErrorCode::MethodNotFound.code()
+                && e.message() == "Method rejected by the routing table"
+        ));
+        assert_eq!(test_harness.builder.requests.lock().unwrap().len(), 1);
+        assert!(test_harness.l2.requests.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_websocket_request() -> eyre::Result<()> {
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        let mut routing_table = RoutingTable::default();
+        routing_table.rules.push(RouteRule {
+            method: "admin_*".to_string(),
+            action: RouteAction::Reject,
+        });
+        let test_harness = TestHarness::with_routing_table(routing_table).await?;
+        let ws_client = WsClientBuilder::new()
+            .build(format!("ws://{}", test_harness.server_addr))
+            .await?;
+
+        // The calls of the connection are routed like the HTTP requests
+        let response = ws_client
+            .request::<String, _>("engine_method", rpc_params![])
+            .await?;
+        assert_eq!(response, "engine response");
+
+        let response = ws_client
+            .request::<String, _>("mock_forwardedMethod", rpc_params![])
+            .await?;
+        assert_eq!(response, "forwarded response");
+        assert_eq!(test_harness.l2.requests.lock().unwrap().len(), 1);
+
+        let response = ws_client
+            .request::<String, _>("eth_sendRawTransaction", ("0x1234",))
+            .await?;
+        assert_eq!(response, format!("{}", B256::from([1; 32])));
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let builder_requests = test_harness.builder.requests.lock().unwrap().clone();
+        assert_eq!(builder_requests.len(), 1);
+        assert_eq!(builder_requests[0]["method"], "eth_sendRawTransaction");
+        assert_eq!(builder_requests[0]["params"][0], "0x1234");
+
+        let response = ws_client
+            .request::<String, _>("non_existent_method", rpc_params![])
+            .await;
+        assert!(matches!(
+            response.unwrap_err(),
+            ClientError::Call(e) if e.code() == ErrorCode::MethodNotFound.code()
+        ));
+
+        let response = ws_client
+            .request::<String, _>("admin_addPeer", rpc_params![])
+            .await;
+        assert!(matches!(
+            response.unwrap_err(),
+            ClientError::Call(e) if e.message() == "Method rejected by the routing table"
+        ));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,104 @@
+use crate::payload::PayloadVersion;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Hardfork activation times of the chain, used to pick the engine API version for a block.
+///
+/// Deserializes both the op-node `rollup.json` (unknown fields are ignored) and a plain
+/// hardfork schedule that only lists the `*_time` fields.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RollupConfig {
+    /// Ecotone activation timestamp, enables the V3 engine API methods
+    #[serde(default)]
+    pub ecotone_time: Option<u64>,
+    /// Isthmus activation timestamp, enables the V4 `getPayload`/`newPayload` methods
+    #[serde(default)]
+    pub isthmus_time: Option<u64>,
+}
+
+impl RollupConfig {
+    pub fn from_file(path: &Path) -> eyre::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn is_ecotone_active(&self, timestamp: u64) -> bool {
+        self.ecotone_time.is_some_and(|time| timestamp >= time)
+    }
+
+    pub fn is_isthmus_active(&self, timestamp: u64) -> bool {
+        self.isthmus_time.is_some_and(|time| timestamp >= time)
+    }
+
+    /// Version of `engine_getPayload`/`engine_newPayload` for a block with the given timestamp.
+    pub fn payload_version(&self, timestamp: u64) -> PayloadVersion {
+        if self.is_isthmus_active(timestamp) {
+            PayloadVersion::V4
+        } else if self.is_ecotone_active(timestamp) {
+            PayloadVersion::V3
+        } else {
+            PayloadVersion::V2
+        }
+    }
+
+    /// Version of `engine_forkchoiceUpdated` for payload attributes with the given timestamp.
+    pub fn fork_choice_updated_version(&self, timestamp: u64) -> PayloadVersion {
+        if self.is_ecotone_active(timestamp) {
+            PayloadVersion::V3
+        } else {
+            PayloadVersion::V2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rollup_json() {
+        let config: RollupConfig = serde_json::from_str(
+            r#"{
+                "genesis": {
+                    "l2_time": 1686068903,
+                    "system_config": { "gasLimit": 30000000 }
+                },
+                "block_time": 2,
+                "regolith_time": 0,
+                "canyon_time": 1704992401,
+                "ecotone_time": 1710374401,
+                "l2_chain_id": 10
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            RollupConfig {
+                ecotone_time: Some(1710374401),
+                isthmus_time: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_version_selection() {
+        let config = RollupConfig {
+            ecotone_time: Some(100),
+            isthmus_time: Some(200),
+        };
+
+        assert_eq!(config.payload_version(99), PayloadVersion::V2);
+        assert_eq!(config.payload_version(100), PayloadVersion::V3);
+        assert_eq!(config.payload_version(199), PayloadVersion::V3);
+        assert_eq!(config.payload_version(200), PayloadVersion::V4);
+
+        assert_eq!(config.fork_choice_updated_version(99), PayloadVersion::V2);
+        assert_eq!(config.fork_choice_updated_version(100), PayloadVersion::V3);
+        assert_eq!(config.fork_choice_updated_version(200), PayloadVersion::V3);
+
+        // no hardforks scheduled
+        let config = RollupConfig::default();
+        assert_eq!(config.payload_version(u64::MAX), PayloadVersion::V2);
+    }
+}
@@ -0,0 +1,197 @@
+use eyre::bail;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Destination of the requests of a method.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteAction {
+    // Forwarded to the L2
+    L2,
+    // Forwarded to the builders, the response of the first builder is returned
+    Builder,
+    // Forwarded to the L2 and the builders, the L2 response is returned
+    Both,
+    // Served by rollup-boost
+    RollupBoost,
+    // Rejected with a JSON-RPC error
+    Reject,
+}
+
+impl std::fmt::Display for RouteAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RouteAction::L2 => write!(f, "l2"),
+            RouteAction::Builder => write!(f, "builder"),
+            RouteAction::Both => write!(f, "both"),
+            RouteAction::RollupBoost => write!(f, "rollup_boost"),
+            RouteAction::Reject => write!(f, "reject"),
+        }
+    }
+}
+
+/// Action of a method name, or of the methods starting with a prefix when it ends with `*`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RouteRule {
+    pub method: String,
+    pub action: RouteAction,
+}
+
+impl RouteRule {
+    fn new(method: &str, action: RouteAction) -> Self {
+        Self {
+            method: method.to_string(),
+            action,
+        }
+    }
+
+    /// How closely the rule matches the method, `None` if it does not. A method name matches
+    /// more closely than any prefix, and a longer prefix more closely than a shorter one.
+    fn specificity(&self, method: &str) -> Option<usize> {
+        match self.method.strip_suffix('*') {
+            Some(prefix) => method.starts_with(prefix).then_some(prefix.len()),
+            None => (self.method == method).then_some(usize::MAX),
+        }
+    }
+}
+
+/// Routing config file, its rules are added to the default routing table.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+struct RoutingConfig {
+    #[serde(default)]
+    rules: Vec<RouteRule>,
+    /// Action of the methods matching no rule
+    #[serde(default)]
+    default: Option<RouteAction>,
+}
+
+/// Routing of the requests by method, applied by the proxy layer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RoutingTable {
+    pub rules: Vec<RouteRule>,
+    /// Action of the methods matching no rule
+    pub default: RouteAction,
+}
+
+impl Default for RoutingTable {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                RouteRule::new("engine_*", RouteAction::RollupBoost),
+                RouteRule::new("eth_sendRawTransaction*", RouteAction::RollupBoost),
+                RouteRule::new("miner_*", RouteAction::RollupBoost),
+                RouteRule::new("eth_sendRawTransaction", RouteAction::Both),
+                RouteRule::new("eth_sendRawTransactionConditional", RouteAction::Both),
+                RouteRule::new("miner_setExtra", RouteAction::Both),
+                RouteRule::new("miner_setGasPrice", RouteAction::Both),
+                RouteRule::new("miner_setGasLimit", RouteAction::Both),
+                RouteRule::new("miner_setMaxDASize", RouteAction::Both),
+            ],
+            default: RouteAction::L2,
+        }
+    }
+}
+
+impl RoutingTable {
+    /// Reads a routing config file. Its rules are added to the default routing table, replacing
+    /// the default rules of the same method or prefix.
+    pub fn from_file(path: &Path) -> eyre::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let config: RoutingConfig = serde_json::from_str(&contents)?;
+
+        let mut methods = HashSet::new();
+        for rule in &config.rules {
+            if rule.method.is_empty() {
+                bail!("routing rule with an empty method");
+            }
+            if !methods.insert(rule.method.as_str()) {
+                bail!("duplicate routing rule for {}", rule.method);
+            }
+        }
+
+        let mut table = Self::default();
+        table
+            .rules
+            .retain(|rule| !methods.contains(rule.method.as_str()));
+        table.rules.extend(config.rules);
+        if let Some(default) = config.default {
+            table.default = default;
+        }
+        Ok(table)
+    }
+
+    pub fn action(&self, method: &str) -> RouteAction {
+        self.rules
+            .iter()
+            .filter_map(|rule| Some((rule.specificity(method)?, rule.action)))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(self.default, |(_, action)| action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_routing_table() {
+        let table = RoutingTable::default();
+        assert_eq!(
+            table.action("engine_forkchoiceUpdatedV3"),
+            RouteAction::RollupBoost
+        );
+        assert_eq!(table.action("eth_sendRawTransaction"), RouteAction::Both);
+        assert_eq!(
+            table.action("eth_sendRawTransactionConditional"),
+            RouteAction::Both
+        );
+        assert_eq!(table.action("miner_setGasLimit"), RouteAction::Both);
+        assert_eq!(table.action("miner_start"), RouteAction::RollupBoost);
+        assert_eq!(table.action("eth_getBlockByNumber"), RouteAction::L2);
+        assert_eq!(table.action(""), RouteAction::L2);
+    }
+
+    #[test]
+    fn test_routing_config() -> eyre::Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("rollup-boost-routing-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{
+                "rules": [
+                    { "method": "eth_sendBundle", "action": "builder" },
+                    { "method": "miner_setGasPrice", "action": "l2" },
+                    { "method": "admin_*", "action": "reject" }
+                ],
+                "default": "l2"
+            }"#,
+        )?;
+        let table = RoutingTable::from_file(&path)?;
+        assert_eq!(table.action("eth_sendBundle"), RouteAction::Builder);
+        assert_eq!(table.action("miner_setGasPrice"), RouteAction::L2);
+        assert_eq!(table.action("miner_setGasLimit"), RouteAction::Both);
+        assert_eq!(table.action("admin_addPeer"), RouteAction::Reject);
+        assert_eq!(
+            table
+                .rules
+                .iter()
+                .filter(|rule| rule.method == "miner_setGasPrice")
+                .count(),
+            1
+        );
+
+        std::fs::write(
+            &path,
+            r#"{ "rules": [
+                { "method": "eth_sendBundle", "action": "builder" },
+                { "method": "eth_sendBundle", "action": "both" }
+            ] }"#,
+        )?;
+        assert!(RoutingTable::from_file(&path).is_err());
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
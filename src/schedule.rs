@@ -0,0 +1,358 @@
+use crate::server::ExecutionMode;
+use crate::state::StateNotifier;
+use eyre::bail;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::info;
+
+/// Unit of the bounds of a scheduled execution mode change.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleUnit {
+    // Block number of the block being built
+    Block,
+    // Timestamp of the FCU payload attributes
+    Timestamp,
+}
+
+impl std::fmt::Display for ScheduleUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleUnit::Block => write!(f, "block"),
+            ScheduleUnit::Timestamp => write!(f, "timestamp"),
+        }
+    }
+}
+
+/// Execution mode applied while the blocks being built are within the bounds, both inclusive.
+/// Without an end, the execution mode is kept once the start is reached.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScheduleWindow {
+    pub execution_mode: ExecutionMode,
+    pub unit: ScheduleUnit,
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl ScheduleWindow {
+    fn position(&self, block_number: Option<u64>, timestamp: u64) -> Option<u64> {
+        match self.unit {
+            ScheduleUnit::Block => block_number,
+            ScheduleUnit::Timestamp => Some(timestamp),
+        }
+    }
+
+    fn contains(&self, block_number: Option<u64>, timestamp: u64) -> bool {
+        self.position(block_number, timestamp)
+            .is_some_and(|position| {
+                position >= self.start && self.end.is_none_or(|end| position <= end)
+            })
+    }
+
+    fn is_over(&self, block_number: Option<u64>, timestamp: u64) -> bool {
+        self.position(block_number, timestamp)
+            .zip(self.end)
+            .is_some_and(|(position, end)| position > end)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScheduledExecutionMode {
+    pub id: u64,
+    #[serde(flatten)]
+    pub window: ScheduleWindow,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct ActiveWindow {
+    id: u64,
+    /// Execution mode restored at the end of the window
+    previous: ExecutionMode,
+}
+
+/// Scheduled windows and the active one, persisted in the state file.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ScheduleState {
+    next_id: u64,
+    scheduled: Vec<ScheduledExecutionMode>,
+    active: Option<ActiveWindow>,
+}
+
+/// Execution mode changes scheduled by block number or timestamp.
+///
+/// The schedule is evaluated on every `engine_forkchoiceUpdated` call with payload attributes,
+/// for the block being built. When a window starts, its execution mode replaces the current one,
+/// which is restored when the window ends. A window without an end is applied once and removed.
+#[derive(Debug)]
+pub struct ExecutionModeSchedule {
+    execution_mode: Arc<Mutex<ExecutionMode>>,
+    state: Mutex<ScheduleState>,
+    state_notifier: StateNotifier,
+}
+
+impl ExecutionModeSchedule {
+    pub fn new(execution_mode: Arc<Mutex<ExecutionMode>>, state_notifier: StateNotifier) -> Self {
+        Self {
+            execution_mode,
+            state: Mutex::new(ScheduleState::default()),
+            state_notifier,
+        }
+    }
+
+    /// Adds a window to the schedule, returns its id.
+    pub async fn schedule(&self, window: ScheduleWindow) -> eyre::Result<u64> {
+        if window.end.is_some_and(|end| end < window.start) {
+            bail!(
+                "schedule end {} is before the start {}",
+                window.end.unwrap_or_default(),
+                window.start
+            );
+        }
+
+        let mut state = self.state.lock().await;
+        let id = state.next_id;
+        state.next_id += 1;
+        info!(message = "scheduled execution mode change", "id" = id, "execution_mode" = ?window.execution_mode, "unit" = %window.unit, "start" = window.start, "end" = ?window.end);
+        state.scheduled.push(ScheduledExecutionMode { id, window });
+        self.state_notifier.notify();
+        Ok(id)
+    }
+
+    /// Removes a window from the schedule, restoring the previous execution mode if it is
+    /// active. Returns `false` if the window is unknown.
+    pub async fn cancel(&self, id: u64) -> bool {
+        let mut state = self.state.lock().await;
+        let Some(index) = state.scheduled.iter().position(|entry| entry.id == id) else {
+            return false;
+        };
+        state.scheduled.remove(index);
+        if state.active.as_ref().is_some_and(|active| active.id == id) {
+            self.restore(&mut state).await;
+        }
+        self.state_notifier.notify();
+        info!(
+            message = "cancelled scheduled execution mode change",
+            "id" = id
+        );
+        true
+    }
+
+    /// Scheduled windows in submission order, and the id of the active one.
+    pub async fn list(&self) -> (Vec<ScheduledExecutionMode>, Option<u64>) {
+        let state = self.state.lock().await;
+        (
+            state.scheduled.clone(),
+            state.active.as_ref().map(|active| active.id),
+        )
+    }
+
+    pub async fn snapshot(&self) -> ScheduleState {
+        self.state.lock().await.clone()
+    }
+
+    /// Restores a persisted schedule, the execution mode of its active window is already part
+    /// of the persisted execution mode.
+    pub async fn restore(&self, state: ScheduleState) {
+        *self.state.lock().await = state;
+    }
+
+    /// Applies the schedule to the block being built. The block number is `None` if its parent
+    /// is unknown, the windows by block number are then left as they are.
+    pub async fn apply(&self, block_number: Option<u64>, timestamp: u64) {
+        let mut state = self.state.lock().await;
+
+        if let Some(active) = state.active.clone() {
+            let still_active = state
+                .scheduled
+                .iter()
+                .find(|entry| entry.id == active.id)
+                .is_some_and(|entry| {
+                    entry.window.contains(block_number, timestamp)
+                        || entry.window.position(block_number, timestamp).is_none()
+                });
+            if still_active {
+                return;
+            }
+            self.restore(&mut state).await;
+        }
+
+        let scheduled = state.scheduled.len();
+        state.scheduled.retain(|entry| {
+            let over = entry.window.is_over(block_number, timestamp);
+            if over {
+                info!(
+                    message = "scheduled execution mode change is over",
+                    "id" = entry.id
+                );
+            }
+            !over
+        });
+        if state.scheduled.len() != scheduled {
+            self.state_notifier.notify();
+        }
+
+        let Some(entry) = state
+            .scheduled
+            .iter()
+            .find(|entry| entry.window.contains(block_number, timestamp))
+            .cloned()
+        else {
+            return;
+        };
+        let mut execution_mode = self.execution_mode.lock().await;
+        let previous = std::mem::replace(&mut *execution_mode, entry.window.execution_mode.clone());
+        info!(message = "applied scheduled execution mode change", "id" = entry.id, "execution_mode" = ?entry.window.execution_mode, "previous" = ?previous, "block_number" = ?block_number, "timestamp" = timestamp);
+        if entry.window.end.is_some() {
+            state.active = Some(ActiveWindow {
+                id: entry.id,
+                previous,
+            });
+        } else {
+            state.scheduled.retain(|scheduled| scheduled.id != entry.id);
+        }
+        self.state_notifier.notify();
+    }
+
+    async fn restore(&self, state: &mut ScheduleState) {
+        let Some(active) = state.active.take() else {
+            return;
+        };
+        let mut execution_mode = self.execution_mode.lock().await;
+        info!(message = "restored execution mode after scheduled window", "id" = active.id, "execution_mode" = ?active.previous);
+        *execution_mode = active.previous;
+        self.state_notifier.notify();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(
+        execution_mode: ExecutionMode,
+        unit: ScheduleUnit,
+        start: u64,
+        end: Option<u64>,
+    ) -> ScheduleWindow {
+        ScheduleWindow {
+            execution_mode,
+            unit,
+            start,
+            end,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_window() {
+        let execution_mode = Arc::new(Mutex::new(ExecutionMode::Enabled));
+        let schedule = ExecutionModeSchedule::new(execution_mode.clone(), StateNotifier::default());
+        let id = schedule
+            .schedule(window(
+                ExecutionMode::Disabled,
+                ScheduleUnit::Block,
+                10,
+                Some(11),
+            ))
+            .await
+            .unwrap();
+
+        schedule.apply(Some(9), 1000).await;
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::Enabled);
+
+        schedule.apply(Some(10), 1002).await;
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::Disabled);
+        assert_eq!(schedule.list().await.1, Some(id));
+
+        // an unknown block number keeps the window active
+        schedule.apply(None, 1004).await;
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::Disabled);
+
+        schedule.apply(Some(11), 1004).await;
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::Disabled);
+
+        // the previous execution mode is restored at the end of the window
+        schedule.apply(Some(12), 1006).await;
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::Enabled);
+        assert_eq!(schedule.list().await, (vec![], None));
+    }
+
+    #[tokio::test]
+    async fn test_timestamp_without_end() {
+        let execution_mode = Arc::new(Mutex::new(ExecutionMode::Enabled));
+        let schedule = ExecutionModeSchedule::new(execution_mode.clone(), StateNotifier::default());
+        schedule
+            .schedule(window(
+                ExecutionMode::DryRun,
+                ScheduleUnit::Timestamp,
+                1000,
+                None,
+            ))
+            .await
+            .unwrap();
+
+        schedule.apply(None, 999).await;
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::Enabled);
+
+        // the change is applied once and kept
+        schedule.apply(None, 1001).await;
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::DryRun);
+        assert_eq!(schedule.list().await, (vec![], None));
+
+        *execution_mode.lock().await = ExecutionMode::Enabled;
+        schedule.apply(None, 1003).await;
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::Enabled);
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let execution_mode = Arc::new(Mutex::new(ExecutionMode::Enabled));
+        let schedule = ExecutionModeSchedule::new(execution_mode.clone(), StateNotifier::default());
+        assert!(schedule
+            .schedule(window(
+                ExecutionMode::Disabled,
+                ScheduleUnit::Block,
+                10,
+                Some(5),
+            ))
+            .await
+            .is_err());
+
+        let id = schedule
+            .schedule(window(
+                ExecutionMode::Disabled,
+                ScheduleUnit::Timestamp,
+                1000,
+                Some(2000),
+            ))
+            .await
+            .unwrap();
+        schedule.apply(None, 1000).await;
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::Disabled);
+
+        // cancelling the active window restores the previous execution mode
+        assert!(schedule.cancel(id).await);
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::Enabled);
+        assert!(!schedule.cancel(id).await);
+    }
+
+    #[tokio::test]
+    async fn test_expired_window() {
+        let execution_mode = Arc::new(Mutex::new(ExecutionMode::Enabled));
+        let schedule = ExecutionModeSchedule::new(execution_mode.clone(), StateNotifier::default());
+        schedule
+            .schedule(window(
+                ExecutionMode::Disabled,
+                ScheduleUnit::Block,
+                10,
+                Some(20),
+            ))
+            .await
+            .unwrap();
+
+        // a window submitted too late is dropped without being applied
+        schedule.apply(Some(21), 1000).await;
+        assert_eq!(*execution_mode.lock().await, ExecutionMode::Enabled);
+        assert!(schedule.list().await.0.is_empty());
+    }
+}
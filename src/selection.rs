@@ -0,0 +1,268 @@
+use crate::payload::OpExecutionPayloadEnvelope;
+use crate::server::PayloadSource;
+use alloy_primitives::U256;
+use clap::{arg, Parser};
+use serde::{Deserialize, Serialize};
+
+/// Policies used on `engine_getPayload` to pick the block returned to the proposer.
+#[derive(Parser, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectionArgs {
+    /// Policy used to pick the payload when several builders return a valid block
+    #[arg(long, env, default_value = "first-valid")]
+    pub builder_selection_policy: BuilderSelectionPolicy,
+
+    /// Policy used to choose between the valid builder payload and the local payload
+    #[arg(long, env, default_value = "builder")]
+    pub block_selection_policy: BlockSelectionPolicy,
+
+    /// Percentage by which the builder payload must beat the local payload for the policies
+    /// comparing them
+    #[arg(long, env, default_value_t = 0)]
+    pub block_selection_margin: u64,
+}
+
+impl Default for SelectionArgs {
+    fn default() -> Self {
+        Self {
+            builder_selection_policy: BuilderSelectionPolicy::FirstValid,
+            block_selection_policy: BlockSelectionPolicy::Builder,
+            block_selection_margin: 0,
+        }
+    }
+}
+
+/// Policy used to pick one payload when several builders return a valid block.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum BuilderSelectionPolicy {
+    // Use the first valid payload that arrives, without waiting for the other builders
+    FirstValid,
+    // Use the valid payload with the highest block value
+    HighestValue,
+    // Use the valid payload of the builder listed first in the configuration
+    Priority,
+}
+
+/// Policy used to choose between the valid builder payload and the local payload.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockSelectionPolicy {
+    // Always use the valid builder payload
+    Builder,
+    // Use the builder payload if its block value beats the local one by the margin, on equal
+    // block values the gas used and then the transaction count break the tie
+    BlockValue,
+}
+
+/// Reason of the choice between the builder and the local payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionReason {
+    // The policy always uses a valid builder payload
+    BuilderPolicy,
+    // The builder payload beats the local payload by the margin
+    BuilderBeatsLocal,
+    // The builder payload does not beat the local payload by the margin
+    LocalNotBeaten,
+    // No valid builder payload is available
+    NoBuilderPayload,
+    // The local payload is not available
+    NoLocalPayload,
+    // The compare execution mode always uses the local payload
+    CompareMode,
+    // The block is not selected by the canary rollout
+    CanarySkipped,
+}
+
+impl std::fmt::Display for SelectionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectionReason::BuilderPolicy => write!(f, "builder_policy"),
+            SelectionReason::BuilderBeatsLocal => write!(f, "builder_beats_local"),
+            SelectionReason::LocalNotBeaten => write!(f, "local_not_beaten"),
+            SelectionReason::NoBuilderPayload => write!(f, "no_builder_payload"),
+            SelectionReason::NoLocalPayload => write!(f, "no_local_payload"),
+            SelectionReason::CompareMode => write!(f, "compare_mode"),
+            SelectionReason::CanarySkipped => write!(f, "canary_skipped"),
+        }
+    }
+}
+
+impl SelectionArgs {
+    /// Chooses between the valid builder payload and the local payload.
+    pub fn select_block(
+        &self,
+        builder: &OpExecutionPayloadEnvelope,
+        local: &OpExecutionPayloadEnvelope,
+    ) -> (PayloadSource, SelectionReason) {
+        if self.block_selection_policy == BlockSelectionPolicy::Builder {
+            return (PayloadSource::Builder, SelectionReason::BuilderPolicy);
+        }
+
+        let builder_scores = [
+            builder.block_value(),
+            U256::from(builder.gas_used()),
+            U256::from(builder.transaction_count()),
+        ];
+        let local_scores = [
+            local.block_value(),
+            U256::from(local.gas_used()),
+            U256::from(local.transaction_count()),
+        ];
+        if beats_on_scores(&builder_scores, &local_scores, self.block_selection_margin) {
+            (PayloadSource::Builder, SelectionReason::BuilderBeatsLocal)
+        } else {
+            (PayloadSource::L2, SelectionReason::LocalNotBeaten)
+        }
+    }
+}
+
+/// Whether the builder scores beat the local scores by at least `margin` percent on the first
+/// score that differs, so the later scores only break the ties of the earlier ones. Equal scores
+/// never beat the local block.
+fn beats_on_scores(builder: &[U256], local: &[U256], margin: u64) -> bool {
+    builder
+        .iter()
+        .zip(local)
+        .find(|(builder, local)| builder != local)
+        .is_some_and(|(builder, local)| beats_by_margin(*builder, *local, margin))
+}
+
+/// Whether `builder` is higher than `local` by at least `margin` percent. A tie never beats the
+/// local block.
+fn beats_by_margin(builder: U256, local: U256, margin: u64) -> bool {
+    builder > local
+        && builder.saturating_mul(U256::from(100))
+            >= local.saturating_mul(U256::from(100u64.saturating_add(margin)))
+}
+
+/// A validated payload returned by one of the builders.
+#[derive(Debug, Clone)]
+pub struct BuilderPayload<T> {
+    /// Position of the builder in the configuration, lower is higher priority
+    pub builder_index: usize,
+    pub block_value: U256,
+    pub payload: T,
+}
+
+impl BuilderSelectionPolicy {
+    /// Whether the policy can return as soon as one valid payload is available.
+    pub fn is_first_valid(&self) -> bool {
+        matches!(self, BuilderSelectionPolicy::FirstValid)
+    }
+
+    /// Picks one payload out of the valid builder payloads, in the order they arrived.
+    pub fn select<T>(&self, payloads: Vec<BuilderPayload<T>>) -> Option<BuilderPayload<T>> {
+        match self {
+            BuilderSelectionPolicy::FirstValid => payloads.into_iter().next(),
+            BuilderSelectionPolicy::HighestValue => payloads.into_iter().reduce(|best, payload| {
+                // on equal value, prefer the builder with the higher priority
+                if payload.block_value > best.block_value
+                    || (payload.block_value == best.block_value
+                        && payload.builder_index < best.builder_index)
+                {
+                    payload
+                } else {
+                    best
+                }
+            }),
+            BuilderSelectionPolicy::Priority => payloads
+                .into_iter()
+                .min_by_key(|payload| payload.builder_index),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payloads() -> Vec<BuilderPayload<()>> {
+        // payloads in arrival order
+        vec![
+            BuilderPayload {
+                builder_index: 2,
+                block_value: U256::from(5),
+                payload: (),
+            },
+            BuilderPayload {
+                builder_index: 1,
+                block_value: U256::from(10),
+                payload: (),
+            },
+            BuilderPayload {
+                builder_index: 0,
+                block_value: U256::from(10),
+                payload: (),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_first_valid() {
+        let selected = BuilderSelectionPolicy::FirstValid
+            .select(payloads())
+            .unwrap();
+        assert_eq!(selected.builder_index, 2);
+    }
+
+    #[test]
+    fn test_highest_value() {
+        let selected = BuilderSelectionPolicy::HighestValue
+            .select(payloads())
+            .unwrap();
+        assert_eq!(selected.block_value, U256::from(10));
+        assert_eq!(selected.builder_index, 0);
+    }
+
+    #[test]
+    fn test_priority() {
+        let selected = BuilderSelectionPolicy::Priority.select(payloads()).unwrap();
+        assert_eq!(selected.builder_index, 0);
+    }
+
+    #[test]
+    fn test_beats_by_margin() {
+        assert!(beats_by_margin(U256::from(101), U256::from(100), 0));
+        assert!(!beats_by_margin(U256::from(99), U256::from(100), 0));
+        assert!(beats_by_margin(U256::from(110), U256::from(100), 10));
+        assert!(!beats_by_margin(U256::from(109), U256::from(100), 10));
+        // a tie keeps the local block
+        assert!(!beats_by_margin(U256::from(100), U256::from(100), 0));
+        assert!(!beats_by_margin(U256::ZERO, U256::ZERO, 0));
+        assert!(!beats_by_margin(U256::ZERO, U256::ZERO, 50));
+        // an empty local block is beaten by any non-empty builder block
+        assert!(beats_by_margin(U256::from(1), U256::ZERO, 50));
+    }
+
+    #[test]
+    fn test_beats_on_scores() {
+        let scores = |value: u64, gas_used: u64, tx_count: u64| {
+            [
+                U256::from(value),
+                U256::from(gas_used),
+                U256::from(tx_count),
+            ]
+        };
+        // the block value decides when it differs
+        assert!(beats_on_scores(&scores(110, 0, 0), &scores(100, 50, 5), 10));
+        assert!(!beats_on_scores(
+            &scores(105, 90, 9),
+            &scores(100, 50, 5),
+            10
+        ));
+        assert!(!beats_on_scores(&scores(90, 90, 9), &scores(100, 50, 5), 0));
+        // on equal block values the gas used, then the transaction count break the tie
+        assert!(beats_on_scores(&scores(0, 60, 0), &scores(0, 50, 5), 10));
+        assert!(!beats_on_scores(&scores(0, 40, 9), &scores(0, 50, 5), 0));
+        assert!(beats_on_scores(&scores(0, 50, 6), &scores(0, 50, 5), 0));
+        // identical blocks keep the local block
+        assert!(!beats_on_scores(&scores(0, 50, 5), &scores(0, 50, 5), 0));
+    }
+
+    #[test]
+    fn test_no_payloads() {
+        assert!(BuilderSelectionPolicy::HighestValue
+            .select(Vec::<BuilderPayload<()>>::new())
+            .is_none());
+    }
+}
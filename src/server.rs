@@ -1,39 +1,74 @@
+use crate::canary::{Canary, CanaryArgs};
+use crate::catch_up::{BuilderCatchUp, CatchUpArgs};
+use crate::circuit_breaker::{CircuitBreaker, CircuitBreakerArgs};
 use crate::client::ExecutionClient;
+use crate::compare::{CompareArgs, CompareHistory, PayloadDiff};
+use crate::deadline::{sleep_until_deadline, DeadlineArgs};
 use crate::debug_api;
+use crate::debug_auth::DebugAuth;
 use crate::metrics::ServerMetrics;
-use alloy_primitives::B256;
-use debug_api::DebugServer;
+use crate::miner_settings::{BuilderRecovery, BuilderRecoveryArgs, MinerSettings};
+use crate::payload::{
+    NewPayload, NewPayloadV3, NewPayloadV4, OpExecutionPayloadEnvelope,
+    OpExecutionPayloadEnvelopeV2, PayloadVersion,
+};
+use crate::rollup_config::RollupConfig;
+use crate::routing::RoutingTable;
+use crate::schedule::ExecutionModeSchedule;
+use crate::selection::{BuilderPayload, SelectionArgs, SelectionReason};
+use crate::state::{PersistedState, StateArgs, StateNotifier, StatePrecedence};
+use crate::sync_queue::{SyncQueue, SyncQueueArgs};
+use crate::sync_status::SyncTracker;
+use crate::validation::validate_payload_attributes;
+use crate::validator::ValidatorPool;
+use alloy_primitives::{Bytes, B256};
+use debug_api::{DebugServer, DebugState};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZero;
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 
 use alloy_rpc_types_engine::{
-    ExecutionPayload, ExecutionPayloadV3, ForkchoiceState, ForkchoiceUpdated, PayloadId,
+    ExecutionPayloadInputV2, ExecutionPayloadV3, ForkchoiceState, ForkchoiceUpdated, PayloadId,
     PayloadStatus,
 };
 use jsonrpsee::core::{async_trait, ClientError, RegisterMethodError, RpcResult};
 use jsonrpsee::types::error::INVALID_REQUEST_CODE;
-use jsonrpsee::types::ErrorObject;
+use jsonrpsee::types::{ErrorCode, ErrorObject, ErrorObjectOwned};
 use jsonrpsee::RpcModule;
 use lru::LruCache;
-use op_alloy_rpc_types_engine::{OpExecutionPayloadEnvelopeV3, OpPayloadAttributes};
+use op_alloy_rpc_types_engine::{
+    OpExecutionPayloadEnvelopeV3, OpExecutionPayloadEnvelopeV4, OpPayloadAttributes,
+};
 use opentelemetry::global::{self, BoxedSpan, BoxedTracer};
 use opentelemetry::trace::{Span, TraceContextExt, Tracer};
 use opentelemetry::{Context, KeyValue};
 use serde::{Deserialize, Serialize};
 
-use tokio::sync::Mutex;
-use tracing::{debug, error, info};
+use tokio::sync::{watch, Mutex};
+use tracing::{debug, error, info, warn};
 
 use jsonrpsee::proc_macros::rpc;
 
 const CACHE_SIZE: usize = 100;
 
+/// Engine API error code for a method version that does not match the active hardfork
+const UNSUPPORTED_FORK_CODE: i32 = -38005;
+
 pub struct PayloadTraceContext {
     tracer: Arc<BoxedTracer>,
     block_hash_to_payload_ids: Arc<Mutex<LruCache<B256, Vec<PayloadId>>>>,
     payload_id_to_span: Arc<Mutex<LruCache<PayloadId, Arc<BoxedSpan>>>>,
-    local_to_external_payload_ids: Arc<Mutex<LruCache<PayloadId, PayloadId>>>,
+    /// Payload ids returned by each builder, keyed by the local payload id and the builder index
+    local_to_external_payload_ids: Arc<Mutex<LruCache<PayloadId, HashMap<usize, PayloadId>>>>,
+    /// Parent hash and payload attributes of the FCU that started each local payload
+    payload_id_to_attributes: Arc<Mutex<LruCache<PayloadId, (B256, OpPayloadAttributes)>>>,
+    /// Completion of the builder FCU calls, keyed by the local payload id and the builder index
+    pending_fcus: Arc<Mutex<LruCache<PayloadId, HashMap<usize, watch::Receiver<bool>>>>>,
+    /// Block numbers of the `engine_newPayload` calls, used to number the next blocks
+    block_hash_to_number: Arc<Mutex<LruCache<B256, u64>>>,
 }
 
 impl PayloadTraceContext {
@@ -49,6 +84,13 @@ impl PayloadTraceContext {
             local_to_external_payload_ids: Arc::new(Mutex::new(LruCache::new(
                 NonZero::new(CACHE_SIZE).unwrap(),
             ))),
+            payload_id_to_attributes: Arc::new(Mutex::new(LruCache::new(
+                NonZero::new(CACHE_SIZE).unwrap(),
+            ))),
+            pending_fcus: Arc::new(Mutex::new(LruCache::new(NonZero::new(CACHE_SIZE).unwrap()))),
+            block_hash_to_number: Arc::new(Mutex::new(LruCache::new(
+                NonZero::new(CACHE_SIZE).unwrap(),
+            ))),
         }
     }
 
@@ -93,14 +135,125 @@ impl PayloadTraceContext {
         block_hash_to_payload_ids.pop(block_hash);
     }
 
-    async fn store_payload_id_mapping(&self, local_id: PayloadId, external_id: PayloadId) {
+    async fn store_payload_id_mapping(
+        &self,
+        local_id: PayloadId,
+        builder_index: usize,
+        external_id: PayloadId,
+    ) {
         let mut local_to_external = self.local_to_external_payload_ids.lock().await;
-        local_to_external.put(local_id, external_id);
+        if let Some(external_ids) = local_to_external.get_mut(&local_id) {
+            external_ids.insert(builder_index, external_id);
+        } else {
+            local_to_external.put(local_id, HashMap::from([(builder_index, external_id)]));
+        }
     }
 
-    async fn get_external_payload_id(&self, local_id: &PayloadId) -> Option<PayloadId> {
+    async fn get_external_payload_id(
+        &self,
+        local_id: &PayloadId,
+        builder_index: usize,
+    ) -> Option<PayloadId> {
         let mut store = self.local_to_external_payload_ids.lock().await;
-        store.get(local_id).copied()
+        store
+            .get(local_id)
+            .and_then(|external_ids| external_ids.get(&builder_index))
+            .copied()
+    }
+
+    async fn store_payload_attributes(
+        &self,
+        payload_id: PayloadId,
+        parent_hash: B256,
+        attributes: OpPayloadAttributes,
+    ) {
+        let mut store = self.payload_id_to_attributes.lock().await;
+        store.put(payload_id, (parent_hash, attributes));
+    }
+
+    async fn get_payload_attributes(
+        &self,
+        payload_id: &PayloadId,
+    ) -> Option<(B256, OpPayloadAttributes)> {
+        let mut store = self.payload_id_to_attributes.lock().await;
+        store.get(payload_id).cloned()
+    }
+
+    async fn get_payload_timestamp(&self, payload_id: &PayloadId) -> Option<u64> {
+        let mut store = self.payload_id_to_attributes.lock().await;
+        store
+            .get(payload_id)
+            .map(|(_, attributes)| attributes.payload_attributes.timestamp)
+    }
+
+    async fn store_block_number(&self, block_hash: B256, block_number: u64) {
+        let mut store = self.block_hash_to_number.lock().await;
+        store.put(block_hash, block_number);
+    }
+
+    /// Number of the block built on top of a parent, `None` if the parent block is unknown.
+    async fn get_child_block_number(&self, parent_hash: &B256) -> Option<u64> {
+        let mut store = self.block_hash_to_number.lock().await;
+        store
+            .get(parent_hash)
+            .map(|parent_number| parent_number + 1)
+    }
+
+    /// Number of the block built for a payload, `None` if its parent block is unknown.
+    async fn get_payload_block_number(&self, payload_id: &PayloadId) -> Option<u64> {
+        let (parent_hash, _) = self.get_payload_attributes(payload_id).await?;
+        self.get_child_block_number(&parent_hash).await
+    }
+
+    /// Tracks an in-flight builder FCU, the returned sender signals its completion.
+    async fn store_pending_fcu(
+        &self,
+        local_id: PayloadId,
+        builder_index: usize,
+    ) -> watch::Sender<bool> {
+        let (done_tx, done_rx) = watch::channel(false);
+        let mut store = self.pending_fcus.lock().await;
+        if let Some(pending) = store.get_mut(&local_id) {
+            pending.insert(builder_index, done_rx);
+        } else {
+            store.put(local_id, HashMap::from([(builder_index, done_rx)]));
+        }
+        done_tx
+    }
+
+    /// Waits until the builder FCU for the local payload id completed, at most until the deadline.
+    /// Returns `true` if the FCU was still in flight.
+    async fn wait_for_pending_fcu(
+        &self,
+        local_id: &PayloadId,
+        builder_index: usize,
+        deadline: Option<tokio::time::Instant>,
+    ) -> bool {
+        let pending = {
+            let mut store = self.pending_fcus.lock().await;
+            store
+                .get(local_id)
+                .and_then(|pending| pending.get(&builder_index))
+                .cloned()
+        };
+        let Some(mut done_rx) = pending else {
+            return false;
+        };
+        if *done_rx.borrow() {
+            return false;
+        }
+
+        // A dropped sender means the FCU task ended, so the result is not relevant here
+        let done = done_rx.wait_for(|done| *done);
+        match deadline {
+            Some(deadline) => {
+                let _ = tokio::time::timeout_at(deadline, done).await;
+            }
+            None => {
+                let _ = done.await;
+            }
+        }
+        true
     }
 }
 
@@ -109,6 +262,8 @@ impl PayloadTraceContext {
 pub enum ExecutionMode {
     // Normal execution, sending all requests
     Enabled,
+    // Fetching and validating the builder payloads, but always returning the local payload
+    Compare,
     // Not sending get_payload requests
     DryRun,
     // Not sending any requests
@@ -117,48 +272,392 @@ pub enum ExecutionMode {
 
 impl ExecutionMode {
     fn is_get_payload_enabled(&self) -> bool {
-        // get payload is only enabled in 'enabled' mode
-        matches!(self, ExecutionMode::Enabled)
+        // get payload is only enabled in 'enabled' and 'compare' modes
+        matches!(self, ExecutionMode::Enabled | ExecutionMode::Compare)
+    }
+
+    fn is_compare(&self) -> bool {
+        matches!(self, ExecutionMode::Compare)
     }
 
     fn is_disabled(&self) -> bool {
         matches!(self, ExecutionMode::Disabled)
     }
+
+    /// Returns the most restrictive of the two execution modes.
+    fn restrict(self, other: ExecutionMode) -> ExecutionMode {
+        match (self, other) {
+            (ExecutionMode::Disabled, _) | (_, ExecutionMode::Disabled) => ExecutionMode::Disabled,
+            (ExecutionMode::DryRun, _) | (_, ExecutionMode::DryRun) => ExecutionMode::DryRun,
+            (ExecutionMode::Compare, _) | (_, ExecutionMode::Compare) => ExecutionMode::Compare,
+            _ => ExecutionMode::Enabled,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct RollupBoostServer {
     pub l2_client: ExecutionClient,
-    pub builder_client: ExecutionClient,
+    /// Builders in priority order, the first one being the highest
+    pub builder_clients: Vec<ExecutionClient>,
+    pub selection: SelectionArgs,
+    pub deadline: DeadlineArgs,
     pub boost_sync: bool,
     pub metrics: Option<Arc<ServerMetrics>>,
     pub payload_trace_context: Arc<PayloadTraceContext>,
     pub execution_mode: Arc<Mutex<ExecutionMode>>,
+    /// Circuit breaker of each builder, in the same order as `builder_clients`
+    pub circuit_breakers: Vec<Arc<CircuitBreaker>>,
+    /// Ordered delivery queue of the calls to each builder, in the same order as `builder_clients`
+    pub sync_queues: Vec<Arc<SyncQueue>>,
+    pub catch_up: CatchUpArgs,
+    /// Whether each builder is catching up, in the same order as `builder_clients`
+    pub catching_up: Vec<Arc<AtomicBool>>,
+    /// Sync status of each builder, in the same order as `builder_clients`
+    pub sync_trackers: Vec<Arc<SyncTracker>>,
+    /// Canonical head of the last valid `engine_forkchoiceUpdated` call
+    pub fork_choice_state: Arc<Mutex<Option<ForkchoiceState>>>,
+    /// Settings of the last `miner_` calls, shared with the proxy that records them
+    pub miner_settings: Arc<Mutex<MinerSettings>>,
+    /// Replay of the miner settings to each builder, in the same order as `builder_clients`
+    pub builder_recoveries: Vec<Arc<BuilderRecovery>>,
+    pub rollup_config: Option<Arc<RollupConfig>>,
+    /// Execution engines validating the builder payloads instead of the L2 execution engine
+    pub validator: Option<Arc<ValidatorPool>>,
+    /// Payload comparisons of the compare execution mode
+    pub compare_history: Arc<CompareHistory>,
+    /// Share of the blocks using the builder payload in enabled mode
+    pub canary: Arc<Canary>,
+    /// Execution mode changes scheduled by block number or timestamp
+    pub execution_mode_schedule: Arc<ExecutionModeSchedule>,
+    /// Routing of the requests by method, shared with the proxy that applies it
+    pub routing_table: Arc<RoutingTable>,
+    /// Signalled on every change of the state persisted in the state file
+    pub state_notifier: StateNotifier,
 }
 
 impl RollupBoostServer {
     pub fn new(
         l2_client: ExecutionClient,
-        builder_client: ExecutionClient,
+        builder_clients: Vec<ExecutionClient>,
         boost_sync: bool,
         metrics: Option<Arc<ServerMetrics>>,
         initial_execution_mode: ExecutionMode,
     ) -> Self {
+        let catching_up = builder_clients
+            .iter()
+            .map(|_| Arc::new(AtomicBool::new(false)))
+            .collect();
+        let sync_trackers = builder_clients
+            .iter()
+            .map(|builder| {
+                Arc::new(SyncTracker::new(
+                    builder.auth_rpc.to_string(),
+                    metrics.clone(),
+                ))
+            })
+            .collect();
+        let execution_mode = Arc::new(Mutex::new(initial_execution_mode));
+        let state_notifier = StateNotifier::default();
+        let miner_settings = Arc::new(Mutex::new(MinerSettings::default()));
+        let builder_recoveries = builder_clients
+            .iter()
+            .map(|builder| {
+                Arc::new(BuilderRecovery::new(
+                    builder.clone(),
+                    miner_settings.clone(),
+                    metrics.clone(),
+                ))
+            })
+            .collect();
         Self {
             l2_client,
-            builder_client,
+            builder_clients,
+            selection: SelectionArgs::default(),
+            deadline: DeadlineArgs::default(),
             boost_sync,
             metrics,
             payload_trace_context: Arc::new(PayloadTraceContext::new()),
-            execution_mode: Arc::new(Mutex::new(initial_execution_mode)),
+            execution_mode: execution_mode.clone(),
+            circuit_breakers: vec![],
+            sync_queues: vec![],
+            catch_up: CatchUpArgs::default(),
+            catching_up,
+            sync_trackers,
+            fork_choice_state: Arc::new(Mutex::new(None)),
+            miner_settings,
+            builder_recoveries,
+            rollup_config: None,
+            validator: None,
+            compare_history: Arc::new(CompareHistory::new(
+                CompareArgs::default(),
+                state_notifier.clone(),
+            )),
+            canary: Arc::new(Canary::new(
+                CanaryArgs::default(),
+                metrics.clone(),
+                state_notifier.clone(),
+            )),
+            execution_mode_schedule: Arc::new(ExecutionModeSchedule::new(
+                execution_mode,
+                state_notifier.clone(),
+            )),
+            routing_table: Arc::new(RoutingTable::default()),
+            state_notifier,
+        }
+        .with_circuit_breaker(CircuitBreakerArgs::default())
+        .with_sync_queue(SyncQueueArgs::default())
+    }
+
+    /// Sets the policies used to pick the block returned by `engine_getPayload`.
+    pub fn with_selection(mut self, selection: SelectionArgs) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Sets the time budget of the builder payload on `engine_getPayload`.
+    pub fn with_deadline(mut self, deadline: DeadlineArgs) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Checks the engine API versions against the hardfork schedule of the rollup config.
+    pub fn with_rollup_config(mut self, rollup_config: RollupConfig) -> Self {
+        self.rollup_config = Some(Arc::new(rollup_config));
+        self
+    }
+
+    /// Sets the routing of the requests by method applied by the proxy.
+    pub fn with_routing_table(mut self, routing_table: RoutingTable) -> Self {
+        self.routing_table = Arc::new(routing_table);
+        self
+    }
+
+    /// Sets the circuit breaker settings applied to every builder.
+    pub fn with_circuit_breaker(mut self, args: CircuitBreakerArgs) -> Self {
+        self.circuit_breakers = self
+            .builder_clients
+            .iter()
+            .map(|builder| {
+                Arc::new(CircuitBreaker::new(
+                    builder.auth_rpc.to_string(),
+                    args.clone(),
+                    self.metrics.clone(),
+                    self.state_notifier.clone(),
+                ))
+            })
+            .collect();
+        self
+    }
+
+    /// Sets the settings of the delivery queue of every builder.
+    pub fn with_sync_queue(mut self, args: SyncQueueArgs) -> Self {
+        self.sync_queues = self
+            .builder_clients
+            .iter()
+            .zip(&self.sync_trackers)
+            .map(|(builder, sync_tracker)| {
+                Arc::new(SyncQueue::new(
+                    builder.clone(),
+                    args,
+                    sync_tracker.clone(),
+                    self.metrics.clone(),
+                ))
+            })
+            .collect();
+        self
+    }
+
+    /// Sets the settings of the builder catch-up sync.
+    pub fn with_catch_up(mut self, catch_up: CatchUpArgs) -> Self {
+        self.catch_up = catch_up;
+        self
+    }
+
+    /// Validates the builder payloads on dedicated execution engines.
+    pub fn with_validator(mut self, validator: ValidatorPool) -> Self {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Sets the size of the history of the compare execution mode.
+    pub fn with_compare(mut self, args: CompareArgs) -> Self {
+        self.compare_history = Arc::new(CompareHistory::new(args, self.state_notifier.clone()));
+        self
+    }
+
+    /// Sets the initial share of the blocks using the builder payload.
+    pub fn with_canary(mut self, args: CanaryArgs) -> Self {
+        self.canary = Arc::new(Canary::new(
+            args,
+            self.metrics.clone(),
+            self.state_notifier.clone(),
+        ));
+        self
+    }
+
+    /// Starts polling the builder client versions to replay the miner settings after a restart.
+    pub fn with_builder_recovery(self, args: BuilderRecoveryArgs) -> Self {
+        if args.builder_client_version_interval > 0 {
+            let interval = Duration::from_millis(args.builder_client_version_interval);
+            for builder_recovery in &self.builder_recoveries {
+                builder_recovery.spawn_client_version_poll(interval);
+            }
         }
+        self
+    }
+
+    pub async fn start_debug_server(
+        &self,
+        debug_addr: &str,
+        auth: Option<DebugAuth>,
+    ) -> eyre::Result<()> {
+        let server = DebugServer::new(
+            self.execution_mode.clone(),
+            DebugState {
+                circuit_breakers: self.circuit_breakers.clone(),
+                sync_trackers: self.sync_trackers.clone(),
+                miner_settings: self.miner_settings.clone(),
+                compare_history: self.compare_history.clone(),
+                canary: self.canary.clone(),
+                execution_mode_schedule: self.execution_mode_schedule.clone(),
+                routing_table: self.routing_table.clone(),
+                state_notifier: self.state_notifier.clone(),
+            },
+        );
+        server.run(debug_addr, auth).await?;
+        Ok(())
     }
 
-    pub async fn start_debug_server(&self, debug_addr: &str) -> eyre::Result<()> {
-        let server = DebugServer::new(self.execution_mode.clone());
-        server.run(debug_addr).await?;
+    /// Restores the runtime state from the state file and spawns the task writing it on change.
+    pub async fn start_state_file(&self, args: StateArgs) -> eyre::Result<()> {
+        let Some(path) = args.state_file else {
+            return Ok(());
+        };
+
+        if let Some(state) = PersistedState::load(&path)? {
+            self.restore_state(state, args.state_precedence).await;
+            info!(message = "restored runtime state", "path" = %path.display());
+        }
+
+        let server = self.clone();
+        tokio::spawn(async move {
+            let mut written = None;
+            loop {
+                let state = server.snapshot_state().await;
+                if written.as_ref() != Some(&state) {
+                    match state.write(&path).await {
+                        Ok(()) => written = Some(state),
+                        Err(e) => {
+                            error!(message = "error writing state file", "path" = %path.display(), "error" = %e)
+                        }
+                    }
+                }
+                server.state_notifier.changed().await;
+            }
+        });
         Ok(())
     }
+
+    async fn snapshot_state(&self) -> PersistedState {
+        let mut circuit_breakers = BTreeMap::new();
+        for (builder, circuit_breaker) in self.builder_clients.iter().zip(&self.circuit_breakers) {
+            circuit_breakers.insert(builder.auth_rpc.to_string(), circuit_breaker.state().await);
+        }
+        PersistedState {
+            execution_mode: self.execution_mode.lock().await.clone(),
+            canary_percentage: Some(self.canary.percentage()),
+            execution_mode_schedule: self.execution_mode_schedule.snapshot().await,
+            circuit_breakers,
+            miner_settings: self.miner_settings.lock().await.clone(),
+            payload_diffs: self.compare_history.latest(None).await,
+        }
+    }
+
+    async fn restore_state(&self, state: PersistedState, precedence: StatePrecedence) {
+        if precedence == StatePrecedence::Persisted {
+            info!(message = "using persisted execution mode", "execution_mode" = ?state.execution_mode, "canary_percentage" = ?state.canary_percentage);
+            *self.execution_mode.lock().await = state.execution_mode;
+            if let Some(percentage) = state.canary_percentage {
+                self.canary.set_percentage(percentage);
+            }
+        }
+        self.execution_mode_schedule
+            .restore(state.execution_mode_schedule)
+            .await;
+        for (builder, circuit_breaker) in self.builder_clients.iter().zip(&self.circuit_breakers) {
+            if let Some(circuit) = state.circuit_breakers.get(&builder.auth_rpc.to_string()) {
+                circuit_breaker.restore(*circuit).await;
+            }
+        }
+        *self.miner_settings.lock().await = state.miner_settings;
+        self.compare_history.restore(state.payload_diffs).await;
+    }
+
+    /// Execution mode of a builder for a new block, the most restrictive of the global execution
+    /// mode and the mode set by the builder circuit breaker, which probes the builder once the
+    /// probe interval elapsed.
+    async fn builder_execution_mode(&self, builder_index: usize) -> ExecutionMode {
+        let execution_mode = self.execution_mode.lock().await.clone();
+        execution_mode.restrict(
+            self.circuit_breakers[builder_index]
+                .probe_execution_mode()
+                .await,
+        )
+    }
+
+    /// Whether the canary rollout skips the builder payload of this block, as in dry-run mode.
+    async fn canary_skips(&self, payload_id: &PayloadId) -> bool {
+        let execution_mode = self.execution_mode.lock().await.clone();
+        if execution_mode != ExecutionMode::Enabled || !self.canary.is_enabled() {
+            return false;
+        }
+        let block_number = self
+            .payload_trace_context
+            .get_payload_block_number(payload_id)
+            .await;
+        !self.canary.is_builder_block(block_number)
+    }
+
+    /// Queues a boost sync call to a builder, `None` if the call is not sent.
+    ///
+    /// No call is queued while the catch-up sync replays the blocks the builder missed. A call
+    /// dropped by a full queue leaves a gap, so it starts the catch-up sync.
+    fn queue_sync_call<T>(
+        &self,
+        builder_index: usize,
+        enqueue: impl FnOnce(&SyncQueue) -> Option<T>,
+    ) -> Option<T> {
+        if self.catching_up[builder_index].load(Ordering::SeqCst) {
+            debug!(message = "builder is catching up, skipping sync call", "url" = ?self.builder_clients[builder_index].auth_rpc);
+            return None;
+        }
+        let response = enqueue(&self.sync_queues[builder_index]);
+        if response.is_none() {
+            if let Some(catch_up) = self.builder_catch_up(builder_index) {
+                catch_up.trigger();
+            }
+        }
+        response
+    }
+
+    /// Catch-up sync of a builder, `None` if the catch-up sync is disabled.
+    fn builder_catch_up(&self, builder_index: usize) -> Option<BuilderCatchUp> {
+        if self.catch_up.builder_catch_up_max_blocks == 0 {
+            return None;
+        }
+        Some(BuilderCatchUp {
+            l2_client: self.l2_client.clone(),
+            builder_client: self.builder_clients[builder_index].clone(),
+            sync_queue: self.sync_queues[builder_index].clone(),
+            fork_choice_state: self.fork_choice_state.clone(),
+            running: self.catching_up[builder_index].clone(),
+            sync_tracker: self.sync_trackers[builder_index].clone(),
+            rollup_config: self.rollup_config.clone(),
+            max_blocks: self.catch_up.builder_catch_up_max_blocks,
+            metrics: self.metrics.clone(),
+        })
+    }
 }
 
 impl TryInto<RpcModule<()>> for RollupBoostServer {
@@ -202,8 +701,35 @@ impl PayloadSource {
     }
 }
 
+/// Why a builder payload was not used for the block. Only the failures of the builder count
+/// against its circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BuilderPayloadError {
+    /// The builder call failed or timed out, or the builder produced a block that is invalid,
+    /// does not match the payload attributes or exceeds the miner limits
+    Builder,
+    /// The block could not be validated because the validation call failed
+    Rejected,
+}
+
 #[rpc(server, client, namespace = "engine")]
 pub trait EngineApi {
+    #[method(name = "forkchoiceUpdatedV2")]
+    async fn fork_choice_updated_v2(
+        &self,
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<OpPayloadAttributes>,
+    ) -> RpcResult<ForkchoiceUpdated>;
+
+    #[method(name = "getPayloadV2")]
+    async fn get_payload_v2(
+        &self,
+        payload_id: PayloadId,
+    ) -> RpcResult<OpExecutionPayloadEnvelopeV2>;
+
+    #[method(name = "newPayloadV2")]
+    async fn new_payload_v2(&self, payload: ExecutionPayloadInputV2) -> RpcResult<PayloadStatus>;
+
     #[method(name = "forkchoiceUpdatedV3")]
     async fn fork_choice_updated_v3(
         &self,
@@ -224,10 +750,70 @@ pub trait EngineApi {
         versioned_hashes: Vec<B256>,
         parent_beacon_block_root: B256,
     ) -> RpcResult<PayloadStatus>;
+
+    #[method(name = "getPayloadV4")]
+    async fn get_payload_v4(
+        &self,
+        payload_id: PayloadId,
+    ) -> RpcResult<OpExecutionPayloadEnvelopeV4>;
+
+    #[method(name = "newPayloadV4")]
+    async fn new_payload_v4(
+        &self,
+        payload: ExecutionPayloadV3,
+        versioned_hashes: Vec<B256>,
+        parent_beacon_block_root: B256,
+        execution_requests: Vec<Bytes>,
+    ) -> RpcResult<PayloadStatus>;
 }
 
 #[async_trait]
 impl EngineApiServer for RollupBoostServer {
+    async fn fork_choice_updated_v2(
+        &self,
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<OpPayloadAttributes>,
+    ) -> RpcResult<ForkchoiceUpdated> {
+        let start = Instant::now();
+        let res = self
+            .fork_choice_updated(fork_choice_state, payload_attributes, PayloadVersion::V2)
+            .await;
+        let elapsed = start.elapsed();
+        if let Some(metrics) = &self.metrics {
+            metrics.fork_choice_updated_v2_total.record(elapsed);
+        }
+        res
+    }
+
+    async fn get_payload_v2(
+        &self,
+        payload_id: PayloadId,
+    ) -> RpcResult<OpExecutionPayloadEnvelopeV2> {
+        let start = Instant::now();
+        let res = self
+            .get_payload(payload_id, PayloadVersion::V2)
+            .await
+            .and_then(|payload| match payload {
+                OpExecutionPayloadEnvelope::V2(payload) => Ok(payload),
+                _ => Err(ErrorCode::InternalError.into()),
+            });
+        let elapsed = start.elapsed();
+        if let Some(metrics) = &self.metrics {
+            metrics.get_payload_v2_total.record(elapsed);
+        }
+        res
+    }
+
+    async fn new_payload_v2(&self, payload: ExecutionPayloadInputV2) -> RpcResult<PayloadStatus> {
+        let start = Instant::now();
+        let res = self.new_payload(NewPayload::V2(payload)).await;
+        let elapsed = start.elapsed();
+        if let Some(metrics) = &self.metrics {
+            metrics.new_payload_v2_total.record(elapsed);
+        }
+        res
+    }
+
     async fn fork_choice_updated_v3(
         &self,
         fork_choice_state: ForkchoiceState,
@@ -235,7 +821,7 @@ impl EngineApiServer for RollupBoostServer {
     ) -> RpcResult<ForkchoiceUpdated> {
         let start = Instant::now();
         let res = self
-            .fork_choice_updated_v3(fork_choice_state, payload_attributes)
+            .fork_choice_updated(fork_choice_state, payload_attributes, PayloadVersion::V3)
             .await;
         let elapsed = start.elapsed();
         if let Some(metrics) = &self.metrics {
@@ -249,7 +835,13 @@ impl EngineApiServer for RollupBoostServer {
         payload_id: PayloadId,
     ) -> RpcResult<OpExecutionPayloadEnvelopeV3> {
         let start = Instant::now();
-        let res = self.get_payload_v3(payload_id).await;
+        let res = self
+            .get_payload(payload_id, PayloadVersion::V3)
+            .await
+            .and_then(|payload| match payload {
+                OpExecutionPayloadEnvelope::V3(payload) => Ok(payload),
+                _ => Err(ErrorCode::InternalError.into()),
+            });
         let elapsed = start.elapsed();
         if let Some(metrics) = &self.metrics {
             metrics.get_payload_v3_total.record(elapsed);
@@ -265,7 +857,11 @@ impl EngineApiServer for RollupBoostServer {
     ) -> RpcResult<PayloadStatus> {
         let start = Instant::now();
         let res = self
-            .new_payload_v3(payload, versioned_hashes, parent_beacon_block_root)
+            .new_payload(NewPayload::V3(NewPayloadV3 {
+                execution_payload: payload,
+                versioned_hashes,
+                parent_beacon_block_root,
+            }))
             .await;
         let elapsed = start.elapsed();
         if let Some(metrics) = &self.metrics {
@@ -273,25 +869,102 @@ impl EngineApiServer for RollupBoostServer {
         }
         res
     }
+
+    async fn get_payload_v4(
+        &self,
+        payload_id: PayloadId,
+    ) -> RpcResult<OpExecutionPayloadEnvelopeV4> {
+        let start = Instant::now();
+        let res = self
+            .get_payload(payload_id, PayloadVersion::V4)
+            .await
+            .and_then(|payload| match payload {
+                OpExecutionPayloadEnvelope::V4(payload) => Ok(payload),
+                _ => Err(ErrorCode::InternalError.into()),
+            });
+        let elapsed = start.elapsed();
+        if let Some(metrics) = &self.metrics {
+            metrics.get_payload_v4_total.record(elapsed);
+        }
+        res
+    }
+
+    async fn new_payload_v4(
+        &self,
+        payload: ExecutionPayloadV3,
+        versioned_hashes: Vec<B256>,
+        parent_beacon_block_root: B256,
+        execution_requests: Vec<Bytes>,
+    ) -> RpcResult<PayloadStatus> {
+        let start = Instant::now();
+        let res = self
+            .new_payload(NewPayload::V4(NewPayloadV4 {
+                execution_payload: payload,
+                versioned_hashes,
+                parent_beacon_block_root,
+                execution_requests,
+            }))
+            .await;
+        let elapsed = start.elapsed();
+        if let Some(metrics) = &self.metrics {
+            metrics.new_payload_v4_total.record(elapsed);
+        }
+        res
+    }
 }
 
 impl RollupBoostServer {
-    async fn fork_choice_updated_v3(
+    async fn fork_choice_updated(
         &self,
         fork_choice_state: ForkchoiceState,
         payload_attributes: Option<OpPayloadAttributes>,
+        version: PayloadVersion,
     ) -> RpcResult<ForkchoiceUpdated> {
         info!(
-            message = "received fork_choice_updated_v3",
+            message = "received fork_choice_updated",
             "head_block_hash" = %fork_choice_state.head_block_hash,
             "has_attributes" = payload_attributes.is_some(),
+            "version" = %version,
         );
 
+        if let (Some(rollup_config), Some(attr)) = (&self.rollup_config, &payload_attributes) {
+            let expected_version =
+                rollup_config.fork_choice_updated_version(attr.payload_attributes.timestamp);
+            if expected_version != version {
+                error!(message = "fork_choice_updated version does not match the active hardfork", "expected_version" = %expected_version, "version" = %version, "timestamp" = attr.payload_attributes.timestamp);
+                return Err(unsupported_fork_error(expected_version, version));
+            }
+        }
+
+        if let Some(attr) = &payload_attributes {
+            let block_number = self
+                .payload_trace_context
+                .get_child_block_number(&fork_choice_state.head_block_hash)
+                .await;
+            self.execution_mode_schedule
+                .apply(block_number, attr.payload_attributes.timestamp)
+                .await;
+        }
+
         // First get the local payload ID from L2 client
         let l2_response = self
             .l2_client
-            .fork_choice_updated_v3(fork_choice_state, payload_attributes.clone())
+            .fork_choice_updated(fork_choice_state, payload_attributes.clone(), version)
             .await?;
+        if l2_response.payload_status.status.is_valid() {
+            *self.fork_choice_state.lock().await = Some(fork_choice_state);
+        }
+
+        if let (Some(attr), Some(local_payload_id)) = (&payload_attributes, l2_response.payload_id)
+        {
+            self.payload_trace_context
+                .store_payload_attributes(
+                    local_payload_id,
+                    fork_choice_state.head_block_hash,
+                    attr.clone(),
+                )
+                .await;
+        }
 
         // TODO: Use _is_block_building_call to log the correct message during the async call to builder
         let (should_send_to_builder, _is_block_building_call) =
@@ -303,119 +976,168 @@ impl RollupBoostServer {
                 (use_tx_pool, true)
             } else {
                 // no payload attributes. It is a FCU call to lock the head block
-                // previously synced with the new_payload_v3 call. Only send to builder if boost_sync is enabled
+                // previously synced with the new_payload call. Only send to builder if boost_sync is enabled
                 (self.boost_sync, false)
             };
 
-        let execution_mode = self.execution_mode.lock().await;
+        let execution_mode = self.execution_mode.lock().await.clone();
 
         if execution_mode.is_disabled() {
             debug!(message = "execution mode is disabled, skipping FCU call to builder", "head_block_hash" = %fork_choice_state.head_block_hash);
         } else if should_send_to_builder {
-            let span: Option<BoxedSpan> =
-                if let (Some(payload_attributes), Some(local_payload_id)) =
-                    (payload_attributes.clone(), l2_response.payload_id)
-                {
-                    let mut parent_span = self
-                        .payload_trace_context
-                        .tracer
-                        .start_with_context("build-block", &Context::current());
+            let ctx: Option<Context> = if let (Some(payload_attributes), Some(local_payload_id)) =
+                (payload_attributes.clone(), l2_response.payload_id)
+            {
+                let mut parent_span = self
+                    .payload_trace_context
+                    .tracer
+                    .start_with_context("build-block", &Context::current());
+
+                parent_span.set_attribute(KeyValue::new(
+                    "parent_hash",
+                    fork_choice_state.head_block_hash.to_string(),
+                ));
+                parent_span.set_attribute(KeyValue::new(
+                    "timestamp",
+                    payload_attributes.payload_attributes.timestamp as i64,
+                ));
+                parent_span
+                    .set_attribute(KeyValue::new("payload_id", local_payload_id.to_string()));
+                let ctx =
+                    Context::current().with_remote_span_context(parent_span.span_context().clone());
+                self.payload_trace_context
+                    .store(
+                        local_payload_id,
+                        fork_choice_state.head_block_hash,
+                        parent_span,
+                    )
+                    .await;
+                Some(ctx)
+            } else {
+                None
+            };
 
-                    parent_span.set_attribute(KeyValue::new(
-                        "parent_hash",
-                        fork_choice_state.head_block_hash.to_string(),
-                    ));
-                    parent_span.set_attribute(KeyValue::new(
-                        "timestamp",
-                        payload_attributes.payload_attributes.timestamp as i64,
-                    ));
-                    parent_span
-                        .set_attribute(KeyValue::new("payload_id", local_payload_id.to_string()));
-                    let ctx = Context::current()
-                        .with_remote_span_context(parent_span.span_context().clone());
+            // async call to every builder to trigger payload building and sync
+            for (builder_index, builder_client) in self.builder_clients.iter().enumerate() {
+                // Only the block building calls probe an open circuit breaker
+                let builder_execution_mode = if payload_attributes.is_some() {
+                    self.builder_execution_mode(builder_index).await
+                } else {
+                    execution_mode
+                        .clone()
+                        .restrict(self.circuit_breakers[builder_index].execution_mode().await)
+                };
+                if builder_execution_mode.is_disabled() {
+                    debug!(message = "builder execution mode is disabled, skipping FCU call to builder", "url" = ?builder_client.auth_rpc, "head_block_hash" = %fork_choice_state.head_block_hash);
+                    continue;
+                }
+                // The call is queued before spawning, so the builder receives it in order
+                let Some(fcu_response) = self.queue_sync_call(builder_index, |sync_queue| {
+                    sync_queue.fork_choice_updated(
+                        fork_choice_state,
+                        payload_attributes.clone(),
+                        version,
+                    )
+                }) else {
+                    continue;
+                };
+                let span: Option<BoxedSpan> = ctx.as_ref().map(|ctx| {
                     self.payload_trace_context
-                        .store(
-                            local_payload_id,
-                            fork_choice_state.head_block_hash,
-                            parent_span,
-                        )
-                        .await;
-                    Some(
+                        .tracer
+                        .start_with_context("fcu", ctx)
+                });
+                let builder_client = builder_client.clone();
+                let catch_up = self.builder_catch_up(builder_index);
+                let sync_tracker = self.sync_trackers[builder_index].clone();
+                let builder_recovery = self.builder_recoveries[builder_index].clone();
+                let payload_trace_context = self.payload_trace_context.clone();
+                let local_payload_id = l2_response.payload_id;
+                let fcu_done = match (&payload_attributes, local_payload_id) {
+                    (Some(_), Some(local_id)) => Some(
                         self.payload_trace_context
-                            .tracer
-                            .start_with_context("fcu", &ctx),
-                    )
-                } else {
-                    None
+                            .store_pending_fcu(local_id, builder_index)
+                            .await,
+                    ),
+                    _ => None,
                 };
-
-            // async call to builder to trigger payload building and sync
-            let builder_client = self.builder_client.clone();
-            let attr = payload_attributes.clone();
-            let payload_trace_context = self.payload_trace_context.clone();
-            let local_payload_id = l2_response.payload_id;
-            tokio::spawn(async move {
-                match builder_client
-                    .fork_choice_updated_v3(fork_choice_state, attr)
-                    .await
-                {
-                    Ok(response) => {
-                        let external_payload_id = response.payload_id;
-                        if let (Some(local_id), Some(external_id)) =
-                            (local_payload_id, external_payload_id)
-                        {
-                            // Only store mapping if local and external IDs are different
-                            if local_id != external_id {
-                                payload_trace_context
-                                    .store_payload_id_mapping(local_id, external_id)
-                                    .await;
+                tokio::spawn(async move {
+                    match fcu_response
+                        .await
+                        .unwrap_or_else(|_| Err(ErrorCode::InternalError.into()))
+                    {
+                        Ok(response) => {
+                            builder_recovery.record_result(true);
+                            let external_payload_id = response.payload_id;
+                            if let (Some(local_id), Some(external_id)) =
+                                (local_payload_id, external_payload_id)
+                            {
+                                // Only store mapping if local and external IDs are different
+                                if local_id != external_id {
+                                    payload_trace_context
+                                        .store_payload_id_mapping(
+                                            local_id,
+                                            builder_index,
+                                            external_id,
+                                        )
+                                        .await;
+                                }
+                            }
+                            if let Some(fcu_done) = fcu_done {
+                                let _ = fcu_done.send(true);
+                            }
+                            sync_tracker.record(&response.payload_status.status, true);
+                            if response.payload_status.status.is_syncing() {
+                                if let Some(catch_up) = catch_up {
+                                    catch_up.trigger();
+                                }
+                            }
+                            if response.is_invalid() {
+                                let payload_id_str = external_payload_id
+                                    .map(|id| id.to_string())
+                                    .unwrap_or_default();
+                                error!(message = "builder rejected fork_choice_updated with attributes", "url" = ?builder_client.auth_rpc, "payload_id" = payload_id_str, "validation_error" = %response.payload_status.status);
+                            } else if let Some(external_id) = external_payload_id {
+                                info!(
+                                    message = "called fork_choice_updated to builder with payload attributes",
+                                    "url" = ?builder_client.auth_rpc,
+                                    "payload_status" = %response.payload_status.status,
+                                    "payload_id" = %external_id
+                                );
+                            } else {
+                                info!(
+                                    message = "called fork_choice_updated to builder without payload attributes",
+                                    "url" = ?builder_client.auth_rpc,
+                                    "payload_status" = %response.payload_status.status
+                                );
                             }
                         }
-                        if response.is_invalid() {
-                            let payload_id_str = external_payload_id
-                                .map(|id| id.to_string())
-                                .unwrap_or_default();
-                            error!(message = "builder rejected fork_choice_updated_v3 with attributes", "url" = ?builder_client.auth_rpc, "payload_id" = payload_id_str, "validation_error" = %response.payload_status.status);
-                        } else if let Some(external_id) = external_payload_id {
-                            info!(
-                                message = "called fork_choice_updated_v3 to builder with payload attributes",
-                                "url" = ?builder_client.auth_rpc,
-                                "payload_status" = %response.payload_status.status,
-                                "payload_id" = %external_id
-                            );
-                        } else {
-                            info!(
-                                message = "called fork_choice_updated_v3 to builder without payload attributes",
+
+                        Err(e) => {
+                            builder_recovery.record_result(false);
+                            error!(
+                                message = "error calling fork_choice_updated to builder",
                                 "url" = ?builder_client.auth_rpc,
-                                "payload_status" = %response.payload_status.status
+                                "error" = %e,
+                                "head_block_hash" = %fork_choice_state.head_block_hash
                             );
                         }
                     }
-
-                    Err(e) => {
-                        error!(
-                            message = "error calling fork_choice_updated_v3 to builder",
-                            "url" = ?builder_client.auth_rpc,
-                            "error" = %e,
-                            "head_block_hash" = %fork_choice_state.head_block_hash
-                        );
-                    }
-                }
-                if let Some(mut s) = span {
-                    s.end()
-                };
-            });
+                    if let Some(mut s) = span {
+                        s.end()
+                    };
+                });
+            }
         } else {
             // If no payload attributes are provided, the builder will not build a block
             // We store a mapping from the local payload ID to an empty payload ID to signal
-            // during get_payload_v3 request that the builder does not need to be queried.
+            // during get_payload request that the builders do not need to be queried.
             if let Some(local_id) = l2_response.payload_id {
-                let payload_trace_context = self.payload_trace_context.clone();
-
-                payload_trace_context
-                    .store_payload_id_mapping(local_id, PayloadId::default())
-                    .await;
-            } else {
+                for builder_index in 0..self.builder_clients.len() {
+                    self.payload_trace_context
+                        .store_payload_id_mapping(local_id, builder_index, PayloadId::default())
+                        .await;
+                }
+            } else {
                 error!(message = "no local payload id returned from l2 client", "head_block_hash" = %fork_choice_state.head_block_hash);
             }
 
@@ -425,15 +1147,50 @@ impl RollupBoostServer {
         Ok(l2_response)
     }
 
-    async fn get_payload_v3(
+    async fn get_payload(
         &self,
         payload_id: PayloadId,
-    ) -> RpcResult<OpExecutionPayloadEnvelopeV3> {
-        info!(message = "received get_payload_v3", "payload_id" = %payload_id);
-        let l2_client_future = self.l2_client.get_payload_v3(payload_id);
+        version: PayloadVersion,
+    ) -> RpcResult<OpExecutionPayloadEnvelope> {
+        info!(message = "received get_payload", "payload_id" = %payload_id, "version" = %version);
+
+        if let Some(rollup_config) = &self.rollup_config {
+            if let Some(timestamp) = self
+                .payload_trace_context
+                .get_payload_timestamp(&payload_id)
+                .await
+            {
+                let expected_version = rollup_config.payload_version(timestamp);
+                if expected_version != version {
+                    error!(message = "get_payload version does not match the active hardfork", "expected_version" = %expected_version, "version" = %version, "payload_id" = %payload_id);
+                    return Err(unsupported_fork_error(expected_version, version));
+                }
+            }
+        }
 
-        let builder_client_future = Box::pin(async move {
-            let execution_mode = self.execution_mode.lock().await;
+        let deadline = self.deadline.deadline(
+            self.payload_trace_context
+                .get_payload_timestamp(&payload_id)
+                .await,
+        );
+        let canary_skipped = self.canary_skips(&payload_id).await;
+        // Latencies of both payloads, reported in the compare execution mode
+        let start = Instant::now();
+        let l2_latency = OnceLock::new();
+        let builder_latency = OnceLock::new();
+        // Set once the local payload can be returned in place of the builder payloads
+        let (l2_ready_tx, mut l2_ready) = watch::channel(false);
+        let l2_client_future = async {
+            let result = self.l2_client.get_payload(payload_id, version).await;
+            let _ = l2_latency.set(start.elapsed());
+            if result.is_ok() {
+                let _ = l2_ready_tx.send(true);
+            }
+            result
+        };
+
+        let builder_fetch = Box::pin(async move {
+            let execution_mode = self.execution_mode.lock().await.clone();
             if !execution_mode.is_get_payload_enabled() {
                 info!(message = "dry run mode is enabled, skipping get payload builder call");
 
@@ -444,6 +1201,15 @@ impl RollupBoostServer {
                     None::<String>,
                 )));
             }
+            if canary_skipped {
+                info!(message = "block not selected by the canary rollout, skipping get payload builder call", "percentage" = self.canary.percentage(), "payload_id" = %payload_id);
+
+                return Err(ClientError::Call(ErrorObject::owned(
+                    INVALID_REQUEST_CODE,
+                    "Block not selected by the canary rollout",
+                    None::<String>,
+                )));
+            }
 
             let parent_span = self
                 .payload_trace_context
@@ -456,47 +1222,70 @@ impl RollupBoostServer {
                 )
             });
 
-            // Get the external builder's payload ID that corresponds to our local payload ID
-            // If no mapping exists, fallback to local ID
-            let external_payload_id = self
-                .payload_trace_context
-                .get_external_payload_id(&payload_id)
-                .await
-                .unwrap_or(payload_id);
-
-            if external_payload_id == PayloadId::default() {
-                info!(
-                    message =
-                        "no-tx-pool call and builder did not build a block, defer to L2 result"
-                );
-
-                // Note: We are sending an error here to return early from the future and this error
-                // is not logged later on, so it's not causing issues. However, we should find a better
-                // way to handle this case in the future rather than relying on this error being silently
-                // ignored.
-                return Err(ClientError::Call(ErrorObject::owned(
-                    INVALID_REQUEST_CODE,
-                    "Builder payload was not valid",
-                    None::<String>,
-                )));
+            // Collect the valid payloads of every builder until the deadline, stopping at the
+            // first one if the selection policy does not need to compare them
+            let mut builder_payloads: FuturesUnordered<_> = self
+                .builder_clients
+                .iter()
+                .enumerate()
+                .map(|(builder_index, builder)| async move {
+                    let result = self
+                        .get_builder_payload(builder_index, builder, payload_id, version, deadline)
+                        .await;
+                    (builder_index, result)
+                })
+                .collect();
+            let mut pending: Vec<usize> = (0..self.builder_clients.len()).collect();
+            let mut valid_payloads = vec![];
+            // The builders are only abandoned once the budget expired and the local payload is
+            // ready, without a local payload they are awaited until they return
+            let expired = async {
+                sleep_until_deadline(deadline).await;
+                let _ = l2_ready.wait_for(|ready| *ready).await;
+            };
+            tokio::pin!(expired);
+            loop {
+                tokio::select! {
+                    next = builder_payloads.next() => {
+                        let Some((builder_index, result)) = next else {
+                            break;
+                        };
+                        pending.retain(|index| *index != builder_index);
+                        match result {
+                            Ok(Some(payload)) => {
+                                self.circuit_breakers[builder_index].record_success().await;
+                                valid_payloads.push(payload);
+                                if self.selection.builder_selection_policy.is_first_valid() {
+                                    break;
+                                }
+                            }
+                            // the builder was not queried for this block
+                            Ok(None) => {}
+                            Err(BuilderPayloadError::Builder) => {
+                                self.circuit_breakers[builder_index].record_failure().await
+                            }
+                            // the builder is not accountable for the rejection
+                            Err(BuilderPayloadError::Rejected) => {}
+                        }
+                    }
+                    _ = &mut expired => {
+                        // the builders still fetching or validating their payload are abandoned,
+                        // which does not count against their circuit breaker
+                        for &builder_index in &pending {
+                            let builder = &self.builder_clients[builder_index];
+                            warn!(message = "builder payload deadline exceeded, abandoning builder", "url" = ?builder.auth_rpc, "payload_id" = %payload_id);
+                            if let Some(metrics) = &self.metrics {
+                                metrics.increment_builder_deadline_exceeded(
+                                    builder.auth_rpc.to_string(),
+                                );
+                            }
+                        }
+                        break;
+                    }
+                }
             }
+            drop(builder_payloads);
 
-            let builder = self.builder_client.clone();
-            let (payload, source) = builder.get_payload_v3(external_payload_id).await.map_err(|e| {
-                error!(message = "error calling get_payload_v3 from builder", "url" = ?builder.auth_rpc, "error" = %e, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id);
-                e
-                })?;
-
-            let block_hash = ExecutionPayload::from(payload.clone().execution_payload).block_hash();
-            info!(message = "received payload from builder", "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id, "block_hash" = %block_hash);
-
-            // Send the payload to the local execution engine with engine_newPayload to validate the block from the builder.
-            // Otherwise, we do not want to risk the network to a halt since op-node will not be able to propose the block.
-            // If validation fails, return the local block since that one has already been validated.
-            let payload_status = self.l2_client.auth_client.new_payload_v3(payload.execution_payload.clone(), vec![], payload.parent_beacon_block_root).await.map_err(|e| {
-                error!(message = "error calling new_payload_v3 to validate builder payload", "url" = ?self.l2_client.auth_rpc, "error" = %e, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id);
-                e
-            })?;
             if let Some(mut s) = span {
                 s.end();
             };
@@ -507,32 +1296,70 @@ impl RollupBoostServer {
                 }
             };
 
-            if payload_status.is_invalid() {
-                error!(message = "builder payload was not valid", "url" = ?builder.auth_rpc, "payload_status" = %payload_status.status, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id);
-                Err(ClientError::Call(ErrorObject::owned(
-                    INVALID_REQUEST_CODE,
-                    "Builder payload was not valid",
-                    None::<String>,
-                )))
-            } else {
-                info!(message = "received payload status from local execution engine validating builder payload", "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id);
-                Ok((payload, source))
+            let valid_payloads_count = valid_payloads.len();
+            let selected = self
+                .selection
+                .builder_selection_policy
+                .select(valid_payloads)
+                .ok_or_else(|| {
+                    ClientError::Call(ErrorObject::owned(
+                        INVALID_REQUEST_CODE,
+                        "No valid builder payload",
+                        None::<String>,
+                    ))
+                })?;
+            let builder = &self.builder_clients[selected.builder_index];
+            info!(message = "selected builder payload", "url" = ?builder.auth_rpc, "policy" = ?self.selection.builder_selection_policy, "valid_payloads" = valid_payloads_count, "block_value" = %selected.block_value, "local_payload_id" = %payload_id);
+            if let Some(metrics) = &self.metrics {
+                metrics.increment_builder_payload_selected(builder.auth_rpc.to_string());
             }
+            Ok((selected.payload, PayloadSource::Builder))
         });
+        let builder_client_future = async {
+            let result = builder_fetch.await;
+            let _ = builder_latency.set(start.elapsed());
+            result
+        };
 
         let (l2_payload, builder_payload) = tokio::join!(l2_client_future, builder_client_future);
-        let payload = match (builder_payload, l2_payload) {
-            (Ok(builder), _) => Ok(builder),
-            (Err(_), Ok(l2)) => Ok(l2),
-            (Err(_), Err(e)) => Err(e),
+        let execution_mode = self.execution_mode.lock().await.clone();
+        let (payload, reason) = match (builder_payload, l2_payload) {
+            (builder, l2) if execution_mode.is_compare() => {
+                let diff = PayloadDiff::new(
+                    payload_id,
+                    builder.as_ref().map(|(payload, _)| payload),
+                    l2.as_ref().ok().map(|(payload, _)| payload),
+                    builder_latency.get().copied(),
+                    l2_latency.get().copied(),
+                );
+                info!(message = "compared builder and local payloads in compare mode", "builder_valid" = diff.builder_valid, "block_value_delta" = ?diff.block_value_delta, "builder_gas_used" = ?diff.builder_gas_used, "local_gas_used" = ?diff.local_gas_used, "builder_only_txs" = diff.builder_only_txs.len(), "local_only_txs" = diff.local_only_txs.len(), "payload_id" = %payload_id);
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_payload_diff(&diff);
+                }
+                self.compare_history.push(diff).await;
+                (l2, SelectionReason::CompareMode)
+            }
+            (_, l2) if canary_skipped => (l2, SelectionReason::CanarySkipped),
+            (Ok(builder), Ok(l2)) => {
+                let (source, reason) = self.selection.select_block(&builder.0, &l2.0);
+                info!(message = "compared builder and local payloads", "policy" = ?self.selection.block_selection_policy, "margin" = self.selection.block_selection_margin, "builder_block_value" = %builder.0.block_value(), "local_block_value" = %l2.0.block_value(), "builder_gas_used" = builder.0.gas_used(), "local_gas_used" = l2.0.gas_used(), "builder_tx_count" = builder.0.transaction_count(), "local_tx_count" = l2.0.transaction_count(), "payload_id" = %payload_id);
+                if source.is_builder() {
+                    (Ok(builder), reason)
+                } else {
+                    (Ok(l2), reason)
+                }
+            }
+            (Ok(builder), Err(_)) => (Ok(builder), SelectionReason::NoLocalPayload),
+            (Err(_), Ok(l2)) => (Ok(l2), SelectionReason::NoBuilderPayload),
+            (Err(_), Err(e)) => (Err(e), SelectionReason::NoBuilderPayload),
         };
         payload.map(|(payload, context)| {
-            let inner_payload = ExecutionPayload::from(payload.clone().execution_payload);
-            let block_hash = inner_payload.block_hash();
-            let block_number = inner_payload.block_number();
+            let block_hash = payload.block_hash();
+            let block_number = payload.block_number();
 
             if let Some(metrics) = &self.metrics {
                 metrics.increment_blocks_created(&context);
+                metrics.increment_block_selection(&context, reason);
             }
 
             // Note: This log message is used by integration tests to track payload context.
@@ -543,24 +1370,166 @@ impl RollupBoostServer {
                 "hash" = %block_hash,
                 "number" = %block_number,
                 "context" = %context,
+                "reason" = %reason,
                 "payload_id" = %payload_id
             );
             payload
         })
     }
 
-    async fn new_payload_v3(
+    /// Fetches the payload built by one builder and validates it against the local execution engine.
+    /// Returns `None` if the builder is not queried for this block.
+    async fn get_builder_payload(
         &self,
-        payload: ExecutionPayloadV3,
-        versioned_hashes: Vec<B256>,
-        parent_beacon_block_root: B256,
-    ) -> RpcResult<PayloadStatus> {
-        let execution_payload = ExecutionPayload::from(payload.clone());
-        let block_hash = execution_payload.block_hash();
-        let parent_hash = execution_payload.parent_hash();
-        info!(message = "received new_payload_v3", "block_hash" = %block_hash);
+        builder_index: usize,
+        builder: &ExecutionClient,
+        payload_id: PayloadId,
+        version: PayloadVersion,
+        deadline: Option<tokio::time::Instant>,
+    ) -> Result<Option<BuilderPayload<OpExecutionPayloadEnvelope>>, BuilderPayloadError> {
+        if !self
+            .builder_execution_mode(builder_index)
+            .await
+            .is_get_payload_enabled()
+        {
+            info!(message = "builder circuit breaker is open, skipping get payload builder call", "url" = ?builder.auth_rpc, "local_payload_id" = %payload_id);
+            return Ok(None);
+        }
+
+        if self.sync_trackers[builder_index].is_syncing() {
+            info!(message = "builder is syncing, skipping get payload builder call", "url" = ?builder.auth_rpc, "local_payload_id" = %payload_id);
+            return Ok(None);
+        }
+
+        // The FCU to the builder is spawned, so getPayload can arrive before the builder returned
+        // its payload ID. Wait for it to resolve the right external payload ID.
+        if self
+            .payload_trace_context
+            .wait_for_pending_fcu(&payload_id, builder_index, deadline)
+            .await
+        {
+            warn!(message = "get_payload called before the builder fork_choice_updated completed", "url" = ?builder.auth_rpc, "local_payload_id" = %payload_id);
+            if let Some(metrics) = &self.metrics {
+                metrics.increment_builder_fcu_race(builder.auth_rpc.to_string());
+            }
+        }
+
+        // Get the external builder's payload ID that corresponds to our local payload ID
+        // If no mapping exists, fallback to local ID
+        let external_payload_id = self
+            .payload_trace_context
+            .get_external_payload_id(&payload_id, builder_index)
+            .await
+            .unwrap_or(payload_id);
+
+        if external_payload_id == PayloadId::default() {
+            info!(
+                message = "no-tx-pool call and builder did not build a block, defer to L2 result",
+                "url" = ?builder.auth_rpc
+            );
+            return Ok(None);
+        }
+
+        let (payload, _) = builder.get_payload(external_payload_id, version).await.map_err(|e| {
+            error!(message = "error calling get_payload from builder", "url" = ?builder.auth_rpc, "error" = %e, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id, "version" = %version);
+            BuilderPayloadError::Builder
+        })?;
+
+        let block_hash = payload.block_hash();
+        info!(message = "received payload from builder", "url" = ?builder.auth_rpc, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id, "block_hash" = %block_hash);
+
+        if let Some(rollup_config) = &self.rollup_config {
+            let expected_version = rollup_config.payload_version(payload.timestamp());
+            if expected_version != version {
+                error!(message = "builder payload does not match the active hardfork", "url" = ?builder.auth_rpc, "expected_version" = %expected_version, "version" = %version, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id);
+                return Err(BuilderPayloadError::Builder);
+            }
+        }
+
+        // Check that the builder built the block op-node asked for before spending a newPayload on it
+        if let Some((parent_hash, attributes)) = self
+            .payload_trace_context
+            .get_payload_attributes(&payload_id)
+            .await
+        {
+            if let Err(e) = validate_payload_attributes(&payload, parent_hash, &attributes) {
+                error!(message = "builder payload does not match the payload attributes", "url" = ?builder.auth_rpc, "error" = %e, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id);
+                return Err(BuilderPayloadError::Builder);
+            }
+        }
+
+        // The batcher throttles the block size with the miner limits, a builder block above them
+        // could not be posted in time
+        let execution_payload = payload.execution_payload();
+        let payload_v1 = execution_payload.as_v1();
+        let limits = self.miner_settings.lock().await.limits;
+        if let Err(e) = limits.check(&payload_v1.transactions, payload_v1.gas_limit) {
+            error!(message = "builder payload exceeds the miner limits", "url" = ?builder.auth_rpc, "error" = %e, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id);
+            if let Some(metrics) = &self.metrics {
+                metrics.increment_builder_miner_limit_exceeded(
+                    builder.auth_rpc.to_string(),
+                    e.limit(),
+                );
+            }
+            return Err(BuilderPayloadError::Builder);
+        }
+
+        // Send the payload to the local execution engine with engine_newPayload to validate the block from the builder.
+        // Otherwise, we do not want to risk the network to a halt since op-node will not be able to propose the block.
+        // If validation fails, return the local block since that one has already been validated.
+        let payload_status = self.validate_builder_payload(NewPayload::from(payload.clone())).await.map_err(|e| {
+            error!(message = "error calling new_payload to validate builder payload", "url" = ?self.l2_client.auth_rpc, "error" = %e, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id, "version" = %version);
+            BuilderPayloadError::Rejected
+        })?;
+
+        if payload_status.is_invalid() {
+            error!(message = "builder payload was not valid", "url" = ?builder.auth_rpc, "payload_status" = %payload_status.status, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id);
+            Err(BuilderPayloadError::Builder)
+        } else {
+            info!(message = "received payload status from local execution engine validating builder payload", "url" = ?builder.auth_rpc, "local_payload_id" = %payload_id, "external_payload_id" = %external_payload_id);
+            Ok(Some(BuilderPayload {
+                builder_index,
+                block_value: payload.block_value(),
+                payload,
+            }))
+        }
+    }
+
+    /// Validates a builder payload on the validators, falling back to the L2 execution engine
+    /// when they do not return a VALID or INVALID status.
+    async fn validate_builder_payload(&self, new_payload: NewPayload) -> RpcResult<PayloadStatus> {
+        if let Some(validator) = &self.validator {
+            if let Some(payload_status) = validator.new_payload(new_payload.clone()).await {
+                return Ok(payload_status);
+            }
+            warn!(message = "validators did not validate builder payload, falling back to the l2 client", "block_hash" = %new_payload.block_hash());
+            if let Some(metrics) = &self.metrics {
+                metrics.increment_validator_fallback();
+            }
+        }
+        self.l2_client.new_payload(new_payload).await
+    }
+
+    async fn new_payload(&self, new_payload: NewPayload) -> RpcResult<PayloadStatus> {
+        let block_hash = new_payload.block_hash();
+        let parent_hash = new_payload.parent_hash();
+        info!(message = "received new_payload", "block_hash" = %block_hash, "version" = %new_payload.version());
+        self.payload_trace_context
+            .store_block_number(block_hash, new_payload.block_number())
+            .await;
+
+        if let Some(rollup_config) = &self.rollup_config {
+            let expected_version = rollup_config.payload_version(new_payload.timestamp());
+            if expected_version != new_payload.version() {
+                error!(message = "new_payload version does not match the active hardfork", "expected_version" = %expected_version, "version" = %new_payload.version(), "block_hash" = %block_hash);
+                return Err(unsupported_fork_error(
+                    expected_version,
+                    new_payload.version(),
+                ));
+            }
+        }
         // async call to builder to sync the builder node
-        let execution_mode = self.execution_mode.lock().await;
+        let execution_mode = self.execution_mode.lock().await.clone();
         if self.boost_sync && !execution_mode.is_disabled() {
             let parent_spans = self
                 .payload_trace_context
@@ -582,40 +1551,85 @@ impl RollupBoostServer {
                 .remove_by_parent_hash(&parent_hash)
                 .await;
 
-            let builder = self.builder_client.clone();
-            let builder_payload = payload.clone();
-            let builder_versioned_hashes = versioned_hashes.clone();
+            // The calls are queued before spawning, so every builder receives them in order
+            let mut builders = vec![];
+            for (builder_index, builder) in self.builder_clients.iter().enumerate() {
+                if execution_mode
+                    .clone()
+                    .restrict(self.circuit_breakers[builder_index].execution_mode().await)
+                    .is_disabled()
+                {
+                    continue;
+                }
+                if let Some(response) = self.queue_sync_call(builder_index, |sync_queue| {
+                    sync_queue.new_payload(new_payload.clone())
+                }) {
+                    builders.push((
+                        builder.clone(),
+                        response,
+                        self.sync_trackers[builder_index].clone(),
+                        self.builder_catch_up(builder_index),
+                        self.builder_recoveries[builder_index].clone(),
+                    ));
+                }
+            }
             tokio::spawn(async move {
-                let _ = builder.new_payload_v3(builder_payload, builder_versioned_hashes, parent_beacon_block_root).await
-                .map(|response: PayloadStatus| {
-                    if response.is_invalid() {
-                        error!(message = "builder rejected new_payload_v3", "url" = ?builder.auth_rpc, "block_hash" = %block_hash);
-                    } else {
-                        info!(message = "called new_payload_v3 to builder", "url" = ?builder.auth_rpc, "payload_status" = %response.status, "block_hash" = %block_hash);
-                    }
-                }).map_err(|e| {
-                    error!(message = "error calling new_payload_v3 to builder", "url" = ?builder.auth_rpc, "error" = %e, "block_hash" = %block_hash);
-                    e
-                });
+                futures::future::join_all(builders.into_iter().map(|(builder, response, sync_tracker, catch_up, builder_recovery)| async move {
+                    let _ = response.await
+                    .unwrap_or_else(|_| Err(ErrorCode::InternalError.into()))
+                    .map(|response: PayloadStatus| {
+                        builder_recovery.record_result(true);
+                        sync_tracker.record(&response.status, false);
+                        if response.is_invalid() {
+                            error!(message = "builder rejected new_payload", "url" = ?builder.auth_rpc, "block_hash" = %block_hash);
+                        } else {
+                            if response.status.is_syncing() {
+                                if let Some(catch_up) = catch_up {
+                                    catch_up.trigger();
+                                }
+                            }
+                            info!(message = "called new_payload to builder", "url" = ?builder.auth_rpc, "payload_status" = %response.status, "block_hash" = %block_hash);
+                        }
+                    }).map_err(|e| {
+                        builder_recovery.record_result(false);
+                        error!(message = "error calling new_payload to builder", "url" = ?builder.auth_rpc, "error" = %e, "block_hash" = %block_hash);
+                        e
+                    });
+                }))
+                .await;
                 if let Some(mut spans) = spans {
                     spans.iter_mut().for_each(|s| s.end());
                 };
             });
         }
-        self.l2_client
-            .new_payload_v3(payload, versioned_hashes, parent_beacon_block_root)
-            .await
+        self.l2_client.new_payload(new_payload).await
     }
 }
 
+fn unsupported_fork_error(
+    expected_version: PayloadVersion,
+    version: PayloadVersion,
+) -> ErrorObjectOwned {
+    ErrorObject::owned(
+        UNSUPPORTED_FORK_CODE,
+        "Unsupported fork",
+        Some(format!(
+            "expected {} method for the active hardfork, got {}",
+            expected_version, version
+        )),
+    )
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::selection::{BlockSelectionPolicy, BuilderSelectionPolicy};
     use alloy_primitives::hex;
     use alloy_primitives::{FixedBytes, U256};
     use alloy_rpc_types_engine::{
-        BlobsBundleV1, ExecutionPayloadV1, ExecutionPayloadV2, PayloadStatusEnum,
+        BlobsBundleV1, ExecutionPayloadFieldV2, ExecutionPayloadV1, ExecutionPayloadV2,
+        PayloadStatusEnum,
     };
 
     use alloy_rpc_types_engine::JwtSecret;
@@ -634,6 +1648,8 @@ mod tests {
     const L2_ADDR: &str = "127.0.0.1:8545";
     const BUILDER_PORT: u16 = 8544;
     const BUILDER_ADDR: &str = "127.0.0.1:8544";
+    const SECOND_BUILDER_PORT: u16 = 8543;
+    const SECOND_BUILDER_ADDR: &str = "127.0.0.1:8543";
     const SERVER_ADDR: &str = "0.0.0.0:8556";
 
     #[derive(Debug, Clone)]
@@ -641,11 +1657,17 @@ mod tests {
         fcu_requests: Arc<Mutex<Vec<(ForkchoiceState, Option<OpPayloadAttributes>)>>>,
         get_payload_requests: Arc<Mutex<Vec<PayloadId>>>,
         new_payload_requests: Arc<Mutex<Vec<(ExecutionPayloadV3, Vec<B256>, B256)>>>,
+        fcu_v2_requests: Arc<Mutex<Vec<(ForkchoiceState, Option<OpPayloadAttributes>)>>>,
+        get_payload_v2_requests: Arc<Mutex<Vec<PayloadId>>>,
+        new_payload_v2_requests: Arc<Mutex<Vec<ExecutionPayloadInputV2>>>,
+        get_payload_v4_requests: Arc<Mutex<Vec<PayloadId>>>,
+        new_payload_v4_requests: Arc<Mutex<Vec<(ExecutionPayloadV3, Vec<B256>, B256, Vec<Bytes>)>>>,
         fcu_response: RpcResult<ForkchoiceUpdated>,
         get_payload_response: RpcResult<OpExecutionPayloadEnvelopeV3>,
         new_payload_response: RpcResult<PayloadStatus>,
 
         pub override_payload_id: Option<PayloadId>,
+        pub get_payload_delay: Option<std::time::Duration>,
     }
 
     impl MockEngineServer {
@@ -654,6 +1676,11 @@ mod tests {
                 fcu_requests: Arc::new(Mutex::new(vec![])),
                 get_payload_requests: Arc::new(Mutex::new(vec![])),
                 new_payload_requests: Arc::new(Mutex::new(vec![])),
+                fcu_v2_requests: Arc::new(Mutex::new(vec![])),
+                get_payload_v2_requests: Arc::new(Mutex::new(vec![])),
+                new_payload_v2_requests: Arc::new(Mutex::new(vec![])),
+                get_payload_v4_requests: Arc::new(Mutex::new(vec![])),
+                new_payload_v4_requests: Arc::new(Mutex::new(vec![])),
                 fcu_response: Ok(ForkchoiceUpdated::new(PayloadStatus::from_status(PayloadStatusEnum::Valid))),
                 get_payload_response: Ok(OpExecutionPayloadEnvelopeV3{
                     execution_payload: ExecutionPayloadV3 {
@@ -689,6 +1716,7 @@ mod tests {
                 parent_beacon_block_root: B256::ZERO,
             }),
             override_payload_id: None,
+            get_payload_delay: None,
             new_payload_response: Ok(PayloadStatus::from_status(PayloadStatusEnum::Valid)),
         }
         }
@@ -697,10 +1725,13 @@ mod tests {
     struct TestHarness {
         l2_server: ServerHandle,
         l2_mock: MockEngineServer,
-        builder_server: ServerHandle,
+        builder_servers: Vec<ServerHandle>,
         builder_mock: MockEngineServer,
         proxy_server: ServerHandle,
         client: HttpClient,
+        execution_mode: Arc<tokio::sync::Mutex<ExecutionMode>>,
+        compare_history: Arc<CompareHistory>,
+        miner_settings: Arc<tokio::sync::Mutex<MinerSettings>>,
     }
 
     impl TestHarness {
@@ -708,6 +1739,28 @@ mod tests {
             boost_sync: bool,
             l2_mock: Option<MockEngineServer>,
             builder_mock: Option<MockEngineServer>,
+            rollup_config: Option<RollupConfig>,
+        ) -> Self {
+            Self::with_builders(
+                boost_sync,
+                l2_mock,
+                vec![builder_mock.unwrap_or(MockEngineServer::new())],
+                SelectionArgs::default(),
+                CircuitBreakerArgs::default(),
+                DeadlineArgs::default(),
+                rollup_config,
+            )
+            .await
+        }
+
+        async fn with_builders(
+            boost_sync: bool,
+            l2_mock: Option<MockEngineServer>,
+            builder_mocks: Vec<MockEngineServer>,
+            selection: SelectionArgs,
+            circuit_breaker: CircuitBreakerArgs,
+            deadline: DeadlineArgs,
+            rollup_config: Option<RollupConfig>,
         ) -> Self {
             let jwt_secret = JwtSecret::random();
 
@@ -716,25 +1769,44 @@ mod tests {
                 ExecutionClient::new(l2_auth_rpc, jwt_secret, 2000, None, PayloadSource::L2)
                     .unwrap();
 
-            let builder_auth_rpc =
-                Uri::from_str(&format!("http://{}:{}", HOST, BUILDER_PORT)).unwrap();
-            let builder_client = ExecutionClient::new(
-                builder_auth_rpc,
-                jwt_secret,
-                2000,
-                None,
-                PayloadSource::Builder,
-            )
-            .unwrap();
+            let builder_ports = [
+                (BUILDER_PORT, BUILDER_ADDR),
+                (SECOND_BUILDER_PORT, SECOND_BUILDER_ADDR),
+            ];
+            let builder_clients = builder_ports
+                .iter()
+                .take(builder_mocks.len())
+                .map(|(port, _)| {
+                    let builder_auth_rpc =
+                        Uri::from_str(&format!("http://{}:{}", HOST, port)).unwrap();
+                    ExecutionClient::new(
+                        builder_auth_rpc,
+                        jwt_secret,
+                        2000,
+                        None,
+                        PayloadSource::Builder,
+                    )
+                    .unwrap()
+                })
+                .collect();
 
-            let rollup_boost_client = RollupBoostServer::new(
+            let mut rollup_boost_client = RollupBoostServer::new(
                 l2_client,
-                builder_client,
+                builder_clients,
                 boost_sync,
                 None,
                 ExecutionMode::Enabled,
-            );
+            )
+            .with_selection(selection)
+            .with_circuit_breaker(circuit_breaker)
+            .with_deadline(deadline);
+            if let Some(rollup_config) = rollup_config {
+                rollup_boost_client = rollup_boost_client.with_rollup_config(rollup_config);
+            }
 
+            let execution_mode = rollup_boost_client.execution_mode.clone();
+            let compare_history = rollup_boost_client.compare_history.clone();
+            let miner_settings = rollup_boost_client.miner_settings.clone();
             let module: RpcModule<()> = rollup_boost_client.try_into().unwrap();
 
             let proxy_server = ServerBuilder::default()
@@ -743,26 +1815,33 @@ mod tests {
                 .unwrap()
                 .start(module);
             let l2_mock = l2_mock.unwrap_or(MockEngineServer::new());
-            let builder_mock = builder_mock.unwrap_or(MockEngineServer::new());
             let l2_server = spawn_server(l2_mock.clone(), L2_ADDR).await;
-            let builder_server = spawn_server(builder_mock.clone(), BUILDER_ADDR).await;
+            let mut builder_servers = vec![];
+            for (builder_mock, (_, addr)) in builder_mocks.iter().zip(builder_ports) {
+                builder_servers.push(spawn_server(builder_mock.clone(), addr).await);
+            }
             TestHarness {
                 l2_server,
                 l2_mock,
-                builder_server,
-                builder_mock,
+                builder_servers,
+                builder_mock: builder_mocks[0].clone(),
                 proxy_server,
                 client: HttpClient::builder()
                     .build(format!("http://{SERVER_ADDR}"))
                     .unwrap(),
+                execution_mode,
+                compare_history,
+                miner_settings,
             }
         }
 
         async fn cleanup(self) {
             self.l2_server.stop().unwrap();
             self.l2_server.stopped().await;
-            self.builder_server.stop().unwrap();
-            self.builder_server.stopped().await;
+            for builder_server in self.builder_servers {
+                builder_server.stop().unwrap();
+                builder_server.stopped().await;
+            }
             self.proxy_server.stop().unwrap();
             self.proxy_server.stopped().await;
         }
@@ -771,14 +1850,163 @@ mod tests {
     #[tokio::test]
     async fn test_server() {
         engine_success().await;
+        engine_success_v2().await;
+        engine_success_v4().await;
+        hardfork_version_mismatch().await;
         boost_sync_enabled().await;
         builder_payload_err().await;
+        multiple_builders_selection().await;
+        block_selection_by_value().await;
+        circuit_breaker_trips().await;
+        miner_limit_violation_trips_circuit_breaker().await;
+        validation_error_does_not_trip_circuit_breaker().await;
+        builder_deadline_exceeded().await;
+        builder_deadline_without_local_payload().await;
+        builder_syncing().await;
+        compare_mode().await;
         test_local_external_payload_ids_different().await;
         test_local_external_payload_ids_same().await;
     }
 
+    #[tokio::test]
+    async fn test_state_file_written_on_change() -> eyre::Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "rollup-boost-server-state-{}.json",
+            std::process::id()
+        ));
+        let l2_client = ExecutionClient::new(
+            Uri::from_static("http://127.0.0.1:8551"),
+            JwtSecret::random(),
+            2000,
+            None,
+            PayloadSource::L2,
+        )?;
+        let server = RollupBoostServer::new(l2_client, vec![], false, None, ExecutionMode::Enabled);
+        server
+            .start_state_file(StateArgs {
+                state_file: Some(path.clone()),
+                ..Default::default()
+            })
+            .await?;
+
+        // the state is written once at startup, then on every change
+        sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(
+            PersistedState::load(&path)?.unwrap().canary_percentage,
+            Some(100)
+        );
+        server.canary.set_percentage(10);
+        sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(
+            PersistedState::load(&path)?.unwrap().canary_percentage,
+            Some(10)
+        );
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_payload_block_number() {
+        let payload_trace_context = PayloadTraceContext::new();
+        let parent_hash = B256::repeat_byte(1);
+        let payload_id = PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]);
+        let attributes = OpPayloadAttributes {
+            payload_attributes: alloy_rpc_types_engine::PayloadAttributes {
+                timestamp: 1000,
+                prev_randao: B256::ZERO,
+                suggested_fee_recipient: alloy_primitives::Address::ZERO,
+                withdrawals: Some(vec![]),
+                parent_beacon_block_root: Some(B256::ZERO),
+                target_blobs_per_block: None,
+                max_blobs_per_block: None,
+            },
+            transactions: None,
+            no_tx_pool: None,
+            gas_limit: Some(30_000_000),
+            eip_1559_params: None,
+        };
+        payload_trace_context
+            .store_payload_attributes(payload_id, parent_hash, attributes)
+            .await;
+
+        // the parent block was not received with engine_newPayload
+        assert_eq!(
+            payload_trace_context
+                .get_payload_block_number(&payload_id)
+                .await,
+            None
+        );
+
+        payload_trace_context
+            .store_block_number(parent_hash, 41)
+            .await;
+        assert_eq!(
+            payload_trace_context
+                .get_payload_block_number(&payload_id)
+                .await,
+            Some(42)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_pending_fcu() {
+        let payload_trace_context = Arc::new(PayloadTraceContext::new());
+        let local_id = PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]);
+        let external_id = PayloadId::new([0, 0, 0, 0, 0, 0, 0, 2]);
+
+        // no FCU in flight for the builder
+        assert!(
+            !payload_trace_context
+                .wait_for_pending_fcu(&local_id, 0, None)
+                .await
+        );
+
+        // getPayload arrives before the builder FCU completed
+        let fcu_done = payload_trace_context.store_pending_fcu(local_id, 0).await;
+        let context = payload_trace_context.clone();
+        tokio::spawn(async move {
+            sleep(std::time::Duration::from_millis(50)).await;
+            context
+                .store_payload_id_mapping(local_id, 0, external_id)
+                .await;
+            fcu_done.send(true).unwrap();
+        });
+        assert!(
+            payload_trace_context
+                .wait_for_pending_fcu(&local_id, 0, None)
+                .await
+        );
+        assert_eq!(
+            payload_trace_context
+                .get_external_payload_id(&local_id, 0)
+                .await,
+            Some(external_id)
+        );
+        assert!(
+            !payload_trace_context
+                .wait_for_pending_fcu(&local_id, 0, None)
+                .await
+        );
+
+        // the wait is bounded by the deadline
+        let _fcu_done = payload_trace_context.store_pending_fcu(local_id, 1).await;
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_millis(50);
+        assert!(
+            payload_trace_context
+                .wait_for_pending_fcu(&local_id, 1, Some(deadline))
+                .await
+        );
+        assert_eq!(
+            payload_trace_context
+                .get_external_payload_id(&local_id, 1)
+                .await,
+            None
+        );
+    }
+
     async fn engine_success() {
-        let test_harness = TestHarness::new(false, None, None).await;
+        let test_harness = TestHarness::new(false, None, None, None).await;
 
         // test fork_choice_updated_v3 success
         let fcu = ForkchoiceState {
@@ -857,8 +2085,214 @@ mod tests {
         test_harness.cleanup().await;
     }
 
+    async fn engine_success_v2() {
+        let test_harness = TestHarness::new(false, None, None, None).await;
+
+        // test fork_choice_updated_v2 success
+        let fcu = ForkchoiceState {
+            head_block_hash: FixedBytes::random(),
+            safe_block_hash: FixedBytes::random(),
+            finalized_block_hash: FixedBytes::random(),
+        };
+        let fcu_response = test_harness.client.fork_choice_updated_v2(fcu, None).await;
+        assert!(fcu_response.is_ok());
+        {
+            let fcu_requests = test_harness.l2_mock.fcu_v2_requests.lock().unwrap();
+            assert_eq!(fcu_requests.len(), 1);
+            assert_eq!(fcu_requests[0].0, fcu);
+            assert_eq!(test_harness.l2_mock.fcu_requests.lock().unwrap().len(), 0);
+            assert_eq!(
+                test_harness
+                    .builder_mock
+                    .fcu_v2_requests
+                    .lock()
+                    .unwrap()
+                    .len(),
+                0
+            );
+        }
+
+        // test get_payload_v2 success, the builder payload is validated with new_payload_v2
+        let get_payload_response = test_harness
+            .client
+            .get_payload_v2(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+            .await;
+        assert!(get_payload_response.is_ok());
+        {
+            let l2_get_payload_requests =
+                test_harness.l2_mock.get_payload_v2_requests.lock().unwrap();
+            let builder_get_payload_requests = test_harness
+                .builder_mock
+                .get_payload_v2_requests
+                .lock()
+                .unwrap();
+            let l2_new_payload_requests =
+                test_harness.l2_mock.new_payload_v2_requests.lock().unwrap();
+            assert_eq!(l2_get_payload_requests.len(), 1);
+            assert_eq!(builder_get_payload_requests.len(), 1);
+            assert_eq!(l2_new_payload_requests.len(), 1);
+            assert_eq!(
+                test_harness
+                    .l2_mock
+                    .new_payload_requests
+                    .lock()
+                    .unwrap()
+                    .len(),
+                0
+            );
+        }
+
+        // test new_payload_v2 success
+        let payload = NewPayload::from(OpExecutionPayloadEnvelope::V2(
+            get_payload_response.unwrap(),
+        ));
+        let NewPayload::V2(payload) = payload else {
+            panic!("expected a V2 payload");
+        };
+        let new_payload_response = test_harness.client.new_payload_v2(payload.clone()).await;
+        assert!(new_payload_response.is_ok());
+        {
+            let l2_new_payload_requests =
+                test_harness.l2_mock.new_payload_v2_requests.lock().unwrap();
+            let builder_new_payload_requests = test_harness
+                .builder_mock
+                .new_payload_v2_requests
+                .lock()
+                .unwrap();
+            assert_eq!(l2_new_payload_requests.len(), 2);
+            assert_eq!(builder_new_payload_requests.len(), 0);
+            assert_eq!(*l2_new_payload_requests.last().unwrap(), payload);
+        }
+
+        test_harness.cleanup().await;
+    }
+
+    async fn engine_success_v4() {
+        let test_harness = TestHarness::new(false, None, None, None).await;
+
+        // test get_payload_v4 success, the builder payload is validated with new_payload_v4
+        let get_payload_response = test_harness
+            .client
+            .get_payload_v4(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+            .await;
+        assert!(get_payload_response.is_ok());
+        {
+            let l2_get_payload_requests =
+                test_harness.l2_mock.get_payload_v4_requests.lock().unwrap();
+            let builder_get_payload_requests = test_harness
+                .builder_mock
+                .get_payload_v4_requests
+                .lock()
+                .unwrap();
+            let l2_new_payload_requests =
+                test_harness.l2_mock.new_payload_v4_requests.lock().unwrap();
+            assert_eq!(l2_get_payload_requests.len(), 1);
+            assert_eq!(builder_get_payload_requests.len(), 1);
+            assert_eq!(l2_new_payload_requests.len(), 1);
+            assert_eq!(
+                test_harness
+                    .l2_mock
+                    .get_payload_requests
+                    .lock()
+                    .unwrap()
+                    .len(),
+                0
+            );
+            assert_eq!(
+                test_harness
+                    .l2_mock
+                    .new_payload_requests
+                    .lock()
+                    .unwrap()
+                    .len(),
+                0
+            );
+        }
+
+        // test new_payload_v4 success
+        let payload = get_payload_response.unwrap();
+        let execution_requests = vec![Bytes::from_static(&[1, 2, 3])];
+        let new_payload_response = test_harness
+            .client
+            .new_payload_v4(
+                payload.execution_payload.clone(),
+                vec![],
+                B256::ZERO,
+                execution_requests.clone(),
+            )
+            .await;
+        assert!(new_payload_response.is_ok());
+        {
+            let l2_new_payload_requests =
+                test_harness.l2_mock.new_payload_v4_requests.lock().unwrap();
+            let builder_new_payload_requests = test_harness
+                .builder_mock
+                .new_payload_v4_requests
+                .lock()
+                .unwrap();
+            assert_eq!(l2_new_payload_requests.len(), 2);
+            assert_eq!(builder_new_payload_requests.len(), 0);
+            let req = l2_new_payload_requests.last().unwrap();
+            assert_eq!(req.0, payload.execution_payload);
+            assert_eq!(req.3, execution_requests);
+        }
+
+        test_harness.cleanup().await;
+    }
+
+    async fn hardfork_version_mismatch() {
+        // The mock payloads are built after Isthmus, so only the V4 methods are accepted
+        let rollup_config = RollupConfig {
+            ecotone_time: Some(0),
+            isthmus_time: Some(0),
+        };
+        let test_harness = TestHarness::new(false, None, None, Some(rollup_config)).await;
+
+        let payload = test_harness
+            .l2_mock
+            .get_payload_response
+            .clone()
+            .unwrap()
+            .execution_payload;
+
+        let new_payload_response = test_harness
+            .client
+            .new_payload_v3(payload.clone(), vec![], B256::ZERO)
+            .await;
+        assert!(matches!(
+            new_payload_response.unwrap_err(),
+            ClientError::Call(e) if e.code() == UNSUPPORTED_FORK_CODE
+        ));
+        assert_eq!(
+            test_harness
+                .l2_mock
+                .new_payload_requests
+                .lock()
+                .unwrap()
+                .len(),
+            0
+        );
+
+        let new_payload_response = test_harness
+            .client
+            .new_payload_v4(payload, vec![], B256::ZERO, vec![])
+            .await;
+        assert!(new_payload_response.is_ok());
+        assert_eq!(
+            test_harness
+                .l2_mock
+                .new_payload_v4_requests
+                .lock()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        test_harness.cleanup().await;
+    }
+
     async fn boost_sync_enabled() {
-        let test_harness = TestHarness::new(true, None, None).await;
+        let test_harness = TestHarness::new(true, None, None, None).await;
 
         let fcu = ForkchoiceState {
             head_block_hash: FixedBytes::random(),
@@ -915,7 +2349,7 @@ mod tests {
             payload.block_value = U256::from(10);
             payload
         });
-        let test_harness = TestHarness::new(true, Some(l2_mock), None).await;
+        let test_harness = TestHarness::new(true, Some(l2_mock), None, None).await;
 
         // test get_payload_v3 return l2 payload if builder payload is invalid
         let get_payload_response = test_harness
@@ -928,9 +2362,432 @@ mod tests {
         test_harness.cleanup().await;
     }
 
+    async fn multiple_builders_selection() {
+        let builder_mock_with_value = |value: u64| {
+            let mut builder_mock = MockEngineServer::new();
+            builder_mock.get_payload_response =
+                builder_mock
+                    .get_payload_response
+                    .clone()
+                    .map(|mut payload| {
+                        payload.block_value = U256::from(value);
+                        payload
+                    });
+            builder_mock
+        };
+
+        for (policy, expected_value) in [
+            (BuilderSelectionPolicy::HighestValue, 20),
+            (BuilderSelectionPolicy::Priority, 10),
+        ] {
+            let test_harness = TestHarness::with_builders(
+                false,
+                None,
+                vec![builder_mock_with_value(10), builder_mock_with_value(20)],
+                SelectionArgs {
+                    builder_selection_policy: policy,
+                    ..Default::default()
+                },
+                CircuitBreakerArgs::default(),
+                DeadlineArgs::default(),
+                None,
+            )
+            .await;
+
+            let get_payload_response = test_harness
+                .client
+                .get_payload_v3(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+                .await;
+            assert_eq!(
+                get_payload_response.unwrap().block_value,
+                U256::from(expected_value)
+            );
+            // both builder payloads are validated against the local execution engine
+            assert_eq!(
+                test_harness
+                    .l2_mock
+                    .new_payload_requests
+                    .lock()
+                    .unwrap()
+                    .len(),
+                2
+            );
+
+            test_harness.cleanup().await;
+        }
+    }
+
+    async fn block_selection_by_value() {
+        let mut l2_mock = MockEngineServer::new();
+        l2_mock.get_payload_response = l2_mock.get_payload_response.clone().map(|mut payload| {
+            payload.block_value = U256::from(100);
+            payload
+        });
+
+        for (builder_value, expected_value) in [(105, 100), (110, 110)] {
+            let mut builder_mock = MockEngineServer::new();
+            builder_mock.get_payload_response =
+                builder_mock
+                    .get_payload_response
+                    .clone()
+                    .map(|mut payload| {
+                        payload.block_value = U256::from(builder_value);
+                        payload
+                    });
+            let test_harness = TestHarness::with_builders(
+                false,
+                Some(l2_mock.clone()),
+                vec![builder_mock],
+                SelectionArgs {
+                    block_selection_policy: BlockSelectionPolicy::BlockValue,
+                    block_selection_margin: 10,
+                    ..Default::default()
+                },
+                CircuitBreakerArgs::default(),
+                DeadlineArgs::default(),
+                None,
+            )
+            .await;
+
+            // the builder payload is only used if it pays at least 10% more than the local one
+            let get_payload_response = test_harness
+                .client
+                .get_payload_v3(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+                .await;
+            assert_eq!(
+                get_payload_response.unwrap().block_value,
+                U256::from(expected_value)
+            );
+
+            test_harness.cleanup().await;
+        }
+    }
+
+    async fn circuit_breaker_trips() {
+        let mut l2_mock = MockEngineServer::new();
+        l2_mock.new_payload_response = l2_mock.new_payload_response.clone().map(|mut status| {
+            status.status = PayloadStatusEnum::Invalid {
+                validation_error: "test".to_string(),
+            };
+            status
+        });
+        let test_harness = TestHarness::with_builders(
+            false,
+            Some(l2_mock),
+            vec![MockEngineServer::new()],
+            SelectionArgs::default(),
+            CircuitBreakerArgs {
+                circuit_breaker_threshold: 1,
+                ..Default::default()
+            },
+            DeadlineArgs::default(),
+            None,
+        )
+        .await;
+
+        // the invalid builder payload trips the circuit breaker to dry run
+        for _ in 0..2 {
+            let get_payload_response = test_harness
+                .client
+                .get_payload_v3(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+                .await;
+            assert!(get_payload_response.is_ok());
+        }
+        assert_eq!(
+            test_harness
+                .builder_mock
+                .get_payload_requests
+                .lock()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            test_harness
+                .l2_mock
+                .get_payload_requests
+                .lock()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        test_harness.cleanup().await;
+    }
+
+    async fn miner_limit_violation_trips_circuit_breaker() {
+        let test_harness = TestHarness::with_builders(
+            false,
+            None,
+            vec![MockEngineServer::new()],
+            SelectionArgs::default(),
+            CircuitBreakerArgs {
+                circuit_breaker_threshold: 1,
+                ..Default::default()
+            },
+            DeadlineArgs::default(),
+            None,
+        )
+        .await;
+        test_harness.miner_settings.lock().await.limits.gas_limit = Some(1_000_000);
+
+        // a builder block above the miner gas limit is the builder's fault
+        for _ in 0..2 {
+            let get_payload_response = test_harness
+                .client
+                .get_payload_v3(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+                .await;
+            assert!(get_payload_response.is_ok());
+        }
+        assert_eq!(
+            test_harness
+                .builder_mock
+                .get_payload_requests
+                .lock()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        test_harness.cleanup().await;
+    }
+
+    async fn validation_error_does_not_trip_circuit_breaker() {
+        let mut l2_mock = MockEngineServer::new();
+        l2_mock.new_payload_response = Err(ErrorCode::InternalError.into());
+        let test_harness = TestHarness::with_builders(
+            false,
+            Some(l2_mock),
+            vec![MockEngineServer::new()],
+            SelectionArgs::default(),
+            CircuitBreakerArgs {
+                circuit_breaker_threshold: 1,
+                ..Default::default()
+            },
+            DeadlineArgs::default(),
+            None,
+        )
+        .await;
+
+        // the local execution engine failing to validate the payload is not the builder's fault
+        for _ in 0..2 {
+            let get_payload_response = test_harness
+                .client
+                .get_payload_v3(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+                .await;
+            assert!(get_payload_response.is_ok());
+        }
+        assert_eq!(
+            test_harness
+                .builder_mock
+                .get_payload_requests
+                .lock()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        test_harness.cleanup().await;
+    }
+
+    async fn builder_deadline_exceeded() {
+        let mut builder_mock = MockEngineServer::new();
+        builder_mock.get_payload_response =
+            builder_mock
+                .get_payload_response
+                .clone()
+                .map(|mut payload| {
+                    payload.block_value = U256::from(10);
+                    payload
+                });
+        let mut slow_builder_mock = MockEngineServer::new();
+        slow_builder_mock.get_payload_response = slow_builder_mock
+            .get_payload_response
+            .clone()
+            .map(|mut payload| {
+                payload.block_value = U256::from(20);
+                payload
+            });
+        slow_builder_mock.get_payload_delay = Some(std::time::Duration::from_millis(500));
+        let test_harness = TestHarness::with_builders(
+            false,
+            None,
+            vec![builder_mock, slow_builder_mock.clone()],
+            SelectionArgs {
+                builder_selection_policy: BuilderSelectionPolicy::HighestValue,
+                ..Default::default()
+            },
+            CircuitBreakerArgs {
+                circuit_breaker_threshold: 1,
+                ..Default::default()
+            },
+            DeadlineArgs {
+                get_payload_deadline: Some(100),
+                ..Default::default()
+            },
+            None,
+        )
+        .await;
+
+        // the payload collected within the deadline is selected without waiting for the slow
+        // builder
+        let start = Instant::now();
+        let get_payload_response = test_harness
+            .client
+            .get_payload_v3(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+            .await;
+        assert_eq!(get_payload_response.unwrap().block_value, U256::from(10));
+        assert!(start.elapsed() < std::time::Duration::from_millis(500));
+
+        // abandoning the slow builder does not open its circuit breaker, it is queried again
+        let get_payload_response = test_harness
+            .client
+            .get_payload_v3(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+            .await;
+        assert_eq!(get_payload_response.unwrap().block_value, U256::from(10));
+        assert_eq!(
+            slow_builder_mock.get_payload_requests.lock().unwrap().len(),
+            2
+        );
+
+        test_harness.cleanup().await;
+    }
+
+    async fn builder_deadline_without_local_payload() {
+        let mut l2_mock = MockEngineServer::new();
+        l2_mock.get_payload_response = Err(ErrorCode::InternalError.into());
+        let mut slow_builder_mock = MockEngineServer::new();
+        slow_builder_mock.get_payload_response = slow_builder_mock
+            .get_payload_response
+            .clone()
+            .map(|mut payload| {
+                payload.block_value = U256::from(10);
+                payload
+            });
+        slow_builder_mock.get_payload_delay = Some(std::time::Duration::from_millis(300));
+        let test_harness = TestHarness::with_builders(
+            false,
+            Some(l2_mock),
+            vec![slow_builder_mock.clone()],
+            SelectionArgs::default(),
+            CircuitBreakerArgs::default(),
+            DeadlineArgs {
+                get_payload_deadline: Some(100),
+                ..Default::default()
+            },
+            None,
+        )
+        .await;
+
+        // without a local payload, the builder is awaited past the deadline
+        let get_payload_response = test_harness
+            .client
+            .get_payload_v3(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+            .await;
+        assert_eq!(get_payload_response.unwrap().block_value, U256::from(10));
+
+        test_harness.cleanup().await;
+    }
+
+    async fn builder_syncing() {
+        let mut builder_mock = MockEngineServer::new();
+        builder_mock.fcu_response = Ok(ForkchoiceUpdated::new(PayloadStatus::from_status(
+            PayloadStatusEnum::Syncing,
+        )));
+        let test_harness = TestHarness::new(true, None, Some(builder_mock), None).await;
+
+        let fcu = ForkchoiceState {
+            head_block_hash: FixedBytes::random(),
+            safe_block_hash: FixedBytes::random(),
+            finalized_block_hash: FixedBytes::random(),
+        };
+        let fcu_response = test_harness.client.fork_choice_updated_v3(fcu, None).await;
+        assert!(fcu_response.is_ok());
+
+        sleep(std::time::Duration::from_millis(100)).await;
+
+        // the syncing builder is not asked for its payload
+        let get_payload_response = test_harness
+            .client
+            .get_payload_v3(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+            .await;
+        assert!(get_payload_response.is_ok());
+        assert_eq!(
+            test_harness
+                .builder_mock
+                .get_payload_requests
+                .lock()
+                .unwrap()
+                .len(),
+            0
+        );
+        assert_eq!(
+            test_harness
+                .l2_mock
+                .get_payload_requests
+                .lock()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        test_harness.cleanup().await;
+    }
+
+    async fn compare_mode() {
+        let mut l2_mock = MockEngineServer::new();
+        l2_mock.get_payload_response = l2_mock.get_payload_response.clone().map(|mut payload| {
+            payload.block_value = U256::from(100);
+            payload
+        });
+        let mut builder_mock = MockEngineServer::new();
+        builder_mock.get_payload_response =
+            builder_mock
+                .get_payload_response
+                .clone()
+                .map(|mut payload| {
+                    payload.block_value = U256::from(110);
+                    payload
+                });
+        let test_harness = TestHarness::new(false, Some(l2_mock), Some(builder_mock), None).await;
+        *test_harness.execution_mode.lock().await = ExecutionMode::Compare;
+
+        // the builder payload is fetched and validated, but the local payload is returned
+        let get_payload_response = test_harness
+            .client
+            .get_payload_v3(PayloadId::new([0, 0, 0, 0, 0, 0, 0, 1]))
+            .await;
+        assert_eq!(get_payload_response.unwrap().block_value, U256::from(100));
+        assert_eq!(
+            test_harness
+                .builder_mock
+                .get_payload_requests
+                .lock()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let diffs = test_harness.compare_history.latest(None).await;
+        assert_eq!(diffs.len(), 1);
+        assert!(diffs[0].builder_valid);
+        assert_eq!(
+            diffs[0].block_value_delta,
+            Some(alloy_primitives::I256::try_from(10i64).unwrap())
+        );
+        assert!(diffs[0].builder_latency_ms.is_some());
+
+        test_harness.cleanup().await;
+    }
+
     async fn spawn_server(mock_engine_server: MockEngineServer, addr: &str) -> ServerHandle {
         let server = ServerBuilder::default().build(addr).await.unwrap();
         let mut module: RpcModule<()> = RpcModule::new(());
+        let fcu_v2_response = mock_engine_server.fcu_response.clone();
+        let get_payload_v2_response = mock_engine_server.get_payload_response.clone();
+        let new_payload_v2_response = mock_engine_server.new_payload_response.clone();
+        let get_payload_v4_response = mock_engine_server.get_payload_response.clone();
+        let new_payload_v4_response = mock_engine_server.new_payload_response.clone();
 
         module
             .register_method("engine_forkchoiceUpdatedV3", move |params, _, _| {
@@ -950,13 +2807,19 @@ mod tests {
             .unwrap();
 
         module
-            .register_method("engine_getPayloadV3", move |params, _, _| {
-                let params: (PayloadId,) = params.parse()?;
-                let mut get_payload_requests =
-                    mock_engine_server.get_payload_requests.lock().unwrap();
-                get_payload_requests.push(params.0);
+            .register_async_method("engine_getPayloadV3", move |params, _, _| {
+                let get_payload_requests = mock_engine_server.get_payload_requests.clone();
+                let get_payload_response = mock_engine_server.get_payload_response.clone();
+                let get_payload_delay = mock_engine_server.get_payload_delay;
+                async move {
+                    let params: (PayloadId,) = params.parse()?;
+                    get_payload_requests.lock().unwrap().push(params.0);
+                    if let Some(delay) = get_payload_delay {
+                        sleep(delay).await;
+                    }
 
-                mock_engine_server.get_payload_response.clone()
+                    get_payload_response
+                }
             })
             .unwrap();
 
@@ -971,6 +2834,76 @@ mod tests {
             })
             .unwrap();
 
+        module
+            .register_method("engine_forkchoiceUpdatedV2", move |params, _, _| {
+                let params: (ForkchoiceState, Option<OpPayloadAttributes>) = params.parse()?;
+                let mut fcu_requests = mock_engine_server.fcu_v2_requests.lock().unwrap();
+                fcu_requests.push(params);
+
+                fcu_v2_response.clone()
+            })
+            .unwrap();
+
+        module
+            .register_method("engine_getPayloadV2", move |params, _, _| {
+                let params: (PayloadId,) = params.parse()?;
+                let mut get_payload_requests =
+                    mock_engine_server.get_payload_v2_requests.lock().unwrap();
+                get_payload_requests.push(params.0);
+
+                get_payload_v2_response
+                    .clone()
+                    .map(|payload| OpExecutionPayloadEnvelopeV2 {
+                        execution_payload: ExecutionPayloadFieldV2::V2(
+                            payload.execution_payload.payload_inner,
+                        ),
+                        block_value: payload.block_value,
+                    })
+            })
+            .unwrap();
+
+        module
+            .register_method("engine_newPayloadV2", move |params, _, _| {
+                let params: (ExecutionPayloadInputV2,) = params.parse()?;
+                let mut new_payload_requests =
+                    mock_engine_server.new_payload_v2_requests.lock().unwrap();
+                new_payload_requests.push(params.0);
+
+                new_payload_v2_response.clone()
+            })
+            .unwrap();
+
+        module
+            .register_method("engine_getPayloadV4", move |params, _, _| {
+                let params: (PayloadId,) = params.parse()?;
+                let mut get_payload_requests =
+                    mock_engine_server.get_payload_v4_requests.lock().unwrap();
+                get_payload_requests.push(params.0);
+
+                get_payload_v4_response
+                    .clone()
+                    .map(|payload| OpExecutionPayloadEnvelopeV4 {
+                        execution_payload: payload.execution_payload,
+                        block_value: payload.block_value,
+                        blobs_bundle: payload.blobs_bundle,
+                        should_override_builder: payload.should_override_builder,
+                        parent_beacon_block_root: payload.parent_beacon_block_root,
+                        execution_requests: vec![],
+                    })
+            })
+            .unwrap();
+
+        module
+            .register_method("engine_newPayloadV4", move |params, _, _| {
+                let params: (ExecutionPayloadV3, Vec<B256>, B256, Vec<Bytes>) = params.parse()?;
+                let mut new_payload_requests =
+                    mock_engine_server.new_payload_v4_requests.lock().unwrap();
+                new_payload_requests.push(params);
+
+                new_payload_v4_response.clone()
+            })
+            .unwrap();
+
         server.start(module)
     }
 
@@ -986,8 +2919,13 @@ mod tests {
         let mut builder_mock = MockEngineServer::new();
         builder_mock.override_payload_id = Some(same_id);
 
-        let test_harness =
-            TestHarness::new(true, Some(l2_mock.clone()), Some(builder_mock.clone())).await;
+        let test_harness = TestHarness::new(
+            true,
+            Some(l2_mock.clone()),
+            Some(builder_mock.clone()),
+            None,
+        )
+        .await;
 
         // Test FCU call
         let fcu = ForkchoiceState {
@@ -1036,8 +2974,13 @@ mod tests {
         let mut builder_mock = MockEngineServer::new();
         builder_mock.override_payload_id = Some(external_id);
 
-        let test_harness =
-            TestHarness::new(true, Some(l2_mock.clone()), Some(builder_mock.clone())).await;
+        let test_harness = TestHarness::new(
+            true,
+            Some(l2_mock.clone()),
+            Some(builder_mock.clone()),
+            None,
+        )
+        .await;
 
         // Test FCU call
         let fcu = ForkchoiceState {
@@ -0,0 +1,144 @@
+use crate::circuit_breaker::CircuitState;
+use crate::compare::PayloadDiff;
+use crate::miner_settings::MinerSettings;
+use crate::schedule::ScheduleState;
+use crate::server::ExecutionMode;
+use clap::{arg, Parser};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+
+/// Which of the state file and the command line sets the execution mode and the canary
+/// percentage at startup.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum StatePrecedence {
+    // The values persisted in the state file
+    Persisted,
+    // The `--execution-mode` and `--canary-percentage` arguments
+    CommandLine,
+}
+
+/// Settings of the state file.
+#[derive(Parser, Debug, Clone, PartialEq, Eq)]
+pub struct StateArgs {
+    /// Path to a file persisting the runtime state across restarts, such as the execution mode
+    /// set through the debug API
+    #[arg(long, env, value_name = "PATH")]
+    pub state_file: Option<PathBuf>,
+
+    /// Whether the persisted execution mode and canary percentage or the `--execution-mode` and
+    /// `--canary-percentage` arguments are used at startup
+    #[arg(long, env, default_value = "persisted")]
+    pub state_precedence: StatePrecedence,
+}
+
+impl Default for StateArgs {
+    fn default() -> Self {
+        Self {
+            state_file: None,
+            state_precedence: StatePrecedence::Persisted,
+        }
+    }
+}
+
+/// Wakes the state file writer up when the persisted state changes. The changes signalled while
+/// the state file is being written are written together once it is done.
+#[derive(Debug, Clone, Default)]
+pub struct StateNotifier(Arc<Notify>);
+
+impl StateNotifier {
+    pub fn notify(&self) {
+        self.0.notify_one();
+    }
+
+    pub async fn changed(&self) {
+        self.0.notified().await;
+    }
+}
+
+/// Runtime state persisted in the state file.
+///
+/// The payload id mappings are not persisted, as they only live for the block being built.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PersistedState {
+    pub execution_mode: ExecutionMode,
+    #[serde(default)]
+    pub canary_percentage: Option<u8>,
+    #[serde(default)]
+    pub execution_mode_schedule: ScheduleState,
+    /// Circuit breaker state of each builder, keyed by the builder url
+    #[serde(default)]
+    pub circuit_breakers: BTreeMap<String, CircuitState>,
+    #[serde(default)]
+    pub miner_settings: MinerSettings,
+    /// Most recent payload comparisons of the compare execution mode, the most recent first
+    #[serde(default)]
+    pub payload_diffs: Vec<PayloadDiff>,
+}
+
+impl PersistedState {
+    /// Reads the state file, `None` if it does not exist yet.
+    pub fn load(path: &Path) -> eyre::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    /// Writes the state file atomically, through a temporary file renamed over it.
+    pub async fn write(&self, path: &Path) -> eyre::Result<()> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let mut file = tokio::fs::File::create(&tmp_path).await?;
+        file.write_all(&serde_json::to_vec_pretty(self)?).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_path, path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_and_load() -> eyre::Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("rollup-boost-state-{}.json", std::process::id()));
+        assert_eq!(PersistedState::load(&path)?, None);
+
+        let mut state = PersistedState {
+            execution_mode: ExecutionMode::Disabled,
+            canary_percentage: Some(10),
+            execution_mode_schedule: ScheduleState::default(),
+            circuit_breakers: BTreeMap::from([(
+                "http://builder:8551/".to_string(),
+                CircuitState::Open,
+            )]),
+            miner_settings: MinerSettings::default(),
+            payload_diffs: vec![],
+        };
+        state.write(&path).await?;
+        assert_eq!(PersistedState::load(&path)?, Some(state.clone()));
+
+        state.execution_mode = ExecutionMode::Enabled;
+        state.write(&path).await?;
+        assert_eq!(PersistedState::load(&path)?, Some(state));
+
+        // older state files without the optional fields are accepted
+        std::fs::write(&path, r#"{"execution_mode":"dry_run"}"#)?;
+        let state = PersistedState::load(&path)?.unwrap();
+        assert_eq!(state.execution_mode, ExecutionMode::DryRun);
+        assert_eq!(state.canary_percentage, None);
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
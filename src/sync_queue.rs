@@ -0,0 +1,362 @@
+use crate::client::ExecutionClient;
+use crate::metrics::ServerMetrics;
+use crate::payload::{NewPayload, PayloadVersion};
+use crate::sync_status::SyncTracker;
+use alloy_rpc_types_engine::{ForkchoiceState, ForkchoiceUpdated, PayloadStatus};
+use clap::{arg, Parser};
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::types::{ErrorCode, ErrorObjectOwned};
+use op_alloy_rpc_types_engine::OpPayloadAttributes;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, warn};
+
+/// Settings of the per-builder delivery queue of the boost sync calls.
+#[derive(Parser, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncQueueArgs {
+    /// Maximum number of calls waiting to be delivered to a builder, further calls are dropped
+    #[arg(long, env, default_value_t = 64)]
+    pub sync_queue_capacity: usize,
+
+    /// Number of retries of a call failing with a transient error. The block building FCUs are
+    /// never retried
+    #[arg(long, env, default_value_t = 3)]
+    pub sync_queue_max_retries: u32,
+
+    /// Backoff in milliseconds before the first retry, doubled on every retry
+    #[arg(long, env, default_value_t = 100)]
+    pub sync_queue_retry_backoff: u64,
+}
+
+impl Default for SyncQueueArgs {
+    fn default() -> Self {
+        Self {
+            sync_queue_capacity: 64,
+            sync_queue_max_retries: 3,
+            sync_queue_retry_backoff: 100,
+        }
+    }
+}
+
+enum SyncCall {
+    ForkChoiceUpdated {
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<OpPayloadAttributes>,
+        version: PayloadVersion,
+        response: oneshot::Sender<RpcResult<ForkchoiceUpdated>>,
+    },
+    NewPayload {
+        new_payload: NewPayload,
+        response: oneshot::Sender<RpcResult<PayloadStatus>>,
+    },
+}
+
+struct QueuedCall {
+    call: SyncCall,
+    enqueued_at: Instant,
+}
+
+/// Ordered delivery of the engine API calls to a builder.
+///
+/// The calls are sent one at a time in the order they were queued, so the builder sees the same
+/// sequence of `engine_newPayload` and `engine_forkchoiceUpdated` calls as the L2 execution
+/// engine. The response of every call is returned through the receiver given when queueing it.
+/// A call dropped by a full queue leaves a gap in the sequence, so the builder is marked as
+/// syncing until it catches up.
+pub struct SyncQueue {
+    builder: String,
+    sender: mpsc::Sender<QueuedCall>,
+    sync_tracker: Arc<SyncTracker>,
+    metrics: Option<Arc<ServerMetrics>>,
+}
+
+impl SyncQueue {
+    /// Creates the queue and spawns the task delivering its calls to the builder.
+    pub fn new(
+        client: ExecutionClient,
+        args: SyncQueueArgs,
+        sync_tracker: Arc<SyncTracker>,
+        metrics: Option<Arc<ServerMetrics>>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(args.sync_queue_capacity.max(1));
+        let builder = client.auth_rpc.to_string();
+        tokio::spawn(run(client, args, receiver, metrics.clone()));
+        Self {
+            builder,
+            sender,
+            sync_tracker,
+            metrics,
+        }
+    }
+
+    pub fn fork_choice_updated(
+        &self,
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<OpPayloadAttributes>,
+        version: PayloadVersion,
+    ) -> Option<oneshot::Receiver<RpcResult<ForkchoiceUpdated>>> {
+        let (response, receiver) = oneshot::channel();
+        self.enqueue(SyncCall::ForkChoiceUpdated {
+            fork_choice_state,
+            payload_attributes,
+            version,
+            response,
+        })
+        .then_some(receiver)
+    }
+
+    pub fn new_payload(
+        &self,
+        new_payload: NewPayload,
+    ) -> Option<oneshot::Receiver<RpcResult<PayloadStatus>>> {
+        let (response, receiver) = oneshot::channel();
+        self.enqueue(SyncCall::NewPayload {
+            new_payload,
+            response,
+        })
+        .then_some(receiver)
+    }
+
+    /// Queues a call without waiting, returns `false` and marks the builder as syncing if the
+    /// queue is full.
+    fn enqueue(&self, call: SyncCall) -> bool {
+        let queued = QueuedCall {
+            call,
+            enqueued_at: Instant::now(),
+        };
+        if let Err(e) = self.sender.try_send(queued) {
+            error!(message = "builder sync queue is full, dropping call", "url" = %self.builder, "error" = %e);
+            if let Some(metrics) = &self.metrics {
+                metrics.increment_builder_sync_queue_dropped(self.builder.clone());
+            }
+            self.sync_tracker.mark_syncing();
+            return false;
+        }
+        self.record_depth();
+        true
+    }
+
+    fn record_depth(&self) {
+        if let Some(metrics) = &self.metrics {
+            let depth = self.sender.max_capacity() - self.sender.capacity();
+            metrics.record_builder_sync_queue_depth(self.builder.clone(), depth as f64);
+        }
+    }
+}
+
+async fn run(
+    client: ExecutionClient,
+    args: SyncQueueArgs,
+    mut receiver: mpsc::Receiver<QueuedCall>,
+    metrics: Option<Arc<ServerMetrics>>,
+) {
+    let builder = client.auth_rpc.to_string();
+    while let Some(queued) = receiver.recv().await {
+        if let Some(metrics) = &metrics {
+            metrics.record_builder_sync_queue_depth(builder.clone(), receiver.len() as f64);
+        }
+
+        match queued.call {
+            SyncCall::ForkChoiceUpdated {
+                fork_choice_state,
+                payload_attributes,
+                version,
+                response,
+            } => {
+                // A block building call is only useful within its slot, so it is sent once
+                // instead of waiting for the retries
+                let result = if payload_attributes.is_some() {
+                    client
+                        .fork_choice_updated(fork_choice_state, payload_attributes, version)
+                        .await
+                } else {
+                    deliver(&args, &builder, metrics.as_deref(), || {
+                        client.fork_choice_updated(fork_choice_state, None, version)
+                    })
+                    .await
+                };
+                let _ = response.send(result);
+            }
+            SyncCall::NewPayload {
+                new_payload,
+                response,
+            } => {
+                let result = deliver(&args, &builder, metrics.as_deref(), || {
+                    client.new_payload(new_payload.clone())
+                })
+                .await;
+                let _ = response.send(result);
+            }
+        }
+
+        if let Some(metrics) = &metrics {
+            metrics.record_builder_sync_queue_lag(builder.clone(), queued.enqueued_at.elapsed());
+        }
+    }
+}
+
+/// Transport failures and timeouts are reported by the execution client as internal errors.
+fn is_transient(error: &ErrorObjectOwned) -> bool {
+    error.code() == ErrorCode::InternalError.code()
+}
+
+/// Sends a call, retrying with an exponential backoff while it fails with a transient error.
+async fn deliver<T, F, Fut>(
+    args: &SyncQueueArgs,
+    builder: &str,
+    metrics: Option<&ServerMetrics>,
+    mut call: F,
+) -> RpcResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = RpcResult<T>>,
+{
+    let mut backoff = Duration::from_millis(args.sync_queue_retry_backoff);
+    let mut retries = 0;
+    loop {
+        match call().await {
+            Err(e) if is_transient(&e) && retries < args.sync_queue_max_retries => {
+                retries += 1;
+                warn!(message = "retrying builder sync call", "url" = %builder, "error" = %e, "retry" = retries, "backoff_ms" = backoff.as_millis() as u64);
+                if let Some(metrics) = metrics {
+                    metrics.increment_builder_sync_queue_retries(builder.to_string());
+                }
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::PayloadSource;
+    use alloy_primitives::B256;
+    use alloy_rpc_types_engine::{JwtSecret, PayloadAttributes};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn args(max_retries: u32) -> SyncQueueArgs {
+        SyncQueueArgs {
+            sync_queue_max_retries: max_retries,
+            sync_queue_retry_backoff: 1,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_errors() {
+        let calls = &AtomicU32::new(0);
+        let result = deliver(&args(3), "builder", None, || async move {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(ErrorCode::InternalError.into())
+            } else {
+                Ok(())
+            }
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // gives up after the maximum number of retries
+        let calls = &AtomicU32::new(0);
+        let result: RpcResult<()> = deliver(&args(2), "builder", None, || async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ErrorCode::InternalError.into())
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_on_call_errors() {
+        let calls = &AtomicU32::new(0);
+        let result: RpcResult<()> = deliver(&args(3), "builder", None, || async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ErrorCode::InvalidParams.into())
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_marks_builder_syncing() -> eyre::Result<()> {
+        let client = ExecutionClient::new(
+            "http://127.0.0.1:1".parse()?,
+            JwtSecret::random(),
+            1000,
+            None,
+            PayloadSource::Builder,
+        )?;
+        let sync_tracker = Arc::new(SyncTracker::new("builder".to_string(), None));
+        let args = SyncQueueArgs {
+            sync_queue_capacity: 1,
+            ..Default::default()
+        };
+        let queue = SyncQueue::new(client, args, sync_tracker.clone(), None);
+
+        // the delivery task does not run before the test yields, the second call is dropped
+        let fork_choice_state = ForkchoiceState::default();
+        assert!(queue
+            .fork_choice_updated(fork_choice_state, None, PayloadVersion::V3)
+            .is_some());
+        assert!(!sync_tracker.is_syncing());
+        assert!(queue
+            .fork_choice_updated(fork_choice_state, None, PayloadVersion::V3)
+            .is_none());
+        assert!(sync_tracker.is_syncing());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_block_building_fcu_not_retried() -> eyre::Result<()> {
+        // the builder is unreachable, so every call fails with a transient error
+        let client = ExecutionClient::new(
+            "http://127.0.0.1:1".parse()?,
+            JwtSecret::random(),
+            1000,
+            None,
+            PayloadSource::Builder,
+        )?;
+        let sync_tracker = Arc::new(SyncTracker::new("builder".to_string(), None));
+        let args = SyncQueueArgs {
+            sync_queue_max_retries: 3,
+            sync_queue_retry_backoff: 1000,
+            ..Default::default()
+        };
+        let queue = SyncQueue::new(client, args, sync_tracker, None);
+        let attributes = OpPayloadAttributes {
+            payload_attributes: PayloadAttributes {
+                timestamp: 1000,
+                prev_randao: B256::ZERO,
+                suggested_fee_recipient: Default::default(),
+                withdrawals: Some(vec![]),
+                parent_beacon_block_root: Some(B256::ZERO),
+                target_blobs_per_block: None,
+                max_blobs_per_block: None,
+            },
+            transactions: None,
+            no_tx_pool: None,
+            gas_limit: Some(30_000_000),
+            eip_1559_params: None,
+        };
+
+        // the failure is returned right away instead of after the backoff, within the slot
+        let start = Instant::now();
+        let response = queue
+            .fork_choice_updated(
+                ForkchoiceState::default(),
+                Some(attributes),
+                PayloadVersion::V3,
+            )
+            .unwrap();
+        assert!(response.await?.is_err());
+        assert!(start.elapsed() < Duration::from_millis(1000));
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use crate::metrics::ServerMetrics;
+use alloy_rpc_types_engine::PayloadStatusEnum;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncState {
+    // The builder reported VALID on the canonical head
+    Synced,
+    // The builder reported SYNCING, its payloads are not requested
+    Syncing,
+}
+
+impl std::fmt::Display for SyncState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncState::Synced => write!(f, "synced"),
+            SyncState::Syncing => write!(f, "syncing"),
+        }
+    }
+}
+
+/// Sync status of a builder as reported by the debug API.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BuilderSyncStatus {
+    pub builder: String,
+    pub state: SyncState,
+}
+
+/// Tracks whether a builder is syncing from the statuses it returns to the engine API calls.
+///
+/// A `SYNCING` status on any call marks the builder as syncing, only a `VALID` status on an
+/// `engine_forkchoiceUpdated` to the canonical head marks it as synced again.
+#[derive(Debug)]
+pub struct SyncTracker {
+    builder: String,
+    syncing: AtomicBool,
+    metrics: Option<Arc<ServerMetrics>>,
+}
+
+impl SyncTracker {
+    pub fn new(builder: String, metrics: Option<Arc<ServerMetrics>>) -> Self {
+        Self {
+            builder,
+            syncing: AtomicBool::new(false),
+            metrics,
+        }
+    }
+
+    pub fn is_syncing(&self) -> bool {
+        self.syncing.load(Ordering::SeqCst)
+    }
+
+    /// Records the status returned by the builder, `on_head` tells whether the call set the
+    /// canonical head.
+    pub fn record(&self, status: &PayloadStatusEnum, on_head: bool) {
+        if status.is_syncing() {
+            if !self.syncing.swap(true, Ordering::SeqCst) {
+                warn!(message = "builder is syncing, skipping its payloads", "builder" = %self.builder);
+            }
+        } else if status.is_valid() && on_head {
+            if self.syncing.swap(false, Ordering::SeqCst) {
+                info!(message = "builder is synced", "builder" = %self.builder);
+            }
+        } else {
+            return;
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_builder_syncing(self.builder.clone(), self.is_syncing());
+        }
+    }
+
+    /// Marks the builder as syncing after it missed a call, until it is `VALID` on the canonical
+    /// head again.
+    pub fn mark_syncing(&self) {
+        self.record(&PayloadStatusEnum::Syncing, false);
+    }
+
+    pub fn status(&self) -> BuilderSyncStatus {
+        BuilderSyncStatus {
+            builder: self.builder.clone(),
+            state: if self.is_syncing() {
+                SyncState::Syncing
+            } else {
+                SyncState::Synced
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_tracker() {
+        let tracker = SyncTracker::new("builder".to_string(), None);
+        assert!(!tracker.is_syncing());
+
+        tracker.record(&PayloadStatusEnum::Syncing, false);
+        assert_eq!(tracker.status().state, SyncState::Syncing);
+
+        // a valid block is not enough, the builder has to be valid on the head
+        tracker.record(&PayloadStatusEnum::Valid, false);
+        assert!(tracker.is_syncing());
+        tracker.record(&PayloadStatusEnum::Accepted, true);
+        assert!(tracker.is_syncing());
+
+        tracker.record(&PayloadStatusEnum::Valid, true);
+        assert_eq!(tracker.status().state, SyncState::Synced);
+    }
+}
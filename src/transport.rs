@@ -0,0 +1,394 @@
+use crate::auth_layer::{secret_to_bearer_header, AuthClientLayer, AuthClientService};
+use alloy_rpc_types_engine::JwtSecret;
+use futures::future::try_join_all;
+use http::header::AUTHORIZATION;
+use http::{HeaderMap, Uri};
+use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use jsonrpsee::core::client::{BatchResponse, Client as PersistentClient, ClientT};
+use jsonrpsee::core::params::BatchRequestBuilder;
+use jsonrpsee::core::traits::ToRpcParams;
+use jsonrpsee::core::{async_trait, http_helpers, BoxError, ClientError};
+use jsonrpsee::http_client::transport::HttpBackend;
+use jsonrpsee::http_client::{HttpBody, HttpClient, HttpClientBuilder, HttpRequest};
+use jsonrpsee::types::{ErrorCode, ErrorObject};
+use jsonrpsee::ws_client::WsClientBuilder;
+#[cfg(feature = "ipc")]
+use reth_ipc::client::IpcClientBuilder;
+use serde::de::DeserializeOwned;
+use serde_json::value::RawValue;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Timeout of the requests forwarded over WebSocket or IPC by the proxy layer.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Whether the url is the path of an IPC socket rather than a url.
+pub fn is_ipc_path(uri: &Uri) -> bool {
+    uri.scheme().is_none() && uri.authority().is_none() && uri.path().starts_with('/')
+}
+
+/// JSON-RPC client of an execution engine, over HTTP, WebSocket or IPC depending on the url.
+pub enum RpcClient {
+    Http(HttpClient<AuthClientService<HttpBackend>>),
+    Connection(Connection),
+}
+
+impl RpcClient {
+    pub fn new(url: &Uri, secret: JwtSecret, timeout: Duration) -> Result<Self, ClientError> {
+        if let Some(connection) = Connection::new(url, secret, timeout) {
+            return Ok(RpcClient::Connection(connection));
+        }
+
+        let client = HttpClientBuilder::new()
+            .set_http_middleware(tower::ServiceBuilder::new().layer(AuthClientLayer::new(secret)))
+            .request_timeout(timeout)
+            .build(url.to_string())?;
+        Ok(RpcClient::Http(client))
+    }
+}
+
+#[async_trait]
+impl ClientT for RpcClient {
+    async fn notification<Params>(&self, method: &str, params: Params) -> Result<(), ClientError>
+    where
+        Params: ToRpcParams + Send,
+    {
+        match self {
+            RpcClient::Http(client) => client.notification(method, params).await,
+            RpcClient::Connection(connection) => {
+                connection
+                    .client()
+                    .await?
+                    .notification(method, params)
+                    .await
+            }
+        }
+    }
+
+    async fn request<R, Params>(&self, method: &str, params: Params) -> Result<R, ClientError>
+    where
+        R: DeserializeOwned,
+        Params: ToRpcParams + Send,
+    {
+        match self {
+            RpcClient::Http(client) => client.request(method, params).await,
+            RpcClient::Connection(connection) => {
+                connection.client().await?.request(method, params).await
+            }
+        }
+    }
+
+    async fn batch_request<'a, R>(
+        &self,
+        batch: BatchRequestBuilder<'a>,
+    ) -> Result<BatchResponse<'a, R>, ClientError>
+    where
+        R: DeserializeOwned + std::fmt::Debug + 'a,
+    {
+        match self {
+            RpcClient::Http(client) => client.batch_request(batch).await,
+            RpcClient::Connection(connection) => {
+                connection.client().await?.batch_request(batch).await
+            }
+        }
+    }
+}
+
+/// Transport of a persistent connection.
+enum ConnectionTransport {
+    /// The JWT is sent when connecting
+    Ws(JwtSecret),
+    /// The connections to the socket are not authenticated
+    #[cfg(feature = "ipc")]
+    Ipc,
+}
+
+/// Persistent connection to an execution engine over WebSocket or IPC. The connection is opened
+/// by the first request and reopened once it is closed, with a new JWT over WebSocket.
+pub struct Connection {
+    url: Uri,
+    transport: ConnectionTransport,
+    timeout: Duration,
+    client: Mutex<Option<Arc<PersistentClient>>>,
+}
+
+impl std::fmt::Debug for Connection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connection")
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Connection {
+    /// Connection to a `ws://` or `wss://` url or to an IPC socket path, `None` for the urls
+    /// served over HTTP. The IPC socket paths need the `ipc` feature.
+    pub fn new(url: &Uri, secret: JwtSecret, timeout: Duration) -> Option<Self> {
+        let transport = match url.scheme_str() {
+            Some("ws") | Some("wss") => ConnectionTransport::Ws(secret),
+            #[cfg(feature = "ipc")]
+            _ if is_ipc_path(url) => ConnectionTransport::Ipc,
+            _ => return None,
+        };
+        Some(Self {
+            url: url.clone(),
+            transport,
+            timeout,
+            client: Mutex::new(None),
+        })
+    }
+
+    async fn client(&self) -> Result<Arc<PersistentClient>, ClientError> {
+        let mut client = self.client.lock().await;
+        if let Some(connected) = client.as_ref().filter(|client| client.is_connected()) {
+            return Ok(connected.clone());
+        }
+
+        let connected = match &self.transport {
+            ConnectionTransport::Ws(secret) => {
+                let mut headers = HeaderMap::new();
+                headers.insert(AUTHORIZATION, secret_to_bearer_header(secret));
+                WsClientBuilder::new()
+                    .set_headers(headers)
+                    .connection_timeout(self.timeout)
+                    .request_timeout(self.timeout)
+                    .build(self.url.to_string())
+                    .await?
+            }
+            #[cfg(feature = "ipc")]
+            ConnectionTransport::Ipc => IpcClientBuilder::default()
+                .request_timeout(self.timeout)
+                .build(self.url.path())
+                .await
+                .map_err(|e| ClientError::Transport(e.into()))?,
+        };
+        let connected = Arc::new(connected);
+        *client = Some(connected.clone());
+        Ok(connected)
+    }
+
+    /// Sends the requests of a JSON-RPC request or batch body and returns the response body. The
+    /// requests are sent with the ids of the connection and answered with their own ids.
+    pub async fn forward(&self, body: &[u8]) -> Result<Vec<u8>, BoxError> {
+        let client = self.client().await?;
+        let response = match serde_json::from_slice::<serde_json::Value>(body)? {
+            serde_json::Value::Array(batch) => {
+                let responses = try_join_all(
+                    batch
+                        .iter()
+                        .map(|request| forward_connection_request(&client, request)),
+                )
+                .await?;
+                let responses: Vec<_> = responses.into_iter().flatten().collect();
+                // A batch of notifications only has no response
+                if responses.is_empty() {
+                    return Ok(vec![]);
+                }
+                serde_json::Value::Array(responses)
+            }
+            request => match forward_connection_request(&client, &request).await? {
+                Some(response) => response,
+                None => return Ok(vec![]),
+            },
+        };
+        Ok(serde_json::to_vec(&response)?)
+    }
+}
+
+/// Params forwarded as they were received.
+struct RawParams(Option<Box<RawValue>>);
+
+impl ToRpcParams for RawParams {
+    fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+        Ok(self.0)
+    }
+}
+
+/// Sends a request over the connection, returns its response or `None` for a notification.
+async fn forward_connection_request(
+    client: &PersistentClient,
+    request: &serde_json::Value,
+) -> Result<Option<serde_json::Value>, ClientError> {
+    let id = request.get("id").cloned();
+    let Some(method) = request.get("method").and_then(serde_json::Value::as_str) else {
+        return Ok(Some(serde_json::json!({
+            "jsonrpc": "2.0",
+            "error": ErrorObject::from(ErrorCode::InvalidRequest),
+            "id": id.unwrap_or_default(),
+        })));
+    };
+    let params = match request.get("params") {
+        None | Some(serde_json::Value::Null) => RawParams(None),
+        Some(params) => RawParams(Some(serde_json::value::to_raw_value(params)?)),
+    };
+
+    let Some(id) = id else {
+        client.notification(method, params).await?;
+        return Ok(None);
+    };
+    let response = match client.request::<serde_json::Value, _>(method, params).await {
+        Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(ClientError::Call(error)) => {
+            serde_json::json!({ "jsonrpc": "2.0", "error": error, "id": id })
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(Some(response))
+}
+
+/// Client of the proxy layer, forwarding the requests over HTTP or over a WebSocket or IPC
+/// connection kept open for each url.
+#[derive(Clone, Debug)]
+pub struct UpstreamClient {
+    http: Client<HttpsConnector<HttpConnector>, HttpBody>,
+    connections: Arc<Mutex<HashMap<Uri, Arc<Connection>>>>,
+}
+
+impl Default for UpstreamClient {
+    fn default() -> Self {
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .expect("no native root CA certificates found")
+            .https_or_http()
+            .enable_http1()
+            .enable_http2()
+            .build();
+
+        Self {
+            http: Client::builder(TokioExecutor::new()).build(connector),
+            connections: Default::default(),
+        }
+    }
+}
+
+impl UpstreamClient {
+    /// Sends a request to `uri` authenticated with `secret`, returns the head and the body of
+    /// the response.
+    pub async fn send(
+        &self,
+        mut req: HttpRequest<HttpBody>,
+        uri: &Uri,
+        secret: JwtSecret,
+    ) -> Result<(http::response::Parts, Vec<u8>), BoxError> {
+        if let Some(connection) = Connection::new(uri, secret, FORWARD_TIMEOUT) {
+            let connection = self
+                .connections
+                .lock()
+                .await
+                .entry(uri.clone())
+                .or_insert_with(|| Arc::new(connection))
+                .clone();
+            let (parts, body) = req.into_parts();
+            let (body_bytes, _) = http_helpers::read_body(&parts.headers, body, u32::MAX).await?;
+            let response_body = connection.forward(&body_bytes).await?;
+            let (parts, ()) = http::Response::new(()).into_parts();
+            return Ok((parts, response_body));
+        }
+
+        *req.uri_mut() = uri.clone();
+        req.headers_mut()
+            .insert(AUTHORIZATION, secret_to_bearer_header(&secret));
+        let (parts, body) = self.http.request(req).await?.into_parts();
+        let (body_bytes, _) = http_helpers::read_body(&parts.headers, body, u32::MAX).await?;
+        Ok((parts, body_bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::server::Server;
+    use jsonrpsee::RpcModule;
+    use serde_json::json;
+
+    fn module() -> eyre::Result<RpcModule<()>> {
+        let mut module = RpcModule::new(());
+        module.register_method("greet_melkor", |_, _, _| "You are the dark lord")?;
+        Ok(module)
+    }
+
+    #[tokio::test]
+    async fn test_ws_forward() -> eyre::Result<()> {
+        let server = Server::builder().build("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        let handle = server.start(module()?);
+
+        let url = format!("ws://{addr}").parse()?;
+        let connection = Connection::new(&url, JwtSecret::random(), Duration::from_secs(1));
+        assert_forward(connection.unwrap()).await?;
+
+        handle.stop()?;
+        Ok(())
+    }
+
+    #[cfg(feature = "ipc")]
+    #[tokio::test]
+    async fn test_ipc_forward() -> eyre::Result<()> {
+        let path = std::env::temp_dir().join(format!("rollup-boost-{}.ipc", std::process::id()));
+        let handle = reth_ipc::server::Builder::default()
+            .build(path.to_string_lossy().to_string())
+            .start(module()?)
+            .await?;
+
+        let url = path.to_string_lossy().parse()?;
+        assert!(is_ipc_path(&url));
+        let connection = Connection::new(&url, JwtSecret::random(), Duration::from_secs(1));
+        assert_forward(connection.unwrap()).await?;
+
+        handle.stop()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_connection_transport() -> eyre::Result<()> {
+        let secret = JwtSecret::random();
+        let timeout = Duration::from_secs(1);
+        assert!(Connection::new(&"http://127.0.0.1:8551".parse()?, secret, timeout).is_none());
+        assert!(Connection::new(&"127.0.0.1:8551".parse()?, secret, timeout).is_none());
+        assert!(Connection::new(&"wss://127.0.0.1:8551".parse()?, secret, timeout).is_some());
+        #[cfg(feature = "ipc")]
+        assert!(Connection::new(&"/tmp/geth.ipc".parse()?, secret, timeout).is_some());
+        Ok(())
+    }
+
+    async fn assert_forward(connection: Connection) -> eyre::Result<()> {
+        let response = connection
+            .forward(br#"{"jsonrpc":"2.0","method":"greet_melkor","params":[],"id":"a"}"#)
+            .await?;
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&response)?,
+            json!({ "jsonrpc": "2.0", "result": "You are the dark lord", "id": "a" })
+        );
+
+        // the responses keep the ids of the batch, the notifications are not answered
+        let response = connection
+            .forward(
+                br#"[
+                    {"jsonrpc":"2.0","method":"greet_melkor","id":7},
+                    {"jsonrpc":"2.0","method":"greet_melkor"},
+                    {"jsonrpc":"2.0","method":"non_existent_method","id":8}
+                ]"#,
+            )
+            .await?;
+        let response: Vec<serde_json::Value> = serde_json::from_slice(&response)?;
+        assert_eq!(response.len(), 2);
+        assert_eq!(response[0]["id"], 7);
+        assert_eq!(response[0]["result"], "You are the dark lord");
+        assert_eq!(response[1]["id"], 8);
+        assert_eq!(
+            response[1]["error"]["code"],
+            ErrorCode::MethodNotFound.code()
+        );
+
+        let response = connection
+            .forward(br#"{"jsonrpc":"2.0","method":"greet_melkor"}"#)
+            .await?;
+        assert!(response.is_empty());
+        Ok(())
+    }
+}
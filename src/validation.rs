@@ -0,0 +1,227 @@
+use crate::payload::OpExecutionPayloadEnvelope;
+use alloy_primitives::{Address, B256};
+use op_alloy_rpc_types_engine::OpPayloadAttributes;
+use thiserror::Error;
+
+/// Version byte of the Holocene `extraData` encoding of the EIP-1559 parameters
+const HOLOCENE_EXTRA_DATA_VERSION: u8 = 0;
+
+/// Mismatch between a builder payload and the attributes of the `engine_forkchoiceUpdated` call
+/// that started the block.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PayloadValidationError {
+    #[error("parent hash {got} does not match {expected}")]
+    ParentHash { expected: B256, got: B256 },
+    #[error("timestamp {got} does not match {expected}")]
+    Timestamp { expected: u64, got: u64 },
+    #[error("prev_randao {got} does not match {expected}")]
+    PrevRandao { expected: B256, got: B256 },
+    #[error("fee recipient {got} does not match {expected}")]
+    FeeRecipient { expected: Address, got: Address },
+    #[error("withdrawals do not match the payload attributes")]
+    Withdrawals,
+    #[error("gas limit {got} does not match {expected}")]
+    GasLimit { expected: u64, got: u64 },
+    #[error("payload does not start with the forced transactions of the payload attributes")]
+    ForcedTransactions,
+    #[error("extra data does not encode the eip-1559 parameters of the payload attributes")]
+    Eip1559Params,
+}
+
+/// Checks that a builder payload was built on the requested parent with the payload attributes.
+pub fn validate_payload_attributes(
+    payload: &OpExecutionPayloadEnvelope,
+    parent_hash: B256,
+    attributes: &OpPayloadAttributes,
+) -> Result<(), PayloadValidationError> {
+    let execution_payload = payload.execution_payload();
+    let payload_v1 = execution_payload.as_v1();
+    let payload_attributes = &attributes.payload_attributes;
+
+    if payload_v1.parent_hash != parent_hash {
+        return Err(PayloadValidationError::ParentHash {
+            expected: parent_hash,
+            got: payload_v1.parent_hash,
+        });
+    }
+    if payload_v1.timestamp != payload_attributes.timestamp {
+        return Err(PayloadValidationError::Timestamp {
+            expected: payload_attributes.timestamp,
+            got: payload_v1.timestamp,
+        });
+    }
+    if payload_v1.prev_randao != payload_attributes.prev_randao {
+        return Err(PayloadValidationError::PrevRandao {
+            expected: payload_attributes.prev_randao,
+            got: payload_v1.prev_randao,
+        });
+    }
+    if payload_v1.fee_recipient != payload_attributes.suggested_fee_recipient {
+        return Err(PayloadValidationError::FeeRecipient {
+            expected: payload_attributes.suggested_fee_recipient,
+            got: payload_v1.fee_recipient,
+        });
+    }
+    if execution_payload.withdrawals() != payload_attributes.withdrawals.as_ref() {
+        return Err(PayloadValidationError::Withdrawals);
+    }
+    if let Some(gas_limit) = attributes.gas_limit {
+        if payload_v1.gas_limit != gas_limit {
+            return Err(PayloadValidationError::GasLimit {
+                expected: gas_limit,
+                got: payload_v1.gas_limit,
+            });
+        }
+    }
+    if let Some(transactions) = &attributes.transactions {
+        if !payload_v1.transactions.starts_with(transactions) {
+            return Err(PayloadValidationError::ForcedTransactions);
+        }
+    }
+    if let Some(eip_1559_params) = attributes.eip_1559_params {
+        // Holocene encodes the parameters as a version byte followed by the 8 parameter bytes.
+        // Zero parameters select the chain defaults, so only the encoding is checked.
+        let extra_data = &payload_v1.extra_data;
+        let valid = extra_data.len() == 9
+            && extra_data[0] == HOLOCENE_EXTRA_DATA_VERSION
+            && (eip_1559_params.is_zero() || extra_data[1..] == eip_1559_params[..]);
+        if !valid {
+            return Err(PayloadValidationError::Eip1559Params);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{b64, bytes, Bytes, U256};
+    use alloy_rpc_types_engine::{
+        BlobsBundleV1, ExecutionPayloadV1, ExecutionPayloadV2, ExecutionPayloadV3,
+        PayloadAttributes,
+    };
+    use op_alloy_rpc_types_engine::OpExecutionPayloadEnvelopeV3;
+
+    const PARENT_HASH: B256 = B256::repeat_byte(1);
+    const DEPOSIT: Bytes = bytes!("7ef8f8a0");
+
+    fn attributes() -> OpPayloadAttributes {
+        OpPayloadAttributes {
+            payload_attributes: PayloadAttributes {
+                timestamp: 1000,
+                prev_randao: B256::repeat_byte(2),
+                suggested_fee_recipient: Address::repeat_byte(3),
+                withdrawals: Some(vec![]),
+                parent_beacon_block_root: Some(B256::ZERO),
+                target_blobs_per_block: None,
+                max_blobs_per_block: None,
+            },
+            transactions: Some(vec![DEPOSIT]),
+            no_tx_pool: None,
+            gas_limit: Some(30_000_000),
+            eip_1559_params: Some(b64!("000000fa00000006")),
+        }
+    }
+
+    fn payload(update: impl FnOnce(&mut ExecutionPayloadV1)) -> OpExecutionPayloadEnvelope {
+        let mut payload_inner = ExecutionPayloadV1 {
+            parent_hash: PARENT_HASH,
+            fee_recipient: Address::repeat_byte(3),
+            state_root: B256::ZERO,
+            receipts_root: B256::ZERO,
+            logs_bloom: Default::default(),
+            prev_randao: B256::repeat_byte(2),
+            block_number: 1,
+            gas_limit: 30_000_000,
+            gas_used: 0,
+            timestamp: 1000,
+            extra_data: bytes!("00000000fa00000006"),
+            base_fee_per_gas: U256::from(1),
+            block_hash: B256::ZERO,
+            transactions: vec![DEPOSIT, bytes!("02f8")],
+        };
+        update(&mut payload_inner);
+        OpExecutionPayloadEnvelope::V3(OpExecutionPayloadEnvelopeV3 {
+            execution_payload: ExecutionPayloadV3 {
+                payload_inner: ExecutionPayloadV2 {
+                    payload_inner,
+                    withdrawals: vec![],
+                },
+                blob_gas_used: 0,
+                excess_blob_gas: 0,
+            },
+            block_value: U256::ZERO,
+            blobs_bundle: BlobsBundleV1 {
+                commitments: vec![],
+                proofs: vec![],
+                blobs: vec![],
+            },
+            should_override_builder: false,
+            parent_beacon_block_root: B256::ZERO,
+        })
+    }
+
+    #[test]
+    fn test_valid_payload() {
+        assert_eq!(
+            validate_payload_attributes(&payload(|_| {}), PARENT_HASH, &attributes()),
+            Ok(())
+        );
+
+        // zero eip-1559 parameters select the chain defaults
+        let mut attributes = attributes();
+        attributes.eip_1559_params = Some(Default::default());
+        assert_eq!(
+            validate_payload_attributes(&payload(|_| {}), PARENT_HASH, &attributes),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_invalid_payload() {
+        let attributes = attributes();
+        let validate = |update: fn(&mut ExecutionPayloadV1)| {
+            validate_payload_attributes(&payload(update), PARENT_HASH, &attributes)
+        };
+
+        assert!(matches!(
+            validate(|p| p.parent_hash = B256::ZERO),
+            Err(PayloadValidationError::ParentHash { .. })
+        ));
+        assert!(matches!(
+            validate(|p| p.timestamp = 1001),
+            Err(PayloadValidationError::Timestamp { .. })
+        ));
+        assert!(matches!(
+            validate(|p| p.prev_randao = B256::ZERO),
+            Err(PayloadValidationError::PrevRandao { .. })
+        ));
+        assert!(matches!(
+            validate(|p| p.fee_recipient = Address::ZERO),
+            Err(PayloadValidationError::FeeRecipient { .. })
+        ));
+        assert!(matches!(
+            validate(|p| p.gas_limit = 1),
+            Err(PayloadValidationError::GasLimit { .. })
+        ));
+        assert_eq!(
+            validate(|p| p.transactions = vec![bytes!("02f8"), DEPOSIT]),
+            Err(PayloadValidationError::ForcedTransactions)
+        );
+        assert_eq!(
+            validate(|p| p.extra_data = bytes!("00000000fa00000008")),
+            Err(PayloadValidationError::Eip1559Params)
+        );
+        assert_eq!(
+            validate(|p| p.extra_data = Bytes::new()),
+            Err(PayloadValidationError::Eip1559Params)
+        );
+
+        let mut attributes = attributes.clone();
+        attributes.payload_attributes.withdrawals = None;
+        assert_eq!(
+            validate_payload_attributes(&payload(|_| {}), PARENT_HASH, &attributes),
+            Err(PayloadValidationError::Withdrawals)
+        );
+    }
+}
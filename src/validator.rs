@@ -0,0 +1,261 @@
+use crate::client::ExecutionClient;
+use crate::metrics::{ClientMetrics, ServerMetrics};
+use crate::payload::NewPayload;
+use crate::server::PayloadSource;
+use alloy_rpc_types_engine::{JwtSecret, PayloadStatus};
+use clap::{arg, Parser};
+use eyre::bail;
+use futures::stream::{FuturesUnordered, StreamExt};
+use http::Uri;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// How the statuses returned by several validators are combined.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorMode {
+    // Use the first validator that returns VALID or INVALID
+    FirstResponse,
+    // Wait until `--validator-quorum` validators return the same status
+    Quorum,
+}
+
+/// Settings of the execution engines validating the builder payloads.
+#[derive(Parser, Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorArgs {
+    /// Auth server addresses of the execution engines validating the builder payloads with
+    /// `engine_newPayload`, instead of the L2 execution engine. The validators do not receive the
+    /// boost sync calls, they must be kept at the head of the chain by their own op-node
+    #[arg(long, env, value_delimiter = ',')]
+    pub validator_url: Vec<Uri>,
+
+    /// Hex encoded JWT secret of the validators
+    #[arg(long, env, value_name = "HEX")]
+    pub validator_jwt_token: Option<JwtSecret>,
+
+    /// Path to the JWT secret of the validators
+    #[arg(long, env, value_name = "PATH")]
+    pub validator_jwt_path: Option<PathBuf>,
+
+    /// Timeout for http calls to the validators in milliseconds
+    #[arg(long, env, default_value_t = 1000)]
+    pub validator_timeout: u64,
+
+    /// How the responses of several validators are combined
+    #[arg(long, env, default_value = "first-response")]
+    pub validator_mode: ValidatorMode,
+
+    /// Number of validators that must return the same status in quorum mode, defaults to a
+    /// majority of the validators
+    #[arg(long, env)]
+    pub validator_quorum: Option<usize>,
+}
+
+/// Pool of execution engines validating the builder payloads, so the speculative blocks do not
+/// load the L2 execution engine.
+pub struct ValidatorPool {
+    validators: Vec<ExecutionClient>,
+    mode: ValidatorMode,
+    quorum: usize,
+    metrics: Option<Arc<ServerMetrics>>,
+}
+
+impl ValidatorPool {
+    /// Creates the pool, `None` if no validator is configured.
+    pub fn new(
+        args: ValidatorArgs,
+        metrics: Option<Arc<ServerMetrics>>,
+    ) -> eyre::Result<Option<Self>> {
+        if args.validator_url.is_empty() {
+            return Ok(None);
+        }
+
+        let jwt_secret = if let Some(secret) = args.validator_jwt_token {
+            secret
+        } else if let Some(path) = args.validator_jwt_path.as_ref() {
+            JwtSecret::from_file(path)?
+        } else {
+            bail!("Missing Validator JWT secret");
+        };
+
+        let quorum = args
+            .validator_quorum
+            .unwrap_or(args.validator_url.len() / 2 + 1);
+        if quorum == 0 || quorum > args.validator_url.len() {
+            bail!(
+                "Validator quorum {} must be between 1 and the number of validators {}",
+                quorum,
+                args.validator_url.len()
+            );
+        }
+
+        let client_metrics = metrics
+            .as_ref()
+            .map(|_| Arc::new(ClientMetrics::with_target("validator".to_string())));
+        let validators = args
+            .validator_url
+            .into_iter()
+            .map(|url| {
+                ExecutionClient::new(
+                    url,
+                    jwt_secret,
+                    args.validator_timeout,
+                    client_metrics.clone(),
+                    // Only tags the payloads of engine_getPayload, never sent to the validators
+                    PayloadSource::L2,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        info!(
+            message = "validating builder payloads on dedicated validators",
+            "validators" = validators.len(),
+            "mode" = ?args.validator_mode,
+            "quorum" = quorum
+        );
+        Ok(Some(Self {
+            validators,
+            mode: args.validator_mode,
+            quorum,
+            metrics,
+        }))
+    }
+
+    /// Validates a payload on the validators, `None` if they did not reach a VALID or INVALID
+    /// status, for example because they are unreachable or syncing.
+    pub async fn new_payload(&self, new_payload: NewPayload) -> Option<PayloadStatus> {
+        let block_hash = new_payload.block_hash();
+        let mut responses: FuturesUnordered<_> = self
+            .validators
+            .iter()
+            .map(|validator| {
+                let new_payload = new_payload.clone();
+                async move { (validator, validator.new_payload(new_payload).await) }
+            })
+            .collect();
+
+        let mut votes = Votes::new(self.mode, self.quorum);
+        while let Some((validator, result)) = responses.next().await {
+            match result {
+                Ok(payload_status) => {
+                    if payload_status.status.is_syncing() {
+                        warn!(message = "validator is syncing, it must be kept at the head by its own op-node", "url" = ?validator.auth_rpc, "block_hash" = %block_hash);
+                    } else {
+                        info!(message = "received payload status from validator", "url" = ?validator.auth_rpc, "payload_status" = %payload_status.status, "block_hash" = %block_hash);
+                    }
+                    if let Some(metrics) = &self.metrics {
+                        metrics.increment_validator_payload_status(
+                            validator.auth_rpc.to_string(),
+                            payload_status.status.to_string(),
+                        );
+                    }
+                    if let Some(payload_status) = votes.add(payload_status) {
+                        return Some(payload_status);
+                    }
+                }
+                Err(e) => {
+                    warn!(message = "error calling new_payload to validator", "url" = ?validator.auth_rpc, "error" = %e, "block_hash" = %block_hash);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Combines the statuses returned by the validators.
+struct Votes {
+    mode: ValidatorMode,
+    quorum: usize,
+    valid: usize,
+    invalid: usize,
+}
+
+impl Votes {
+    fn new(mode: ValidatorMode, quorum: usize) -> Self {
+        Self {
+            mode,
+            quorum,
+            valid: 0,
+            invalid: 0,
+        }
+    }
+
+    /// Adds the status of a validator, returns the status of the pool once it is decided.
+    fn add(&mut self, payload_status: PayloadStatus) -> Option<PayloadStatus> {
+        // A syncing validator cannot tell whether the payload is valid
+        let votes = if payload_status.is_valid() {
+            &mut self.valid
+        } else if payload_status.is_invalid() {
+            &mut self.invalid
+        } else {
+            return None;
+        };
+        *votes += 1;
+
+        match self.mode {
+            ValidatorMode::FirstResponse => Some(payload_status),
+            ValidatorMode::Quorum => (*votes >= self.quorum).then_some(payload_status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_rpc_types_engine::PayloadStatusEnum;
+
+    fn status(status: PayloadStatusEnum) -> PayloadStatus {
+        PayloadStatus::from_status(status)
+    }
+
+    fn invalid() -> PayloadStatus {
+        status(PayloadStatusEnum::Invalid {
+            validation_error: "invalid block".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_first_response() {
+        let mut votes = Votes::new(ValidatorMode::FirstResponse, 2);
+        assert_eq!(votes.add(status(PayloadStatusEnum::Syncing)), None);
+        assert_eq!(votes.add(invalid()), Some(invalid()));
+    }
+
+    #[test]
+    fn test_quorum() {
+        let mut votes = Votes::new(ValidatorMode::Quorum, 2);
+        assert_eq!(votes.add(status(PayloadStatusEnum::Valid)), None);
+        assert_eq!(votes.add(invalid()), None);
+        assert_eq!(votes.add(status(PayloadStatusEnum::Syncing)), None);
+        assert_eq!(
+            votes.add(status(PayloadStatusEnum::Valid)),
+            Some(status(PayloadStatusEnum::Valid))
+        );
+    }
+
+    #[test]
+    fn test_validator_pool_args() {
+        let args = |urls: &[&str], quorum| ValidatorArgs {
+            validator_url: urls.iter().map(|url| url.parse().unwrap()).collect(),
+            validator_jwt_token: Some(JwtSecret::random()),
+            validator_jwt_path: None,
+            validator_timeout: 1000,
+            validator_mode: ValidatorMode::Quorum,
+            validator_quorum: quorum,
+        };
+
+        assert!(ValidatorPool::new(args(&[], None), None).unwrap().is_none());
+
+        let pool = ValidatorPool::new(
+            args(&["http://a:8551", "http://b:8551", "http://c:8551"], None),
+            None,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(pool.quorum, 2);
+
+        assert!(ValidatorPool::new(args(&["http://a:8551"], Some(2)), None).is_err());
+    }
+}